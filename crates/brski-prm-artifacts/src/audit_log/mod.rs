@@ -0,0 +1,127 @@
+//! The MASA audit log, see RFC 8995 5.8
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use ietf_voucher::assertion::Assertion;
+use serde::{Deserialize, Serialize};
+
+pub const AUDIT_LOG_VERSION: &str = "1";
+
+/// Every voucher a MASA issued for a pledge
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditLog {
+    pub version: String,
+    pub events: Vec<AuditLogEvent>,
+    /// How many events the MASA left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<Truncation>,
+}
+
+impl AuditLog {
+    pub fn new(events: Vec<AuditLogEvent>) -> Self {
+        Self {
+            version: AUDIT_LOG_VERSION.to_string(),
+            events,
+            truncation: None,
+        }
+    }
+}
+
+/// A voucher issuance
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditLogEvent {
+    pub date: DateTime<Utc>,
+    /// The domain the voucher was issued to, see [domain_id]
+    #[serde(rename = "domainID")]
+    pub domain_id: String,
+    /// Base64 encoded nonce, `None` for nonceless vouchers
+    pub nonce: Option<String>,
+    pub assertion: Option<Assertion>,
+    /// Number of domainID entries that were left out
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "count")]
+    pub truncated: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Truncation {
+    #[serde(rename = "nonced duplicates", default, with = "count")]
+    pub nonced_duplicates: Option<u64>,
+    #[serde(rename = "nonceless duplicates", default, with = "count")]
+    pub nonceless_duplicates: Option<u64>,
+    #[serde(default, with = "count")]
+    pub arbitrary: Option<u64>,
+}
+
+/// The domainID of the domain owning `pinned-domain-cert`, the base64 encoded SubjectKeyIdentifier of the certificate
+pub fn domain_id(subject_key_identifier: &[u8]) -> String {
+    STANDARD.encode(subject_key_identifier)
+}
+
+/// RFC 8995 encodes counts as strings
+mod count {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_str(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Count {
+            Number(u64),
+            String(String),
+        }
+
+        match Option::<Count>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(Count::Number(count)) => Ok(Some(count)),
+            Some(Count::String(count)) => count.parse().map(Some).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_the_rfc_example() {
+        let log: AuditLog = serde_json::from_str(
+            r#"{
+              "version":"1",
+              "events":[
+                {
+                 "date":"2019-05-15T17:25:55.644-04:00",
+                 "domainID":"BduJhdHPpfhQLyponf48JzXSGZ8=",
+                 "nonce":"VOUFT-FYe3hm9ZUnyoAAAAA",
+                 "assertion":"proximity",
+                 "truncated":"0"
+                },
+                {
+                 "date":"2017-05-15T17:25:55.644-04:00",
+                 "domainID":"BduJhdHPpfhQLyponf48JzXSGZ8=",
+                 "nonce":null,
+                 "assertion":"logged"
+                }
+              ],
+              "truncation": {
+                "nonced duplicates": "0",
+                "nonceless duplicates": "1",
+                "arbitrary": "2"
+              }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(log.events.len(), 2);
+        assert_eq!(log.events[0].assertion, Some(Assertion::Proximity));
+        assert_eq!(log.events[0].truncated, Some(0));
+        assert_eq!(log.events[1].nonce, None);
+        assert_eq!(log.truncation.unwrap().arbitrary, Some(2));
+    }
+}
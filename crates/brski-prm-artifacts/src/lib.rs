@@ -1,3 +1,4 @@
+pub mod audit_log;
 pub mod cacerts;
 pub mod error;
 pub mod issued_voucher;
@@ -7,5 +8,7 @@ pub mod rer;
 pub mod rvr;
 pub mod status;
 pub mod token_type;
+pub mod transparency_log;
 pub use ietf_voucher;
 pub mod pledge_info;
+pub mod protocol_version;
@@ -4,10 +4,18 @@ use std::vec;
 use ietf_voucher::pki::X509Req;
 use serde::{Deserialize, Serialize};
 
+use crate::{error::BRSKIPRMError, protocol_version::ProtocolVersion};
+
+#[cfg(feature = "json")]
+use serde_with::base64::Base64;
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(try_from = "PledgeEnrollRequestWire", into = "PledgeEnrollRequestWire")]
 pub struct PledgeEnrollRequest {
-    #[serde(rename = "ietf-ztp-types")]
     pub csr: ResponsePayloadInner,
+
+    /// The revision the CSR is encoded for. See [ProtocolVersion].
+    pub protocol_version: ProtocolVersion,
 }
 
 #[cfg_attr(feature = "json", serde_with::serde_as)]
@@ -18,10 +26,63 @@ pub struct ResponsePayloadInner {
     pub p10_csr: X509Req,
 }
 
+/// The encoded form of a [PledgeEnrollRequest]. The drafts wrap the CSR in `ietf-ztp-types`, the RFC does not.
+#[cfg_attr(feature = "json", serde_with::serde_as)]
+#[derive(Serialize, Deserialize)]
+struct PledgeEnrollRequestWire {
+    #[serde(rename = "ietf-ztp-types", skip_serializing_if = "Option::is_none")]
+    wrapped: Option<ResponsePayloadInner>,
+
+    #[serde(rename = "p10-csr", skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
+    p10_csr: Option<X509Req>,
+}
+
+impl From<PledgeEnrollRequest> for PledgeEnrollRequestWire {
+    fn from(value: PledgeEnrollRequest) -> Self {
+        match value.protocol_version {
+            ProtocolVersion::Draft => Self {
+                wrapped: Some(value.csr),
+                p10_csr: None,
+            },
+            ProtocolVersion::Rfc => Self {
+                wrapped: None,
+                p10_csr: Some(value.csr.p10_csr),
+            },
+        }
+    }
+}
+
+impl TryFrom<PledgeEnrollRequestWire> for PledgeEnrollRequest {
+    type Error = BRSKIPRMError;
+
+    fn try_from(value: PledgeEnrollRequestWire) -> Result<Self, Self::Error> {
+        match (value.wrapped, value.p10_csr) {
+            (Some(csr), None) => Ok(Self {
+                csr,
+                protocol_version: ProtocolVersion::Draft,
+            }),
+            (None, Some(p10_csr)) => Ok(Self {
+                csr: ResponsePayloadInner { p10_csr },
+                protocol_version: ProtocolVersion::Rfc,
+            }),
+            (None, None) => Err(BRSKIPRMError::Malformed("Missing CSR in PER".to_string())),
+            (Some(_), Some(_)) => Err(BRSKIPRMError::Malformed(
+                "CSR present in more than one revision in PER".to_string(),
+            )),
+        }
+    }
+}
+
 impl PledgeEnrollRequest {
     pub fn new(csr: X509Req) -> Self {
+        Self::with_protocol_version(csr, ProtocolVersion::default())
+    }
+
+    pub fn with_protocol_version(csr: X509Req, protocol_version: ProtocolVersion) -> Self {
         Self {
             csr: ResponsePayloadInner { p10_csr: csr },
+            protocol_version,
         }
     }
 }
@@ -1,12 +1,18 @@
 use serde::{Deserialize, Serialize};
 
-use crate::token_type::{DataInterchangeFormat, PlainTokenType, VoucherTokenType};
+use crate::{
+    protocol_version::ProtocolVersion,
+    token_type::{DataInterchangeFormat, PlainTokenType, VoucherTokenType},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PledgeInfo {
     pub data_interchance_format: DataInterchangeFormat,
     pub supported_token_type: PlainTokenType,
     pub supported_voucher_type: VoucherTokenType,
+    /// The BRSKI-PRM revision the pledge was built against. Pledges predating this member speak the drafts.
+    #[serde(default)]
+    pub protocol_version: ProtocolVersion,
 }
 
 impl PledgeInfo {
@@ -15,6 +21,7 @@ impl PledgeInfo {
             data_interchance_format: DataInterchangeFormat::JSON,
             supported_token_type: PlainTokenType::JOSE,
             supported_voucher_type: VoucherTokenType::JWS,
+            protocol_version: ProtocolVersion::default(),
         }
     }
 
@@ -23,6 +30,7 @@ impl PledgeInfo {
             data_interchance_format: DataInterchangeFormat::CBOR,
             supported_token_type: PlainTokenType::COSE,
             supported_voucher_type: VoucherTokenType::COSE,
+            protocol_version: ProtocolVersion::default(),
         }
     }
 }
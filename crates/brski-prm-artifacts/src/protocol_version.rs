@@ -0,0 +1 @@
+pub use ietf_voucher::protocol_version::ProtocolVersion;
@@ -8,31 +8,103 @@ use ietf_voucher::agent_signed_data::AgentSignedData;
 use ietf_voucher::pki::X509;
 use signeable_payload::signeable::{raw_signed::RawSigned, signed::Signed};
 
-use crate::error::BRSKIPRMError;
+use crate::{error::BRSKIPRMError, protocol_version::ProtocolVersion};
 
 #[cfg(feature = "json")]
-use serde_with::serde_as;
+use serde_with::{base64::Base64, serde_as};
 
 // A pledge voucher request. You can not directly serialize this struct, you must first convert it to a RawPVR.
-#[cfg_attr(feature = "json", serde_as)]
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(
+    try_from = "VoucherRequestTriggerWire",
+    into = "VoucherRequestTriggerWire"
+)]
+pub struct VoucherRequestTrigger {
+    /// registrar EE TLS certificate
+    pub agent_provided_proximity_registrar_cert: X509,
+
+    pub agent_signed_data: RawSigned<AgentSignedData>,
+
+    /// The revision the member names are taken from. See [ProtocolVersion].
+    pub protocol_version: ProtocolVersion,
+}
+
+/// The encoded form of a [VoucherRequestTrigger]. Exactly one of the registrar certificate members is set.
+#[cfg_attr(feature = "json", serde_as)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
+struct VoucherRequestTriggerWire {
+    /// base-64 encoded registrar EE TLS certificate, named as in the earlier drafts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
+    agent_signed_proximity_cert: Option<X509>,
 
-pub struct VoucherRequestTrigger {
     /// base-64 encoded registrar EE TLS certificate
-    #[cfg_attr(feature = "json", serde_as(as = "Base64"))]
-    pub agent_signed_proximity_cert: X509,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
+    agent_provided_proximity_registrar_cert: Option<X509>,
 
     #[cfg_attr(feature = "json", serde_as(as = "Base64"))]
-    pub agent_signed_data: RawSigned<AgentSignedData>,
+    agent_signed_data: RawSigned<AgentSignedData>,
+}
+
+impl From<VoucherRequestTrigger> for VoucherRequestTriggerWire {
+    fn from(value: VoucherRequestTrigger) -> Self {
+        let cert = Some(value.agent_provided_proximity_registrar_cert);
+        match value.protocol_version {
+            ProtocolVersion::Draft => Self {
+                agent_signed_proximity_cert: cert,
+                agent_provided_proximity_registrar_cert: None,
+                agent_signed_data: value.agent_signed_data,
+            },
+            ProtocolVersion::Rfc => Self {
+                agent_signed_proximity_cert: None,
+                agent_provided_proximity_registrar_cert: cert,
+                agent_signed_data: value.agent_signed_data,
+            },
+        }
+    }
+}
+
+impl TryFrom<VoucherRequestTriggerWire> for VoucherRequestTrigger {
+    type Error = BRSKIPRMError;
+
+    fn try_from(value: VoucherRequestTriggerWire) -> Result<Self, Self::Error> {
+        let (agent_provided_proximity_registrar_cert, protocol_version) = match (
+            value.agent_signed_proximity_cert,
+            value.agent_provided_proximity_registrar_cert,
+        ) {
+            (Some(cert), None) => (cert, ProtocolVersion::Draft),
+            (None, Some(cert)) => (cert, ProtocolVersion::Rfc),
+            (None, None) => {
+                return Err(BRSKIPRMError::Malformed(
+                    "Missing registrar certificate in PVR Trigger".to_string(),
+                ))
+            }
+            (Some(_), Some(_)) => {
+                return Err(BRSKIPRMError::Malformed(
+                    "Registrar certificate present in more than one revision in PVR Trigger"
+                        .to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            agent_provided_proximity_registrar_cert,
+            agent_signed_data: value.agent_signed_data,
+            protocol_version,
+        })
+    }
 }
 
 impl fmt::Display for VoucherRequestTrigger {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Trigger: agent_signed_proximity_cert: {}, agent_signed_data: {:?}",
-            self.agent_signed_proximity_cert, self.agent_signed_data
+            "Trigger ({}): agent_provided_proximity_registrar_cert: {}, agent_signed_data: {:?}",
+            self.protocol_version,
+            self.agent_provided_proximity_registrar_cert,
+            self.agent_signed_data
         )
     }
 }
@@ -96,6 +96,14 @@ impl VoucherTokenType {
         }
     }
 
+    /// Extension of vouchers stored as files, e.g. pre-issued vouchers
+    pub fn file_extension(&self) -> &str {
+        match self {
+            VoucherTokenType::JWS => "jws",
+            VoucherTokenType::COSE => "cose",
+        }
+    }
+
     pub fn from_content_type(content_type: &str) -> Self {
         match content_type {
             JWS_VOUCHER => VoucherTokenType::JWS,
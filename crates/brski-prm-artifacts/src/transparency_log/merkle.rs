@@ -0,0 +1,361 @@
+//! Merkle tree hashes and proofs as defined in RFC 9162 2.1
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+/// Hash of a log entry, `SHA-256(0x00 || entry)`
+pub fn leaf_hash(entry: &[u8]) -> Hash {
+    hash(&[&[0x00], entry])
+}
+
+/// Hash of an interior node, `SHA-256(0x01 || left || right)`
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    hash(&[&[0x01], left, right])
+}
+
+fn hash(parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.finalize());
+    hash
+}
+
+/// The largest power of two smaller than `n`, `n` has to be greater than 1
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// The Merkle Tree Hash of the leaves
+pub fn root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => hash(&[]),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+        }
+    }
+}
+
+/// The audit path of leaf `index`, `None` if there is no such leaf
+pub fn inclusion_proof(index: usize, leaves: &[Hash]) -> Option<Vec<Hash>> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    Some(path(index, 0, leaves.len(), &|start, n| {
+        root(&leaves[start..start + n])
+    }))
+}
+
+/// Proof that the tree of the first `first` leaves is a prefix of the tree of all leaves,
+/// `None` if `first` is 0 or larger than the tree
+pub fn consistency_proof(first: usize, leaves: &[Hash]) -> Option<Vec<Hash>> {
+    if first == 0 || first > leaves.len() {
+        return None;
+    }
+
+    Some(subproof(first, 0, leaves.len(), true, &|start, n| {
+        root(&leaves[start..start + n])
+    }))
+}
+
+/// Hash of the `n` leaves from `start` on
+type SubtreeRoot<'a> = dyn Fn(usize, usize) -> Hash + 'a;
+
+/// PATH(index, D[start:start + n]) of RFC 9162 2.1.3.1
+fn path(index: usize, start: usize, n: usize, root: &SubtreeRoot) -> Vec<Hash> {
+    if n <= 1 {
+        return vec![];
+    }
+    let k = split(n);
+    let (mut proof, sibling) = if index < k {
+        (path(index, start, k, root), root(start + k, n - k))
+    } else {
+        (path(index - k, start + k, n - k, root), root(start, k))
+    };
+    proof.push(sibling);
+    proof
+}
+
+/// SUBPROOF(m, D[start:start + n], complete) of RFC 9162 2.1.4.1
+fn subproof(m: usize, start: usize, n: usize, complete: bool, root: &SubtreeRoot) -> Vec<Hash> {
+    if m == n {
+        return if complete {
+            vec![]
+        } else {
+            vec![root(start, n)]
+        };
+    }
+    let k = split(n);
+    let (mut proof, sibling) = if m <= k {
+        (
+            subproof(m, start, k, complete, root),
+            root(start + k, n - k),
+        )
+    } else {
+        (
+            subproof(m - k, start + k, n - k, false, root),
+            root(start, k),
+        )
+    };
+    proof.push(sibling);
+    proof
+}
+
+/// A Merkle tree that keeps the hash of every complete subtree, so appending a leaf, its root
+/// and proofs for any of its sizes take a logarithmic number of hashes instead of rehashing all leaves
+#[derive(Debug, Clone, Default)]
+pub struct MerkleTree {
+    /// The leaves, then the hashes of the complete subtrees of 2, 4, 8... leaves from left to right
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of leaves
+    pub fn size(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+
+    pub fn append(&mut self, leaf: Hash) {
+        let mut node = leaf;
+        for level in 0.. {
+            if self.levels.len() == level {
+                self.levels.push(vec![]);
+            }
+            let nodes = &mut self.levels[level];
+            nodes.push(node);
+            if nodes.len() % 2 == 1 {
+                break;
+            }
+            node = node_hash(&nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+        }
+    }
+
+    /// The Merkle Tree Hash of all leaves
+    pub fn root(&self) -> Hash {
+        self.subtree_root(0, self.size())
+    }
+
+    /// The audit path of leaf `index` in the tree of the first `tree_size` leaves,
+    /// `None` if there is no such leaf or tree
+    pub fn inclusion_proof(&self, index: usize, tree_size: usize) -> Option<Vec<Hash>> {
+        if index >= tree_size || tree_size > self.size() {
+            return None;
+        }
+
+        Some(path(index, 0, tree_size, &|start, n| {
+            self.subtree_root(start, n)
+        }))
+    }
+
+    /// Proof that the tree of the first `first` leaves is a prefix of the tree of the first `second` leaves,
+    /// `None` if `first` is 0 or larger than `second`, or `second` larger than the tree
+    pub fn consistency_proof(&self, first: usize, second: usize) -> Option<Vec<Hash>> {
+        if first == 0 || first > second || second > self.size() {
+            return None;
+        }
+
+        Some(subproof(first, 0, second, true, &|start, n| {
+            self.subtree_root(start, n)
+        }))
+    }
+
+    /// The Merkle Tree Hash of the `n` leaves from `start` on. As in every range the RFC 9162 recursion visits,
+    /// `start` is a multiple of the largest power of two not larger than `n`, so the left subtree is complete
+    fn subtree_root(&self, start: usize, n: usize) -> Hash {
+        match n {
+            0 => hash(&[]),
+            n if n.is_power_of_two() => {
+                let level = n.trailing_zeros();
+                self.levels[level as usize][start >> level]
+            }
+            n => {
+                let k = split(n);
+                node_hash(
+                    &self.subtree_root(start, k),
+                    &self.subtree_root(start + k, n - k),
+                )
+            }
+        }
+    }
+}
+
+/// Checks that `leaf` is entry `index` of the tree of `tree_size` leaves with hash `root`
+pub fn verify_inclusion(
+    leaf: &Hash,
+    index: u64,
+    tree_size: u64,
+    proof: &[Hash],
+    root: &Hash,
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    let (mut f_n, mut s_n) = (index, tree_size - 1);
+    let mut r = *leaf;
+    for p in proof {
+        if s_n == 0 {
+            return false;
+        }
+        if f_n & 1 == 1 || f_n == s_n {
+            r = node_hash(p, &r);
+            while f_n & 1 == 0 && f_n != 0 {
+                f_n >>= 1;
+                s_n >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    s_n == 0 && r == *root
+}
+
+/// Checks that the tree of `first` leaves with hash `first_root` is a prefix of the tree of `second` leaves with hash `second_root`
+pub fn verify_consistency(
+    first: u64,
+    second: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if first == 0 || first > second {
+        return false;
+    }
+    if first == second {
+        return proof.is_empty() && first_root == second_root;
+    }
+
+    let mut proof = proof.to_vec();
+    if first.is_power_of_two() {
+        proof.insert(0, *first_root);
+    }
+    let Some((start, rest)) = proof.split_first() else {
+        return false;
+    };
+
+    let (mut f_n, mut s_n) = (first - 1, second - 1);
+    while f_n & 1 == 1 {
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    let (mut f_r, mut s_r) = (*start, *start);
+    for c in rest {
+        if s_n == 0 {
+            return false;
+        }
+        if f_n & 1 == 1 || f_n == s_n {
+            f_r = node_hash(c, &f_r);
+            s_r = node_hash(c, &s_r);
+            while f_n & 1 == 0 && f_n != 0 {
+                f_n >>= 1;
+                s_n >>= 1;
+            }
+        } else {
+            s_r = node_hash(&s_r, c);
+        }
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    s_n == 0 && f_r == *first_root && s_r == *second_root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_proves_inclusion_and_consistency() {
+        let leaves: Vec<Hash> = (0u8..7).map(|entry| leaf_hash(&[entry])).collect();
+
+        for size in 1..=leaves.len() {
+            let tree = &leaves[..size];
+            let tree_root = root(tree);
+
+            for index in 0..size {
+                let proof = inclusion_proof(index, tree).unwrap();
+                assert!(verify_inclusion(
+                    &tree[index],
+                    index as u64,
+                    size as u64,
+                    &proof,
+                    &tree_root
+                ));
+                assert!(!verify_inclusion(
+                    &leaf_hash(b"forged"),
+                    index as u64,
+                    size as u64,
+                    &proof,
+                    &tree_root
+                ));
+            }
+
+            for first in 1..=size {
+                let proof = consistency_proof(first, tree).unwrap();
+                assert!(verify_consistency(
+                    first as u64,
+                    size as u64,
+                    &root(&leaves[..first]),
+                    &tree_root,
+                    &proof
+                ));
+            }
+        }
+
+        let mut rewritten = leaves.clone();
+        rewritten[2] = leaf_hash(b"rewritten");
+        let proof = consistency_proof(3, &leaves).unwrap();
+        assert!(!verify_consistency(
+            3,
+            7,
+            &root(&rewritten[..3]),
+            &root(&leaves),
+            &proof
+        ));
+        assert_eq!(inclusion_proof(7, &leaves), None);
+    }
+
+    #[test]
+    fn it_keeps_the_tree_while_appending() {
+        let leaves: Vec<Hash> = (0u8..13).map(|entry| leaf_hash(&[entry])).collect();
+        let mut tree = MerkleTree::new();
+        assert_eq!(tree.root(), root(&[]));
+
+        for (size, leaf) in (1..=leaves.len()).zip(&leaves) {
+            tree.append(*leaf);
+            assert_eq!(tree.size(), size);
+            assert_eq!(tree.root(), root(&leaves[..size]));
+        }
+
+        for size in 1..=leaves.len() {
+            for index in 0..size {
+                assert_eq!(
+                    tree.inclusion_proof(index, size),
+                    inclusion_proof(index, &leaves[..size])
+                );
+            }
+            for first in 1..=size {
+                assert_eq!(
+                    tree.consistency_proof(first, size),
+                    consistency_proof(first, &leaves[..size])
+                );
+            }
+        }
+        assert_eq!(tree.inclusion_proof(0, 14), None);
+        assert_eq!(tree.consistency_proof(0, 13), None);
+    }
+}
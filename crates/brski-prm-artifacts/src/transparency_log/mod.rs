@@ -0,0 +1,174 @@
+//! The MASA transparency log, a Merkle tree over every voucher the MASA issued (RFC 9162 style).
+//! Hashes and vouchers are base64 encoded.
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use ietf_voucher::pki::X509;
+use serde::{Deserialize, Serialize};
+use signeable_payload::signeable::unsigned::Unsigned;
+
+use crate::token_type::PlainTokenType;
+
+pub mod merkle;
+
+use merkle::Hash;
+
+/// The size and root hash of the log at `timestamp`, signed by the MASA
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TreeHead {
+    pub tree_size: u64,
+    pub timestamp: DateTime<Utc>,
+    pub root_hash: String,
+}
+
+impl TreeHead {
+    pub fn new(tree_size: u64, timestamp: DateTime<Utc>, root_hash: &Hash) -> Self {
+        Self {
+            tree_size,
+            timestamp,
+            root_hash: encode(root_hash),
+        }
+    }
+}
+
+/// Audit path of the entry `leaf_index` in the tree of `tree_size` entries
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    pub audit_path: Vec<String>,
+}
+
+impl InclusionProof {
+    pub fn new(leaf_index: u64, tree_size: u64, audit_path: &[Hash]) -> Self {
+        Self {
+            leaf_index,
+            tree_size,
+            audit_path: audit_path.iter().map(encode).collect(),
+        }
+    }
+
+    /// Checks that `voucher` is part of the log with `tree_head`
+    pub fn verify(&self, voucher: &[u8], tree_head: &TreeHead) -> bool {
+        match (decode_all(&self.audit_path), decode(&tree_head.root_hash)) {
+            (Some(audit_path), Some(root)) => {
+                self.tree_size == tree_head.tree_size
+                    && merkle::verify_inclusion(
+                        &merkle::leaf_hash(voucher),
+                        self.leaf_index,
+                        self.tree_size,
+                        &audit_path,
+                        &root,
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Proof that the log of `first` entries is a prefix of the log of `second` entries
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub first: u64,
+    pub second: u64,
+    pub proof: Vec<String>,
+}
+
+impl ConsistencyProof {
+    pub fn new(first: u64, second: u64, proof: &[Hash]) -> Self {
+        Self {
+            first,
+            second,
+            proof: proof.iter().map(encode).collect(),
+        }
+    }
+
+    /// Checks that the log only grew between the tree heads `first` and `second`
+    pub fn verify(&self, first: &TreeHead, second: &TreeHead) -> bool {
+        match (
+            decode_all(&self.proof),
+            decode(&first.root_hash),
+            decode(&second.root_hash),
+        ) {
+            (Some(proof), Some(first_root), Some(second_root)) => {
+                self.first == first.tree_size
+                    && self.second == second.tree_size
+                    && merkle::verify_consistency(
+                        self.first,
+                        self.second,
+                        &first_root,
+                        &second_root,
+                        &proof,
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A signed voucher as appended to the log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub leaf_index: u64,
+    pub serial_number: String,
+    pub date: DateTime<Utc>,
+    /// Content type of `voucher`
+    pub content_type: String,
+    pub voucher: String,
+}
+
+impl LogEntry {
+    /// The voucher as it was signed, the leaf of the Merkle tree
+    pub fn voucher(&self) -> Option<Vec<u8>> {
+        STANDARD.decode(&self.voucher).ok()
+    }
+}
+
+/// A [TreeHead] signed by the MASA
+#[derive(Debug, Clone)]
+pub struct TreeHeadResponse {
+    payload: TreeHead,
+    masa_sign_certs: Vec<X509>,
+    signature_type: PlainTokenType,
+}
+
+impl TryFrom<TreeHeadResponse> for Unsigned<TreeHead> {
+    type Error = signeable_payload::error::SigneableError;
+
+    fn try_from(value: TreeHeadResponse) -> Result<Self, Self::Error> {
+        let mut header_set = signeable_payload::header::HeaderSet::new();
+        header_set.set_x509_certificate_chain(&value.masa_sign_certs, true);
+        header_set.set_algorithm(
+            signeable_payload::algorithm::Algorithm::ES256.to_string(),
+            true,
+        );
+        header_set.set_content_type(value.signature_type.as_content_type(), false);
+
+        Ok(Unsigned::new(value.payload, header_set))
+    }
+}
+
+impl TreeHeadResponse {
+    pub fn new(
+        payload: TreeHead,
+        masa_sign_certs: impl IntoIterator<Item = impl Into<X509>>,
+        signature_type: PlainTokenType,
+    ) -> Self {
+        Self {
+            payload,
+            masa_sign_certs: masa_sign_certs.into_iter().map(Into::into).collect(),
+            signature_type,
+        }
+    }
+}
+
+fn encode(hash: &Hash) -> String {
+    STANDARD.encode(hash)
+}
+
+fn decode(hash: &str) -> Option<Hash> {
+    STANDARD.decode(hash).ok()?.try_into().ok()
+}
+
+fn decode_all(hashes: &[String]) -> Option<Vec<Hash>> {
+    hashes.iter().map(|hash| decode(hash)).collect()
+}
@@ -0,0 +1,154 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::validate::Validate;
+
+/// Name of the profile the registrar ships with
+pub const DEFAULT_CERT_PROFILE: &str = "ldevid";
+
+/// Parameters of the LDevIDs the registrar CA issues.
+///
+/// Subject and public key always come from the pledge's CSR, everything else is taken from the profile.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CertProfile {
+    pub validity_days: u32,
+    /// If unset, digitalSignature is used, plus keyEncipherment for RSA keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_usage: Option<Vec<KeyUsageFlag>>,
+    /// Short names as understood by OpenSSL (e.g. `clientAuth`) or dotted OIDs
+    pub extended_key_usage: Vec<String>,
+    /// Whether the SANs requested in the CSR are copied into the certificate.
+    /// The registrar can not check them against the pledge, so only enable this for pledges that may claim any name.
+    pub copy_csr_sans: bool,
+    pub san_templates: Vec<SanTemplate>,
+    /// Dotted OIDs put into the certificatePolicies extension
+    pub policy_oids: Vec<String>,
+    pub crl_urls: Vec<String>,
+    pub ocsp_urls: Vec<String>,
+}
+
+impl Default for CertProfile {
+    fn default() -> Self {
+        Self {
+            validity_days: 365,
+            key_usage: None,
+            extended_key_usage: vec!["clientAuth".to_owned()],
+            copy_csr_sans: false,
+            san_templates: vec![],
+            policy_oids: vec![],
+            crl_urls: vec![],
+            ocsp_urls: vec![],
+        }
+    }
+}
+
+impl Validate for CertProfile {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.validity_days == 0 {
+            return Err(anyhow!("validity_days must be greater than 0".to_owned()));
+        }
+
+        if self.key_usage.as_ref().is_some_and(|ku| ku.is_empty()) {
+            return Err(anyhow!(
+                "key_usage must not be empty, remove it to use the defaults".to_owned()
+            ));
+        }
+
+        if self
+            .policy_oids
+            .iter()
+            .any(|oid| oid.is_empty() || !oid.split('.').all(|arc| arc.parse::<u64>().is_ok()))
+        {
+            return Err(anyhow!("policy_oids must be dotted OIDs".to_owned()));
+        }
+
+        if self
+            .crl_urls
+            .iter()
+            .chain(self.ocsp_urls.iter())
+            .any(|url| url.is_empty())
+        {
+            return Err(anyhow!("CRL and OCSP URLs cannot be empty".to_owned()));
+        }
+
+        if self.san_templates.iter().any(|t| t.value.is_empty()) {
+            return Err(anyhow!("SAN templates cannot be empty".to_owned()));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyUsageFlag {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SanKind {
+    Dns,
+    Uri,
+    Email,
+    Ip,
+}
+
+/// A SAN derived from the pledge's IDevID.
+///
+/// `value` may contain the placeholders `{serial}` (serialNumber of the IDevID subject),
+/// `{hw-type}` and `{hw-serial}` (hardwareModuleName of the IDevID SAN).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SanTemplate {
+    pub kind: SanKind,
+    pub value: String,
+}
+
+/// Selects a cert profile for pledges by glob patterns on their IDevID, `*` and `?` work as in policy rules.
+/// All patterns given have to match, a rule needs at least one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CertProfileRule {
+    /// Name of the profile LDevIDs of matching pledges are issued with
+    pub profile: String,
+    /// Pattern for the organization of the IDevID issuer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// Pattern for the hwType of the hardwareModuleName SAN
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hw_type: Option<String>,
+    /// Pattern for the serialNumber of the IDevID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+}
+
+impl CertProfileRule {
+    fn patterns(&self) -> [&Option<String>; 3] {
+        [&self.manufacturer, &self.hw_type, &self.serial]
+    }
+}
+
+impl Validate for CertProfileRule {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.patterns().iter().all(|pattern| pattern.is_none()) {
+            return Err(anyhow!(
+                "rule for {} matches every pledge, use default_cert_profile instead",
+                self.profile
+            ));
+        }
+
+        if self
+            .patterns()
+            .iter()
+            .any(|pattern| pattern.as_ref().is_some_and(|p| p.is_empty()))
+        {
+            return Err(anyhow!("rule patterns cannot be empty".to_owned()));
+        }
+
+        Ok(())
+    }
+}
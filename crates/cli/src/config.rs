@@ -5,8 +5,16 @@ use serde::{Deserialize, Serialize};
 use crate::cli::{Cli, OperatingMode};
 
 pub use crate::{
-    masa_config::MasaConfig, pledge_config::PledgeConfig,
-    registrar_agent_config::RegistrarAgentConfig, registrar_config::RegistrarConfig,
+    cert_profile::{
+        CertProfile, CertProfileRule, KeyUsageFlag, SanKind, SanTemplate, DEFAULT_CERT_PROFILE,
+    },
+    masa_config::{MasaConfig, NoncelessVoucherPolicy, UnownedDeviceAction},
+    pledge_config::PledgeConfig,
+    policy_config::{AuditLogPolicy, PolicyAction, PolicyConfig, PolicyRule},
+    registrar_agent_config::RegistrarAgentConfig,
+    registrar_config::RegistrarConfig,
+    tenant_config::{TenantConfig, DEFAULT_TENANT},
+    vendor_config::VendorConfig,
 };
 use crate::{
     masa_config::NullableMasaConfig, pledge_config::NullablePledgeConfig,
@@ -1,14 +1,22 @@
+mod cert_profile;
 mod cli;
 pub mod config;
 mod layering;
 mod masa_config;
+mod masa_issue;
 mod pledge_config;
+mod policy_config;
+mod registrar_agent_command;
 mod registrar_agent_config;
 mod registrar_config;
+mod tenant_config;
 mod util;
 mod validate;
+mod vendor_config;
 
 pub use cli::Command;
+pub use masa_issue::{IssueArgs, MasaCommand, VoucherFormat};
+pub use registrar_agent_command::RegistrarAgentCommand;
 
 use clap::Parser;
 use cli::Cli;
@@ -70,6 +78,8 @@ mod tests {
                 bootstrap_serials = ["example"]
                 autodiscover = true
                 use_tls = true
+                [registrar_agent.pledges]
+                "00-D0-E5-F2-00-02" = "http://localhost:3002"
             "#,
             )?;
 
@@ -83,6 +93,12 @@ mod tests {
             assert_eq!(config.registrar_agent.bootstrap_serials, vec!["example"]);
             assert!(config.registrar_agent.autodiscover);
             assert!(config.registrar_agent.use_tls);
+            assert_eq!(config.registrar_agent.discovery_timeout, 5);
+            assert_eq!(
+                config.registrar_agent.pledges["00-D0-E5-F2-00-02"],
+                "http://localhost:3002"
+            );
+            assert!(config.pledge.advertise);
 
             Ok(())
         });
@@ -106,4 +122,183 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn it_parses_cert_profiles() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "Config.toml",
+                r#"
+                [registrar]
+                default_cert_profile = "iot"
+                [registrar.cert_profiles.iot]
+                validity_days = 30
+                key_usage = ["digital-signature", "key-agreement"]
+                extended_key_usage = ["clientAuth", "serverAuth"]
+                policy_oids = ["1.3.6.1.4.1.0.1"]
+                ocsp_urls = ["http://ocsp.example.com"]
+                [[registrar.cert_profiles.iot.san_templates]]
+                kind = "dns"
+                value = "{serial}.pledges.example.com"
+                [[registrar.cert_profile_rules]]
+                profile = "iot"
+                manufacturer = "ACME *"
+                hw_type = "1.3.6.1.4.1.0.2"
+            "#,
+            )?;
+
+            let config = get_config().unwrap();
+
+            let profile = &config.registrar.cert_profiles["iot"];
+            assert_eq!(profile.validity_days, 30);
+            assert_eq!(
+                profile.key_usage,
+                Some(vec![
+                    config::KeyUsageFlag::DigitalSignature,
+                    config::KeyUsageFlag::KeyAgreement
+                ])
+            );
+            assert_eq!(profile.san_templates[0].kind, config::SanKind::Dns);
+            assert!(!profile.copy_csr_sans);
+            assert!(config
+                .registrar
+                .cert_profiles
+                .contains_key(config::DEFAULT_CERT_PROFILE));
+
+            let rule = &config.registrar.cert_profile_rules[0];
+            assert_eq!(rule.profile, "iot");
+            assert_eq!(rule.manufacturer.as_deref(), Some("ACME *"));
+            assert_eq!(rule.serial, None);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn it_parses_vendors() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "Config.toml",
+                r#"
+                [registrar.vendors.acme]
+                manufacturer = "ACME *"
+                masa_url = "https://masa.acme.example"
+            "#,
+            )?;
+
+            let config = get_config().unwrap();
+
+            let vendor = &config.registrar.vendors["acme"];
+            assert_eq!(vendor.manufacturer, "ACME *");
+            assert_eq!(
+                vendor.masa_url.as_deref(),
+                Some("https://masa.acme.example")
+            );
+            assert!(vendor.client_authentication);
+            assert!(!config.registrar.use_idevid_masa_uri);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn it_parses_tenants() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "Config.toml",
+                r#"
+                [masa]
+                voucher_lifetime = 3600
+                [masa.tenants.lighting]
+                ca_certificate = "lighting/vendor-ca.cert"
+                intermediate_certificates = ["lighting/intermediate.cert"]
+                masa_certificate = "lighting/masa.cert"
+                masa_key = "lighting/masa.key"
+                database = "lighting.sqlite"
+                unowned_devices = "refuse"
+            "#,
+            )?;
+
+            let config = get_config().unwrap();
+
+            let tenant = &config.masa.tenants["lighting"];
+            assert_eq!(tenant.intermediate_certificates.len(), 1);
+            assert_eq!(
+                tenant.unowned_devices,
+                Some(config::UnownedDeviceAction::Refuse)
+            );
+            assert_eq!(tenant.voucher_lifetime, None);
+            assert_eq!(config.masa.voucher_lifetime, 3600);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn it_parses_the_masa_issue_command() {
+        let cli = Cli::try_parse_from([
+            "open-brski",
+            "masa",
+            "--port",
+            "3005",
+            "issue",
+            "--serials",
+            "serials.txt",
+            "--domain-cert",
+            "registrar.cert",
+            "--output",
+            "vouchers",
+        ])
+        .unwrap();
+
+        let Command::Masa(masa) = cli.command else {
+            panic!("expected the masa command");
+        };
+        let Some(MasaCommand::Issue(args)) = &masa.command else {
+            panic!("expected the issue subcommand");
+        };
+        assert_eq!(args.format, VoucherFormat::Cose);
+        assert_eq!(args.output, std::path::PathBuf::from("vouchers"));
+        assert_eq!(masa.port.as_deref(), Some("3005"));
+
+        assert!(
+            Cli::try_parse_from(["open-brski", "masa", "issue", "--output", "vouchers"]).is_err()
+        );
+        assert!(Cli::try_parse_from([
+            "open-brski",
+            "masa",
+            "issue",
+            "--serials",
+            "serials.txt",
+            "--output",
+            "vouchers"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn it_parses_the_registrar_agent_phases() {
+        let cli = Cli::try_parse_from([
+            "open-brski",
+            "registrar-agent",
+            "--job-store",
+            "jobs",
+            "export",
+            "--output",
+            "bundle.json",
+        ])
+        .unwrap();
+
+        let Command::RegistrarAgent(agent) = cli.command else {
+            panic!("expected the registrar-agent command");
+        };
+        let Some(RegistrarAgentCommand::Export { output }) = &agent.command else {
+            panic!("expected the export subcommand");
+        };
+        assert_eq!(output, &std::path::PathBuf::from("bundle.json"));
+        assert!(agent.job_store.is_some());
+
+        assert!(Cli::try_parse_from(["open-brski", "registrar-agent", "collect"]).is_ok());
+        assert!(Cli::try_parse_from(["open-brski", "registrar-agent", "import"]).is_err());
+    }
 }
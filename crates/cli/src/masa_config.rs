@@ -1,19 +1,76 @@
-use crate::util::parse_relative_path_buf;
+use std::collections::BTreeMap;
+
+use crate::util::{parse_new_relative_path_buf, parse_relative_path_buf};
 use anyhow::anyhow;
 use clap::{arg, Args};
 use figment::value::magic::RelativePathBuf;
 use serde::{Deserialize, Serialize};
 
-use crate::validate::Validate;
+use crate::{
+    masa_issue::MasaCommand,
+    tenant_config::{TenantConfig, DEFAULT_TENANT},
+    validate::Validate,
+};
+
+/// What the MASA does for pledges without an ownership record
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnownedDeviceAction {
+    /// Issue no voucher
+    Refuse,
+    /// Issue `logged` vouchers
+    Log,
+    /// Issue the requested `proximity` or `agent-proximity` assertion once its evidence was verified, `logged` otherwise
+    #[default]
+    Proximity,
+}
+
+/// When the MASA answers a voucher request without nonce with a nonceless voucher.
+/// Such vouchers expire and can be renewed until their `last-renewal-date`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoncelessVoucherPolicy {
+    /// Refuse voucher requests without nonce
+    Never,
+    /// Only for pledges owned by the requesting domain, i.e. `verified` vouchers
+    #[default]
+    Owned,
+    /// For every pledge a voucher is issued for
+    Always,
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct MasaConfig {
     pub port: String,
     pub ca_certificate: RelativePathBuf,
     pub ca_key: RelativePathBuf,
+    /// CAs between `ca_certificate` and the IDevIDs or the MASA certificate, included in the voucher x5c
+    pub intermediate_certificates: Vec<RelativePathBuf>,
     pub masa_certificate: RelativePathBuf,
     pub masa_key: RelativePathBuf,
+    /// Registrar trusted without registration
     pub registrar_ee_certificate: RelativePathBuf,
+    /// Serve HTTPS using the MASA certificate
+    pub use_tls: bool,
+    /// Only accept TLS clients presenting the certificate of a trusted registrar.
+    /// Without it, registrars may still authenticate and are then bound to the RVR they send.
+    pub require_client_auth: bool,
+    /// Where certificates of registrars registered via the admin API are kept
+    pub registrars_dir: RelativePathBuf,
+    /// SQLite database holding the audit log of issued vouchers and the pledge owners
+    pub database: RelativePathBuf,
+    /// Pledges owned by the requesting domain get `verified` vouchers, those owned by another domain none
+    pub unowned_devices: UnownedDeviceAction,
+    pub nonceless_vouchers: NoncelessVoucherPolicy,
+    /// Seconds a nonceless voucher is valid, it never outlives the pinned domain certificate
+    pub voucher_lifetime: u64,
+    /// Seconds after issuance a nonceless voucher may still be renewed
+    pub renewal_period: u64,
+    /// Further product lines by name, the `[masa]` section itself is the tenant [DEFAULT_TENANT]
+    pub tenants: BTreeMap<String, TenantConfig>,
+    /// Bearer token for the admin API, the API is disabled if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
 }
 impl Validate for MasaConfig {
     fn validate(&self) -> anyhow::Result<()> {
@@ -45,6 +102,73 @@ impl Validate for MasaConfig {
                 "registrar ee_certificate is empty or not exist".to_owned()
             ));
         }
+
+        if self.require_client_auth && !self.use_tls {
+            return Err(anyhow!(
+                "masa require_client_auth needs use_tls to be enabled".to_owned()
+            ));
+        }
+
+        if self.voucher_lifetime == 0 {
+            return Err(anyhow!(
+                "masa voucher_lifetime must be greater than 0".to_owned()
+            ));
+        }
+
+        if self.renewal_period < self.voucher_lifetime {
+            return Err(anyhow!(
+                "masa renewal_period cannot be shorter than voucher_lifetime".to_owned()
+            ));
+        }
+
+        if self
+            .intermediate_certificates
+            .iter()
+            .any(|certificate| !certificate.relative().is_file())
+        {
+            return Err(anyhow!(
+                "masa intermediate_certificates contains a file that does not exist".to_owned()
+            ));
+        }
+
+        let mut databases = vec![self.database.relative()];
+        for (name, tenant) in &self.tenants {
+            if name == DEFAULT_TENANT {
+                return Err(anyhow!(
+                    "masa tenant {} is configured by the [masa] section itself",
+                    DEFAULT_TENANT
+                ));
+            }
+            tenant
+                .validate()
+                .map_err(|e| anyhow!("masa tenant {}: {}", name, e))?;
+
+            let voucher_lifetime = tenant.voucher_lifetime.unwrap_or(self.voucher_lifetime);
+            if tenant.renewal_period.unwrap_or(self.renewal_period) < voucher_lifetime {
+                return Err(anyhow!(
+                    "masa tenant {}: renewal_period cannot be shorter than voucher_lifetime",
+                    name
+                ));
+            }
+
+            // tenants must not see each other's owners and logs
+            let database = tenant.database.relative();
+            if databases.contains(&database) {
+                return Err(anyhow!(
+                    "masa tenant {}: database is used by another tenant",
+                    name
+                ));
+            }
+            databases.push(database);
+        }
+
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.is_empty())
+        {
+            return Err(anyhow!("masa admin_token cannot be empty".to_owned()));
+        }
         Ok(())
     }
 }
@@ -59,6 +183,7 @@ impl Default for MasaConfig {
             ca_key: RelativePathBuf::from(
                 "/etc/open-brski/conf/masa/certificate-authority/vendor-ca.key",
             ),
+            intermediate_certificates: vec![],
             masa_certificate: RelativePathBuf::from(
                 "/etc/open-brski/conf/masa/signing-authority/vendor.cert",
             ),
@@ -68,12 +193,27 @@ impl Default for MasaConfig {
             registrar_ee_certificate: RelativePathBuf::from(
                 "/etc/open-brski/conf/registrar/signing-authority/registrar.cert",
             ),
+            use_tls: false,
+            require_client_auth: false,
+            registrars_dir: RelativePathBuf::from("/var/lib/open-brski/masa/registrars"),
+            database: RelativePathBuf::from("/var/lib/open-brski/masa/masa.sqlite"),
+            unowned_devices: UnownedDeviceAction::default(),
+            nonceless_vouchers: NoncelessVoucherPolicy::default(),
+            // two weeks, renewable for a year
+            voucher_lifetime: 14 * 86400,
+            renewal_period: 365 * 86400,
+            tenants: BTreeMap::new(),
+            admin_token: None,
         }
     }
 }
 
 #[derive(Args, Serialize, Deserialize, Debug)]
 pub struct NullableMasaConfig {
+    /// Runs instead of the server
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<MasaCommand>,
     #[arg(short, long)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<String>,
@@ -93,4 +233,24 @@ pub struct NullableMasaConfig {
     #[clap(value_parser = parse_relative_path_buf)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub masa_key: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_tls: Option<bool>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_client_auth: Option<bool>,
+    #[arg(long)]
+    #[clap(value_parser = parse_new_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registrars_dir: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[clap(value_parser = parse_new_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voucher_lifetime: Option<u64>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renewal_period: Option<u64>,
 }
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Subcommand, ValueEnum};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MasaCommand {
+    /// Issue nonceless vouchers ahead of time for sites that never reach the MASA
+    Issue(IssueArgs),
+}
+
+/// Format of the pre-issued vouchers, RVRs read with `--rvrs` are expected in the same format
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum VoucherFormat {
+    Jws,
+    #[default]
+    Cose,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(ArgGroup::new("input").required(true).args(["rvrs", "serials"])))]
+pub struct IssueArgs {
+    /// File with one base64 encoded RVR per line
+    #[arg(long)]
+    pub rvrs: Option<PathBuf>,
+    /// File with one pledge serial number per line, the vouchers pin `--domain-cert`
+    #[arg(long, requires = "domain_cert")]
+    pub serials: Option<PathBuf>,
+    /// PEM encoded registrar certificate of the site, only used with `--serials`
+    #[arg(long)]
+    pub domain_cert: Option<PathBuf>,
+    /// Directory the vouchers are written to, one `<serial-number>.<format>` file each
+    #[arg(long)]
+    pub output: PathBuf,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: VoucherFormat,
+    /// Tenant issuing the vouchers for `--serials`, RVRs select it by the pledge IDevID
+    #[arg(long)]
+    pub tenant: Option<String>,
+}
@@ -1,5 +1,6 @@
 use crate::{util::parse_relative_path_buf, validate::Validate};
 use anyhow::anyhow;
+use brski_prm_artifacts::protocol_version::ProtocolVersion;
 use clap::Args;
 use figment::value::magic::RelativePathBuf;
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,13 @@ pub struct PledgeConfig {
     pub idev_id: String,
     pub idevid_certificate: RelativePathBuf,
     pub idevid_privkey: RelativePathBuf,
+    /// The BRSKI-PRM revision this pledge speaks, announced to agents through its PledgeInfo
+    pub protocol_version: ProtocolVersion,
+    /// Announce the pledge as `_brski-pledge._tcp` over mDNS so registrar-agents can discover it
+    pub advertise: bool,
+    /// Bearer token for the admin API standing in for the reset button, the API is disabled if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
 }
 
 impl Validate for PledgeConfig {
@@ -28,6 +36,14 @@ impl Validate for PledgeConfig {
         if !self.idevid_privkey.relative().exists() {
             return Err(anyhow!("idevid_privkey does not exist".to_owned()));
         }
+
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.is_empty())
+        {
+            return Err(anyhow!("pledge admin_token cannot be empty".to_owned()));
+        }
         Ok(())
     }
 }
@@ -43,6 +59,9 @@ impl Default for PledgeConfig {
             idevid_privkey: RelativePathBuf::from(
                 "/etc/open-brski/conf/registrar-agent/idevid_privkey.key",
             ),
+            protocol_version: ProtocolVersion::default(),
+            advertise: true,
+            admin_token: None,
         }
     }
 }
@@ -63,4 +82,10 @@ pub struct NullablePledgeConfig {
     #[clap(value_parser = parse_relative_path_buf)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub idevid_privkey: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<ProtocolVersion>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advertise: Option<bool>,
 }
@@ -0,0 +1,109 @@
+use anyhow::anyhow;
+use figment::value::magic::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+
+use crate::validate::Validate;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyAction {
+    #[default]
+    Allow,
+    Deny,
+    /// Hold the voucher request until an operator approves or rejects the pledge
+    Review,
+}
+
+/// Decides which pledges the registrar accepts.
+///
+/// Rules are checked in order and the first matching rule decides.
+/// If no rule matches, the script is asked and if it does not decide either, `default_action` applies.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PolicyConfig {
+    pub default_action: PolicyAction,
+    pub rules: Vec<PolicyRule>,
+    /// Rhai script defining `fn accept(device)`.
+    /// `device` is a map with `serial`, `manufacturer` and `masa_uri`. Returning `true` accepts the device,
+    /// `false` or a string (used as reason) rejects it and `()` leaves the decision to `default_action`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<RelativePathBuf>,
+    /// Seconds the agent is told to wait before asking again about a pledge awaiting review
+    pub retry_after: u64,
+    /// Checks on the MASA audit log of a pledge, the log is only fetched if one is enabled
+    pub audit_log: AuditLogPolicy,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            default_action: PolicyAction::Allow,
+            rules: vec![],
+            script: None,
+            retry_after: 60,
+            audit_log: AuditLogPolicy::default(),
+        }
+    }
+}
+
+/// Which earlier vouchers in the MASA audit log of a pledge make the registrar reject it.
+/// The log is fetched once the MASA issued the voucher, before the voucher is relayed to the pledge.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct AuditLogPolicy {
+    /// Reject pledges that were issued a voucher for another domain before
+    pub reject_other_domains: bool,
+    /// Reject pledges with a nonceless voucher for another domain, such vouchers can be replayed at any time
+    pub reject_nonceless_elsewhere: bool,
+}
+
+impl AuditLogPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.reject_other_domains || self.reject_nonceless_elsewhere
+    }
+}
+
+/// Matches devices by glob patterns, `*` matching any number and `?` exactly one character.
+/// All patterns given have to match, a rule without patterns matches every device.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PolicyRule {
+    pub action: PolicyAction,
+    /// Pattern for the serialNumber of the IDevID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Pattern for the organization of the IDevID issuer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// Pattern for the MASA URI of the pledge
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masa_uri: Option<String>,
+    /// Reason reported when the rule rejects a device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Validate for PolicyConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(script) = &self.script {
+            if !script.relative().exists() {
+                return Err(anyhow!("policy script does not exist".to_owned()));
+            }
+        }
+
+        if self.retry_after == 0 {
+            return Err(anyhow!("policy retry_after cannot be 0".to_owned()));
+        }
+
+        for rule in &self.rules {
+            let patterns = [&rule.serial, &rule.manufacturer, &rule.masa_uri];
+            if patterns
+                .iter()
+                .any(|p| p.as_ref().is_some_and(|p| p.is_empty()))
+            {
+                return Err(anyhow!("policy rule patterns cannot be empty".to_owned()));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+/// The phases of bootstrapping pledges without registrar connectivity, each works on the jobs in `job_store`.
+/// Run collect and deliver at the pledges and forward where the registrar is reachable, bundles carry the jobs in between.
+#[derive(Subcommand, Debug, Clone)]
+pub enum RegistrarAgentCommand {
+    /// Collect PVRs and PERs from the discovered pledges
+    Collect,
+    /// Forward collected requests and delivered statuses to the registrar
+    Forward,
+    /// Deliver vouchers, CA certificates and LDevID certificates to the pledges
+    Deliver,
+    /// Write all jobs into a bundle
+    Export {
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Merge the jobs of a bundle into the job store, the job further along wins
+    Import {
+        #[arg(long)]
+        input: PathBuf,
+    },
+}
@@ -1,5 +1,12 @@
-use crate::{util::parse_relative_path_buf, validate::Validate};
+use std::collections::BTreeMap;
+
+use crate::{
+    registrar_agent_command::RegistrarAgentCommand,
+    util::{parse_new_relative_path_buf, parse_relative_path_buf},
+    validate::Validate,
+};
 use anyhow::anyhow;
+use brski_prm_artifacts::protocol_version::ProtocolVersion;
 use clap::{arg, Args};
 use figment::value::magic::RelativePathBuf;
 use serde::{Deserialize, Serialize};
@@ -8,13 +15,40 @@ use serde::{Deserialize, Serialize};
 pub struct RegistrarAgentConfig {
     pub port: String,
     pub bootstrap_serials: Vec<String>,
+    /// Browse for pledges announcing `_brski-pledge._tcp` over mDNS instead of using `pledges`
     pub autodiscover: bool,
+    /// Seconds to browse for pledges before bootstrapping the ones found
+    pub discovery_timeout: u64,
+    /// Pledges bootstrapped without autodiscover, serial number to URL
+    pub pledges: BTreeMap<String, String>,
     pub autodiscover_registrar: bool,
+    /// Talk to the registrar over mutually authenticated TLS, using the ee certificate as client certificate
     pub use_tls: bool,
     pub ee_certificate: RelativePathBuf,
     pub ee_key: RelativePathBuf,
+    /// Registrar certificate, or the CA that issued it, the registrar's TLS certificate is pinned to
     pub registrar_certificate: RelativePathBuf,
     pub registrar_url: String,
+    /// Forces the BRSKI-PRM revision used towards pledges. If unset, the revision announced in the pledge's PledgeInfo is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<ProtocolVersion>,
+    /// Seconds to keep polling the registrar while a voucher request awaits manual approval
+    pub voucher_approval_timeout: u64,
+    /// Pledges bootstrapped at the same time
+    pub bootstrap_workers: usize,
+    /// Times a failed bootstrapping step is repeated before the pledge is given up
+    pub bootstrap_retries: u32,
+    /// Seconds a bootstrapping step may take, forwarding the PVR may take voucher_approval_timeout on top
+    pub bootstrap_step_timeout: u64,
+    /// Seconds collected PVRs stay forwardable, at most the registrar's `agent_signed_data_max_age`.
+    /// Older jobs are collected again instead of being refused by the registrar.
+    pub agent_signed_data_max_age: u64,
+    /// Directory keeping a job per pledge, so bootstrapping can be split into phases run apart from each other
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_store: Option<RelativePathBuf>,
+    /// Bearer token for the management API, the API is disabled if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
 }
 
 impl Default for RegistrarAgentConfig {
@@ -23,6 +57,8 @@ impl Default for RegistrarAgentConfig {
             port: "3003".to_owned(),
             bootstrap_serials: vec![],
             autodiscover: false,
+            discovery_timeout: 5,
+            pledges: BTreeMap::new(),
             use_tls: false,
             ee_certificate: RelativePathBuf::from(
                 "/etc/open-brski/conf/registrar-agent/ee_certificate.pem",
@@ -33,6 +69,14 @@ impl Default for RegistrarAgentConfig {
                 "/etc/open-brski/conf/registrar/ee_certificate.pem",
             ),
             registrar_url: "http://localhost:3001".to_owned(),
+            protocol_version: None,
+            voucher_approval_timeout: 3600,
+            bootstrap_workers: 4,
+            bootstrap_retries: 2,
+            bootstrap_step_timeout: 120,
+            agent_signed_data_max_age: 86400,
+            job_store: None,
+            admin_token: None,
         }
     }
 }
@@ -43,6 +87,35 @@ impl Validate for RegistrarAgentConfig {
             return Err(anyhow!("registrar-agent: Port cannot be empty".to_owned()));
         }
 
+        if self.autodiscover && self.discovery_timeout == 0 {
+            return Err(anyhow!(
+                "registrar-agent: discovery_timeout must be positive to autodiscover pledges"
+                    .to_owned()
+            ));
+        }
+
+        if self.bootstrap_workers == 0 {
+            return Err(anyhow!(
+                "registrar-agent: bootstrap_workers must be positive".to_owned()
+            ));
+        }
+
+        if self.agent_signed_data_max_age == 0 {
+            return Err(anyhow!(
+                "registrar-agent: agent_signed_data_max_age must be positive".to_owned()
+            ));
+        }
+
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.is_empty())
+        {
+            return Err(anyhow!(
+                "registrar-agent: admin_token cannot be empty".to_owned()
+            ));
+        }
+
         if !self.ee_certificate.relative().exists() {
             return Err(anyhow!(
                 "registrar-agent: ee_certificate is empty or not exist".to_owned()
@@ -70,6 +143,10 @@ impl Validate for RegistrarAgentConfig {
 
 #[derive(Args, Serialize, Deserialize, Debug)]
 pub struct NullableRegistrarAgentConfig {
+    /// Runs instead of the server
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<RegistrarAgentCommand>,
     #[arg(short, long)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<String>,
@@ -81,6 +158,9 @@ pub struct NullableRegistrarAgentConfig {
     pub autodiscover: Option<bool>,
     #[arg(long)]
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub discovery_timeout: Option<u64>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub autodiscover_registrar: Option<bool>,
     #[arg(short, long)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -101,4 +181,26 @@ pub struct NullableRegistrarAgentConfig {
     #[clap(value_parser = parse_relative_path_buf)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub registrar_url: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<ProtocolVersion>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voucher_approval_timeout: Option<u64>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bootstrap_workers: Option<usize>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bootstrap_retries: Option<u32>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bootstrap_step_timeout: Option<u64>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_signed_data_max_age: Option<u64>,
+    #[arg(long)]
+    #[clap(value_parser = parse_new_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_store: Option<RelativePathBuf>,
 }
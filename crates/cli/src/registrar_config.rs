@@ -1,4 +1,11 @@
-use crate::util::parse_relative_path_buf;
+use std::collections::BTreeMap;
+
+use crate::{
+    cert_profile::{CertProfile, CertProfileRule, DEFAULT_CERT_PROFILE},
+    policy_config::PolicyConfig,
+    util::{parse_new_relative_path_buf, parse_relative_path_buf},
+    vendor_config::VendorConfig,
+};
 use anyhow::anyhow;
 use clap::Args;
 use figment::value::magic::RelativePathBuf;
@@ -15,7 +22,41 @@ pub struct RegistrarConfig {
     pub registrar_certificate: RelativePathBuf,
     pub registrar_key: RelativePathBuf,
     pub reg_agt_ee_cert: RelativePathBuf,
+    /// MASA for pledges whose IDevID names none and that match no vendor
     pub masa_url: String,
+    /// Send voucher requests to the MASA URI extension of the pledge's IDevID (RFC 8995 2.3.2)
+    pub use_idevid_masa_uri: bool,
+    /// Per manufacturer MASA settings by name
+    pub vendors: BTreeMap<String, VendorConfig>,
+    /// Trust anchor for the MASA's TLS certificate, the built-in web PKI roots are used if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masa_ca_certificate: Option<RelativePathBuf>,
+    /// CA the signatures of vouchers have to chain to before the registrar relays them
+    pub voucher_ca_certificate: RelativePathBuf,
+    /// CA the IDevIDs of pledges matching no vendor have to chain to, voucher requests are rejected otherwise
+    pub idevid_ca_certificate: RelativePathBuf,
+    /// Directory of vouchers pre-issued with `open-brski masa issue`, consulted before contacting the MASA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_vouchers: Option<RelativePathBuf>,
+    /// Serve HTTPS and require registrar-agents to authenticate with a client certificate
+    pub use_tls: bool,
+    /// CA the client certificates of registrar-agents have to chain to, not the `ca_certificate` issuing LDevIDs
+    pub agent_ca_certificate: RelativePathBuf,
+    /// How many seconds agent-signed-data may be old when its PVR reaches the registrar
+    pub agent_signed_data_max_age: u64,
+    /// SQLite database holding the per-pledge onboarding state
+    pub database: RelativePathBuf,
+    /// Certificate profiles by name
+    pub cert_profiles: BTreeMap<String, CertProfile>,
+    /// Rules selecting the profile by the pledge's IDevID, the first matching rule decides
+    pub cert_profile_rules: Vec<CertProfileRule>,
+    /// Profile LDevIDs are issued with if no rule matches
+    pub default_cert_profile: String,
+    /// Which pledges are accepted
+    pub policy: PolicyConfig,
+    /// Bearer token for the admin API, the API is disabled if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
 }
 
 impl Default for RegistrarConfig {
@@ -38,10 +79,44 @@ impl Default for RegistrarConfig {
                 "/etc/open-brski/conf/registrar/signing-authority/registrar.key",
             ),
             masa_url: "http://localhost:3000".to_owned(),
+            use_idevid_masa_uri: false,
+            vendors: BTreeMap::new(),
+            masa_ca_certificate: None,
+            voucher_ca_certificate: RelativePathBuf::from(
+                "/etc/open-brski/conf/masa/certificate-authority/vendor-ca.cert",
+            ),
+            idevid_ca_certificate: RelativePathBuf::from(
+                "/etc/open-brski/conf/masa/certificate-authority/vendor-ca.cert",
+            ),
+            imported_vouchers: None,
+            use_tls: false,
+            agent_ca_certificate: RelativePathBuf::from(
+                "/etc/open-brski/conf/registrar-agent/registrar-agent-ca.cert",
+            ),
+            // agents may collect PVRs offline and forward them later
+            agent_signed_data_max_age: 86400,
+            database: RelativePathBuf::from("/var/lib/open-brski/registrar/registrar.sqlite"),
+            cert_profiles: BTreeMap::from([(
+                DEFAULT_CERT_PROFILE.to_owned(),
+                CertProfile::default(),
+            )]),
+            cert_profile_rules: vec![],
+            default_cert_profile: DEFAULT_CERT_PROFILE.to_owned(),
+            policy: PolicyConfig::default(),
+            admin_token: None,
         }
     }
 }
 
+impl RegistrarConfig {
+    /// Returns the configured profile with the given name
+    pub fn cert_profile(&self, name: &str) -> Option<(&str, &CertProfile)> {
+        self.cert_profiles
+            .get_key_value(name)
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+}
+
 impl Validate for RegistrarConfig {
     fn validate(&self) -> anyhow::Result<()> {
         if self.port.is_empty() {
@@ -63,6 +138,98 @@ impl Validate for RegistrarConfig {
         if !self.registrar_key.relative().exists() {
             return Err(anyhow!("ee_certificate is empty or not exist".to_owned()));
         }
+
+        if let Some(masa_ca_certificate) = &self.masa_ca_certificate {
+            if !masa_ca_certificate.relative().exists() {
+                return Err(anyhow!(
+                    "masa_ca_certificate is empty or does not exist".to_owned()
+                ));
+            }
+        }
+
+        if !self.voucher_ca_certificate.relative().exists() {
+            return Err(anyhow!(
+                "voucher_ca_certificate is empty or does not exist".to_owned()
+            ));
+        }
+
+        if !self.idevid_ca_certificate.relative().exists() {
+            return Err(anyhow!(
+                "idevid_ca_certificate is empty or does not exist".to_owned()
+            ));
+        }
+
+        if let Some(imported_vouchers) = &self.imported_vouchers {
+            if !imported_vouchers.relative().is_dir() {
+                return Err(anyhow!("imported_vouchers is not a directory".to_owned()));
+            }
+        }
+
+        for (name, vendor) in &self.vendors {
+            vendor
+                .validate()
+                .map_err(|e| anyhow!("vendor {}: {}", name, e))?;
+        }
+
+        if self.use_tls && !self.agent_ca_certificate.relative().exists() {
+            return Err(anyhow!(
+                "agent_ca_certificate is empty or does not exist".to_owned()
+            ));
+        }
+
+        // every enrolled pledge would be accepted as registrar-agent otherwise
+        if self.use_tls
+            && std::fs::read(self.agent_ca_certificate.relative()).ok()
+                == std::fs::read(self.ca_certificate.relative()).ok()
+        {
+            return Err(anyhow!(
+                "agent_ca_certificate has to be a dedicated CA, not the ca_certificate issuing LDevIDs"
+                    .to_owned()
+            ));
+        }
+
+        if self.agent_signed_data_max_age == 0 {
+            return Err(anyhow!(
+                "agent_signed_data_max_age must be greater than 0".to_owned()
+            ));
+        }
+
+        if !self.cert_profiles.contains_key(&self.default_cert_profile) {
+            return Err(anyhow!(
+                "default_cert_profile {} is not a configured cert profile",
+                self.default_cert_profile
+            ));
+        }
+
+        self.policy
+            .validate()
+            .map_err(|e| anyhow!("policy: {}", e))?;
+
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.is_empty())
+        {
+            return Err(anyhow!("registrar admin_token cannot be empty".to_owned()));
+        }
+
+        for (name, profile) in &self.cert_profiles {
+            profile
+                .validate()
+                .map_err(|e| anyhow!("cert profile {}: {}", name, e))?;
+        }
+
+        for rule in &self.cert_profile_rules {
+            rule.validate()
+                .map_err(|e| anyhow!("cert profile rule: {}", e))?;
+
+            if !self.cert_profiles.contains_key(&rule.profile) {
+                return Err(anyhow!(
+                    "cert profile rule selects {}, which is not a configured cert profile",
+                    rule.profile
+                ));
+            }
+        }
         Ok(())
     }
 }
@@ -88,4 +255,40 @@ pub struct NullableRegistrarConfig {
     #[clap(value_parser = parse_relative_path_buf)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub masa_key: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[clap(value_parser = parse_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masa_ca_certificate: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[clap(value_parser = parse_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voucher_ca_certificate: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[clap(value_parser = parse_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idevid_ca_certificate: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[clap(value_parser = parse_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_vouchers: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_idevid_masa_uri: Option<bool>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_tls: Option<bool>,
+    #[arg(long)]
+    #[clap(value_parser = parse_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_ca_certificate: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_signed_data_max_age: Option<u64>,
+    #[arg(long)]
+    #[clap(value_parser = parse_new_relative_path_buf)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<RelativePathBuf>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_cert_profile: Option<String>,
 }
@@ -0,0 +1,87 @@
+use anyhow::anyhow;
+use figment::value::magic::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    masa_config::{NoncelessVoucherPolicy, UnownedDeviceAction},
+    validate::Validate,
+};
+
+/// Name of the tenant configured by the `[masa]` section itself
+pub const DEFAULT_TENANT: &str = "default";
+
+/// A product line served by the MASA with its own vendor CA, voucher signing key and database.
+///
+/// Voucher requests go to the tenant whose vendor CA issued the pledge IDevID,
+/// the policy of the `[masa]` section applies unless the tenant overrides it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TenantConfig {
+    /// Vendor CA the pledge IDevIDs of this product line chain to
+    pub ca_certificate: RelativePathBuf,
+    /// CAs between `ca_certificate` and the IDevIDs or the MASA certificate, included in the voucher x5c
+    pub intermediate_certificates: Vec<RelativePathBuf>,
+    pub masa_certificate: RelativePathBuf,
+    pub masa_key: RelativePathBuf,
+    /// Holds the owners, the audit log and the transparency log of this product line
+    pub database: RelativePathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unowned_devices: Option<UnownedDeviceAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonceless_vouchers: Option<NoncelessVoucherPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voucher_lifetime: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renewal_period: Option<u64>,
+}
+
+impl Default for TenantConfig {
+    fn default() -> Self {
+        Self {
+            ca_certificate: RelativePathBuf::from(""),
+            intermediate_certificates: vec![],
+            masa_certificate: RelativePathBuf::from(""),
+            masa_key: RelativePathBuf::from(""),
+            database: RelativePathBuf::from(""),
+            unowned_devices: None,
+            nonceless_vouchers: None,
+            voucher_lifetime: None,
+            renewal_period: None,
+        }
+    }
+}
+
+impl Validate for TenantConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        let files = [
+            ("ca_certificate", &self.ca_certificate),
+            ("masa_certificate", &self.masa_certificate),
+            ("masa_key", &self.masa_key),
+        ];
+        for (name, file) in files {
+            if !file.relative().is_file() {
+                return Err(anyhow!("{} is empty or does not exist", name));
+            }
+        }
+
+        if self
+            .intermediate_certificates
+            .iter()
+            .any(|certificate| !certificate.relative().is_file())
+        {
+            return Err(anyhow!(
+                "intermediate_certificates contains a file that does not exist".to_owned()
+            ));
+        }
+
+        if self.database.relative().as_os_str().is_empty() {
+            return Err(anyhow!("database cannot be empty".to_owned()));
+        }
+
+        if self.voucher_lifetime == Some(0) {
+            return Err(anyhow!("voucher_lifetime must be greater than 0".to_owned()));
+        }
+
+        Ok(())
+    }
+}
@@ -9,3 +9,8 @@ pub fn parse_relative_path_buf(r: &str) -> Result<RelativePathBuf, String> {
         Err(format!("Path {:?} does not exist", r))
     }
 }
+
+/// Like [parse_relative_path_buf], but for files that are created if missing
+pub fn parse_new_relative_path_buf(r: &str) -> Result<RelativePathBuf, String> {
+    Ok(RelativePathBuf::from(r))
+}
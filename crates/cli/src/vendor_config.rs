@@ -0,0 +1,83 @@
+use anyhow::anyhow;
+use figment::value::magic::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+
+use crate::validate::Validate;
+
+/// How the registrar reaches the MASA of one manufacturer.
+///
+/// A vendor applies to pledges whose IDevID issuer organization matches `manufacturer`,
+/// the first matching vendor in name order is used.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VendorConfig {
+    /// Pattern for the organization of the IDevID issuer, `*` and `?` work as in policy rules
+    pub manufacturer: String,
+    /// Used instead of the MASA URI from the IDevID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masa_url: Option<String>,
+    /// Trust anchor for the TLS certificate of this vendor's MASA, the registrar's `masa_ca_certificate` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masa_ca_certificate: Option<RelativePathBuf>,
+    /// CA the vouchers of this vendor's MASA have to chain to, the registrar's `voucher_ca_certificate` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voucher_ca_certificate: Option<RelativePathBuf>,
+    /// CA the IDevIDs of this vendor's pledges have to chain to, the registrar's `idevid_ca_certificate` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idevid_ca_certificate: Option<RelativePathBuf>,
+    /// Whether the registrar certificate is presented as TLS client certificate
+    pub client_authentication: bool,
+}
+
+impl Default for VendorConfig {
+    fn default() -> Self {
+        Self {
+            manufacturer: String::new(),
+            masa_url: None,
+            masa_ca_certificate: None,
+            voucher_ca_certificate: None,
+            idevid_ca_certificate: None,
+            client_authentication: true,
+        }
+    }
+}
+
+impl Validate for VendorConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.manufacturer.is_empty() {
+            return Err(anyhow!("manufacturer cannot be empty".to_owned()));
+        }
+
+        if self.masa_url.as_ref().is_some_and(|url| url.is_empty()) {
+            return Err(anyhow!(
+                "masa_url cannot be empty, remove it to use the IDevID MASA URI".to_owned()
+            ));
+        }
+
+        if let Some(masa_ca_certificate) = &self.masa_ca_certificate {
+            if !masa_ca_certificate.relative().exists() {
+                return Err(anyhow!(
+                    "masa_ca_certificate is empty or does not exist".to_owned()
+                ));
+            }
+        }
+
+        if let Some(voucher_ca_certificate) = &self.voucher_ca_certificate {
+            if !voucher_ca_certificate.relative().exists() {
+                return Err(anyhow!(
+                    "voucher_ca_certificate is empty or does not exist".to_owned()
+                ));
+            }
+        }
+
+        if let Some(idevid_ca_certificate) = &self.idevid_ca_certificate {
+            if !idevid_ca_certificate.relative().exists() {
+                return Err(anyhow!(
+                    "idevid_ca_certificate is empty or does not exist".to_owned()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Verification of the agent-signed-data a registrar-agent adds to PVRs, done by the registrar and the MASA
+
+use brski_prm_artifacts::ietf_voucher::{agent_signed_data::AgentSignedData, VoucherRequest};
+use chrono::{DateTime, Duration, Utc};
+use openssl::x509::X509;
+use signeable_payload::{BasicVeryingContext, SignerVerifyer, Verified};
+
+/// How far the agent's clock may be ahead of ours
+pub const ALLOWED_CLOCK_SKEW: i64 = 300;
+
+/// Why the agent-signed-data of a PVR was not accepted
+#[derive(Debug, thiserror::Error)]
+pub enum AgentSignedDataError {
+    #[error("PVR does not contain agent-signed-data")]
+    Missing,
+
+    #[error("Can not read the registrar-agent certificate: {0}")]
+    AgentCertificate(String),
+
+    #[error("agent-signed-data is not signed by the registrar-agent: {0}")]
+    InvalidSignature(String),
+
+    #[error("agent-signed-data is for serial number {agent}, the PVR for {pvr}")]
+    SerialMismatch { agent: String, pvr: String },
+
+    #[error("agent-signed-data was created on {0}, which is in the future")]
+    CreatedInFuture(DateTime<Utc>),
+}
+
+/// Checks that `agent` signed the agent-signed-data of `pvr` for the PVR's pledge and that it was not created
+/// after `now`, give or take [ALLOWED_CLOCK_SKEW]. Returns it, so callers can check the key id or its age.
+pub fn verify_agent_signed_data(
+    pvr: &VoucherRequest,
+    agent: &X509,
+    verifyer: Box<dyn SignerVerifyer<AgentSignedData>>,
+    now: DateTime<Utc>,
+) -> Result<Verified<AgentSignedData>, AgentSignedDataError> {
+    let agent_signed_data = pvr
+        .details
+        .agent_signed_data
+        .clone()
+        .ok_or(AgentSignedDataError::Missing)?;
+
+    let agent_der = agent
+        .to_der()
+        .map_err(|e| AgentSignedDataError::AgentCertificate(e.to_string()))?;
+    let agent_pub_key = agent
+        .public_key()
+        .and_then(|key| key.public_key_to_der())
+        .map_err(|e| AgentSignedDataError::AgentCertificate(e.to_string()))?;
+
+    let verified = agent_signed_data
+        .into_verifyable_boxed(verifyer)
+        .verify(Some(BasicVeryingContext {
+            pub_key: Some(agent_pub_key),
+        }))
+        .map_err(|e| AgentSignedDataError::InvalidSignature(e.to_string()))?;
+
+    // An embedded certificate takes precedence over the agent's key when verifying, so it has to be the agent's
+    if let Some(chain) = verified.headers().x509_certificate_chain() {
+        if chain.first() != Some(&agent_der) {
+            return Err(AgentSignedDataError::InvalidSignature(
+                "signed with an embedded foreign certificate".to_string(),
+            ));
+        }
+    }
+
+    let data = &verified.payload().data;
+    if data.serial_number != pvr.details.serial_number {
+        return Err(AgentSignedDataError::SerialMismatch {
+            agent: data.serial_number.clone(),
+            pvr: pvr.details.serial_number.clone(),
+        });
+    }
+    if data.created_on > now + Duration::seconds(ALLOWED_CLOCK_SKEW) {
+        return Err(AgentSignedDataError::CreatedInFuture(data.created_on));
+    }
+
+    Ok(verified)
+}
@@ -0,0 +1,256 @@
+use std::{collections::BTreeMap, net::SocketAddr, time::Duration};
+
+use brski_prm_artifacts::{
+    pledge_info::PledgeInfo,
+    token_type::{
+        DataInterchangeFormat, PlainTokenType, VoucherTokenType, CBOR, COSE, COSE_VOUCHER, JOSE,
+        JSON, JWS_VOUCHER,
+    },
+};
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
+use tracing::{event, Level};
+
+/// DNS-SD service pledges announce themselves under, BRSKI-PRM section 6.1
+pub const PLEDGE_SERVICE_TYPE: &str = "_brski-pledge._tcp.local.";
+
+pub const TXT_SERIAL: &str = "serial";
+pub const TXT_DATA_INTERCHANGE_FORMAT: &str = "dif";
+pub const TXT_TOKEN_TYPE: &str = "token";
+pub const TXT_VOUCHER_TYPE: &str = "voucher";
+pub const TXT_PROTOCOL_VERSION: &str = "version";
+
+/// A pledge found while browsing for [`PLEDGE_SERVICE_TYPE`]
+#[derive(Debug, Clone)]
+pub struct BrowsedPledge {
+    pub serial: String,
+    pub address: SocketAddr,
+    /// Capabilities from the TXT records, None if the pledge did not announce them all
+    pub pledge_info: Option<PledgeInfo>,
+}
+
+/// Keeps the pledge announced until dropped
+pub struct PledgeAnnouncer {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl PledgeAnnouncer {
+    /// Announces the pledge on all interfaces, including loopback so agents on the same host find it.
+    /// The instance name is the serial number, the TXT records carry the serial and the capabilities of `pledge_info`.
+    pub fn announce(serial: &str, port: u16, pledge_info: &PledgeInfo) -> anyhow::Result<Self> {
+        let daemon = daemon()?;
+
+        let records = txt_records(serial, pledge_info);
+        let properties: Vec<(&str, &str)> = records
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+            .collect();
+        let service = ServiceInfo::new(
+            PLEDGE_SERVICE_TYPE,
+            serial,
+            &format!("{}.local.", serial),
+            "",
+            port,
+            properties.as_slice(),
+        )?
+        .enable_addr_auto();
+        let fullname = service.get_fullname().to_string();
+
+        daemon.register(service)?;
+        event!(Level::INFO, "Announcing pledge as {}", fullname);
+
+        Ok(Self { daemon, fullname })
+    }
+}
+
+impl Drop for PledgeAnnouncer {
+    fn drop(&mut self) {
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            event!(Level::WARN, "Could not withdraw {}: {}", self.fullname, e);
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Browses for pledges until `timeout` elapses, pledges withdrawn in the meantime are left out
+pub async fn browse_pledges(timeout: Duration) -> anyhow::Result<Vec<BrowsedPledge>> {
+    let daemon = daemon()?;
+    let receiver = daemon.browse(PLEDGE_SERVICE_TYPE)?;
+
+    let mut pledges = BTreeMap::new();
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            () = &mut deadline => break,
+            service_event = receiver.recv_async() => match service_event {
+                Ok(ServiceEvent::ServiceResolved(info)) => match browsed_pledge(&info) {
+                    Some(pledge) => {
+                        event!(Level::DEBUG, "Resolved pledge {:?}", pledge);
+                        pledges.insert(info.get_fullname().to_string(), pledge);
+                    }
+                    None => event!(
+                        Level::WARN,
+                        "Ignoring {}, it has no usable address",
+                        info.get_fullname()
+                    ),
+                },
+                Ok(ServiceEvent::ServiceRemoved(_, fullname)) => {
+                    pledges.remove(&fullname);
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    let _ = daemon.stop_browse(PLEDGE_SERVICE_TYPE);
+    let _ = daemon.shutdown();
+
+    Ok(pledges.into_values().collect())
+}
+
+fn daemon() -> anyhow::Result<ServiceDaemon> {
+    let daemon = ServiceDaemon::new()?;
+    daemon.enable_interface(IfKind::LoopbackV4)?;
+    Ok(daemon)
+}
+
+fn browsed_pledge(info: &ServiceInfo) -> Option<BrowsedPledge> {
+    // link-local IPv6 addresses would need a scope, so IPv4 is preferred
+    let addresses = info.get_addresses();
+    let ip = addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or(addresses.iter().next())?;
+
+    // BRSKI-PRM uses the serial number as instance name, the TXT record is only needed where it does not fit
+    let serial = match info.get_property_val_str(TXT_SERIAL) {
+        Some(serial) => serial.to_string(),
+        None => info
+            .get_fullname()
+            .strip_suffix(PLEDGE_SERVICE_TYPE)?
+            .trim_end_matches('.')
+            .to_string(),
+    };
+
+    Some(BrowsedPledge {
+        serial,
+        address: SocketAddr::new(*ip, info.get_port()),
+        pledge_info: pledge_info_from_txt(|key| info.get_property_val_str(key)),
+    })
+}
+
+fn txt_records(serial: &str, pledge_info: &PledgeInfo) -> Vec<(&'static str, String)> {
+    vec![
+        (TXT_SERIAL, serial.to_string()),
+        (
+            TXT_DATA_INTERCHANGE_FORMAT,
+            pledge_info
+                .data_interchance_format
+                .as_content_type()
+                .to_string(),
+        ),
+        (
+            TXT_TOKEN_TYPE,
+            pledge_info
+                .supported_token_type
+                .as_content_type()
+                .to_string(),
+        ),
+        (
+            TXT_VOUCHER_TYPE,
+            pledge_info
+                .supported_voucher_type
+                .as_content_type()
+                .to_string(),
+        ),
+        (
+            TXT_PROTOCOL_VERSION,
+            pledge_info.protocol_version.to_string(),
+        ),
+    ]
+}
+
+/// The capabilities announced in the TXT records, None if any is missing or unknown
+fn pledge_info_from_txt<'a>(txt: impl Fn(&str) -> Option<&'a str>) -> Option<PledgeInfo> {
+    let data_interchance_format = match txt(TXT_DATA_INTERCHANGE_FORMAT)? {
+        JSON => DataInterchangeFormat::JSON,
+        CBOR => DataInterchangeFormat::CBOR,
+        _ => return None,
+    };
+    let supported_token_type = match txt(TXT_TOKEN_TYPE)? {
+        token_type @ (JOSE | COSE) => PlainTokenType::from_content_type(token_type),
+        _ => return None,
+    };
+    let supported_voucher_type = match txt(TXT_VOUCHER_TYPE)? {
+        voucher_type @ (JWS_VOUCHER | COSE_VOUCHER) => {
+            VoucherTokenType::from_content_type(voucher_type)
+        }
+        _ => return None,
+    };
+    let protocol_version = txt(TXT_PROTOCOL_VERSION)?.parse().ok()?;
+
+    Some(PledgeInfo {
+        data_interchance_format,
+        supported_token_type,
+        supported_voucher_type,
+        protocol_version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use brski_prm_artifacts::protocol_version::ProtocolVersion;
+
+    use super::*;
+
+    #[test]
+    fn it_round_trips_txt_records() {
+        let mut pledge_info = PledgeInfo::simple_json();
+        pledge_info.protocol_version = ProtocolVersion::Rfc;
+
+        let records: HashMap<_, _> = txt_records("00-D0-E5-F2-00-02", &pledge_info)
+            .into_iter()
+            .collect();
+        let parsed = pledge_info_from_txt(|key| records.get(key).map(String::as_str)).unwrap();
+
+        assert_eq!(records[TXT_SERIAL], "00-D0-E5-F2-00-02");
+        assert_eq!(parsed.data_interchance_format.as_content_type(), JSON);
+        assert_eq!(parsed.supported_voucher_type.as_content_type(), JWS_VOUCHER);
+        assert_eq!(parsed.protocol_version, ProtocolVersion::Rfc);
+
+        assert!(pledge_info_from_txt(|key| match key {
+            TXT_TOKEN_TYPE => Some("application/pkcs7-mime"),
+            _ => records.get(key).map(String::as_str),
+        })
+        .is_none());
+    }
+
+    #[tokio::test]
+    async fn it_browses_announced_pledges_on_loopback() {
+        let serial = format!("dns-sd-test-{}", std::process::id());
+        let _announcer =
+            PledgeAnnouncer::announce(&serial, 3102, &PledgeInfo::simple_cbor()).unwrap();
+
+        let pledges = browse_pledges(Duration::from_secs(3)).await.unwrap();
+
+        let pledge = pledges
+            .iter()
+            .find(|pledge| pledge.serial == serial)
+            .expect("announced pledge was not found");
+        assert_eq!(pledge.address.port(), 3102);
+        assert_eq!(
+            pledge
+                .pledge_info
+                .as_ref()
+                .unwrap()
+                .supported_voucher_type
+                .as_content_type(),
+            COSE_VOUCHER
+        );
+    }
+}
@@ -1,8 +1,12 @@
 #![feature(adt_const_params)]
 #![allow(incomplete_features)]
 
+#[cfg(feature = "openssl")]
+pub mod agent_signed_data;
 pub mod defaults;
+pub mod dns_sd;
 pub mod error;
 pub mod middleware;
 pub mod server_error;
+pub mod tls;
 pub mod util;
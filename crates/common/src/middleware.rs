@@ -1,3 +1,5 @@
+use crate::server_error::ServerError;
+
 pub async fn log_request_size(
     request: axum::extract::Request,
     next: axum::middleware::Next,
@@ -15,3 +17,84 @@ pub async fn log_request_size(
     // Proceed to the next layer
     next.run(request).await
 }
+
+/// Bearer token guarding an admin API, the API is disabled if unset
+#[derive(Clone, Debug, Default)]
+pub struct AdminToken(pub Option<String>);
+
+/// Only lets requests with `Authorization: Bearer <admin_token>` through,
+/// layered with `axum::middleware::from_fn_with_state(AdminToken(..), require_admin_token)`
+pub async fn require_admin_token(
+    axum::extract::State(AdminToken(expected)): axum::extract::State<AdminToken>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, ServerError> {
+    let expected = expected.ok_or(ServerError::Forbidden("Admin API is disabled".to_string()))?;
+
+    let provided = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    // compared in constant time, common does not always pull in openssl
+    let differs = provided
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0, |differs, (a, b)| differs | (a ^ b));
+    if provided.len() != expected.len() || differs != 0 {
+        return Err(ServerError::Forbidden("Invalid admin token".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use tower::util::ServiceExt;
+
+    use super::*;
+
+    async fn status(token: Option<&str>, authorization: Option<&str>) -> StatusCode {
+        let app = Router::new().route("/admin", get(|| async {})).route_layer(
+            axum::middleware::from_fn_with_state(
+                AdminToken(token.map(str::to_string)),
+                require_admin_token,
+            ),
+        );
+
+        let mut request = Request::builder().uri("/admin");
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn it_requires_the_admin_token() {
+        assert_eq!(
+            status(Some("secret"), Some("Bearer secret")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(Some("secret"), Some("Bearer secre")).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(status(Some("secret"), None).await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            status(None, Some("Bearer secret")).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -32,6 +32,12 @@ pub enum ServerError {
     #[error(transparent)]
     BRSKIError(#[from] brski_prm_artifacts::error::BRSKIPRMError),
 
+    #[error("Forbidden - Reason: {0}")]
+    Forbidden(String),
+
+    #[error("Not Found")]
+    NotFound,
+
     #[error("Not Acceptible")]
     NotAcceptible,
 
@@ -59,9 +65,14 @@ impl IntoResponse for ServerError {
         event!(tracing::Level::ERROR, error = %self);
         self.source().map(|e| info!("Caused by: {}", e));
 
+        if let Self::Forbidden(reason) = self {
+            return (axum::http::StatusCode::FORBIDDEN, reason).into_response();
+        }
+
         let status = match self {
             Self::BadRequest => axum::http::StatusCode::BAD_REQUEST,
             Self::InternalError(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound => axum::http::StatusCode::NOT_FOUND,
             Self::NotAcceptible => axum::http::StatusCode::NOT_ACCEPTABLE,
             Self::UnsupportedMediaType => axum::http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Self::BRSKIError(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
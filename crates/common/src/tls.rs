@@ -0,0 +1,69 @@
+use std::{future::Future, io, pin::Pin};
+
+use axum::{middleware::AddExtension, Extension};
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+
+/// The certificate a TLS client authenticated with, handlers extract it as `Option<Extension<TlsClientIdentity>>`
+/// as it is missing entirely when the server does not use TLS.
+#[derive(Clone, Debug, Default)]
+pub struct TlsClientIdentity {
+    /// DER encoded end-entity certificate, `None` if client authentication is optional and the client did not present one
+    pub certificate: Option<Vec<u8>>,
+}
+
+impl TlsClientIdentity {
+    /// Flattens the extension as extracted by handlers
+    pub fn certificate_of(identity: Option<Extension<TlsClientIdentity>>) -> Option<Vec<u8>> {
+        identity.and_then(|Extension(identity)| identity.certificate)
+    }
+}
+
+/// Terminates TLS and makes the client certificate available to handlers as [TlsClientIdentity]
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, TlsClientIdentity>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+
+            // the certificate was already verified by the server config's client cert verifier
+            let identity = TlsClientIdentity {
+                certificate: stream
+                    .get_ref()
+                    .1
+                    .peer_certificates()
+                    .and_then(|certificates| certificates.first())
+                    .map(|certificate| certificate.0.clone()),
+            };
+
+            Ok((stream, Extension(identity).layer(service)))
+        })
+    }
+}
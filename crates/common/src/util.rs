@@ -1,4 +1,4 @@
-use brski_prm_artifacts::token_type::PKCS7;
+use brski_prm_artifacts::token_type::{VoucherTokenType, PKCS7};
 
 use crate::server_error::ServerError;
 
@@ -8,3 +8,83 @@ pub fn is_pkcs7(content_type: &str) -> Result<(), ServerError> {
         _ => Err(ServerError::UnsupportedMediaType),
     }
 }
+
+/// Name of the file a voucher for the pledge is kept in when issued ahead of time.
+/// None for serial numbers that would leave the voucher directory.
+pub fn voucher_file_name(serial_number: &str, token_type: &VoucherTokenType) -> Option<String> {
+    serial_file_name(serial_number, token_type.file_extension())
+}
+
+/// Name of a file kept for the pledge, None for serial numbers that would leave its directory
+pub fn serial_file_name(serial_number: &str, extension: &str) -> Option<String> {
+    if serial_number.is_empty()
+        || serial_number.starts_with('.')
+        || serial_number.contains(['/', '\\'])
+    {
+        return None;
+    }
+    Some(format!("{}.{}", serial_number, extension))
+}
+
+/// The domainID of the domain owning `pinned_domain_cert` (RFC 8995 5.8.2).
+/// Certificates without a SubjectKeyIdentifier are identified by the SHA-1 hash of their DER encoded public key.
+#[cfg(feature = "openssl")]
+pub fn domain_id(pinned_domain_cert: &openssl::x509::X509Ref) -> Result<String, ServerError> {
+    use brski_prm_artifacts::audit_log;
+    use openssl::hash::{hash, MessageDigest};
+
+    let key_identifier = match pinned_domain_cert.subject_key_id() {
+        Some(skid) => skid.as_slice().to_vec(),
+        None => hash(
+            MessageDigest::sha1(),
+            &pinned_domain_cert.public_key()?.public_key_to_der()?,
+        )?
+        .to_vec(),
+    };
+
+    Ok(audit_log::domain_id(&key_identifier))
+}
+
+/// Checks that `chain`, DER encoded certificates starting with the end entity as in an x5c header,
+/// chains to `trust_anchor`. Returns why it does not.
+#[cfg(feature = "openssl")]
+pub fn verify_certificate_chain(
+    trust_anchor: &openssl::x509::X509Ref,
+    chain: &[Vec<u8>],
+) -> Result<(), String> {
+    use openssl::{
+        stack::Stack,
+        x509::{store::X509StoreBuilder, X509StoreContext, X509},
+    };
+
+    let verify = || -> Result<Option<String>, openssl::error::ErrorStack> {
+        let (leaf, intermediates) = match chain.split_first() {
+            Some(split) => split,
+            None => return Ok(Some("certificate chain is empty".to_string())),
+        };
+
+        let leaf = X509::from_der(leaf)?;
+        let mut untrusted = Stack::new()?;
+        for der in intermediates {
+            untrusted.push(X509::from_der(der)?)?;
+        }
+
+        let mut store = X509StoreBuilder::new()?;
+        store.add_cert(trust_anchor.to_owned())?;
+        let store = store.build();
+
+        let mut context = X509StoreContext::new()?;
+        context.init(&store, &leaf, &untrusted, |context| {
+            Ok(match context.verify_cert()? {
+                true => None,
+                false => Some(context.error().error_string().to_string()),
+            })
+        })
+    };
+
+    match verify() {
+        Ok(None) => Ok(()),
+        Ok(Some(reason)) => Err(reason),
+        Err(e) => Err(e.to_string()),
+    }
+}
@@ -0,0 +1,162 @@
+use base64::prelude::*;
+use brski_prm_artifacts::{
+    ietf_voucher::{agent_signed_data::AgentSignedData, assertion::Assertion, VoucherRequest},
+    status::{enroll::status::PledgeEnrollStatus, voucher::status::VoucherStatus},
+};
+use signeable_payload::{jws::JoseSignerVerifyer, BasicVeryingContext, RawSigned};
+
+use crate::vectors::{self, minify};
+
+fn parse_pvr() -> VoucherRequest {
+    serde_json::from_str(vectors::PRM_PVR).unwrap()
+}
+
+fn agent_pub_key(pvr: &VoucherRequest) -> Vec<u8> {
+    let agent_sign_cert = pvr.details.agent_sign_cert.as_ref().unwrap();
+    agent_sign_cert[0]
+        .public_key()
+        .unwrap()
+        .public_key_to_der()
+        .unwrap()
+}
+
+#[test]
+fn it_parses_the_pvr_example() {
+    let pvr = parse_pvr();
+
+    assert_eq!(pvr.details.assertion, Some(Assertion::AgentProximity));
+    assert_eq!(pvr.details.serial_number, "0123456789");
+    assert_eq!(pvr.details.nonce.as_ref().unwrap().len(), 16);
+    assert!(pvr
+        .details
+        .agent_provided_proximity_registrar_cert
+        .is_some());
+    assert_eq!(pvr.details.agent_sign_cert.as_ref().unwrap().len(), 2);
+}
+
+#[test]
+fn pvr_example_round_trips() {
+    let pvr = parse_pvr();
+
+    // the example orders its members differently than we do, every leaf has to encode identically though
+    let expected: serde_json::Value = serde_json::from_str(vectors::PRM_PVR).unwrap();
+    assert_eq!(serde_json::to_value(&pvr).unwrap(), expected);
+}
+
+#[test]
+fn agent_sign_cert_example_forms_a_chain() {
+    let pvr = parse_pvr();
+    let chain = pvr.details.agent_sign_cert.unwrap();
+
+    assert!(chain[0].verify(&chain[1].public_key().unwrap()).unwrap());
+}
+
+#[test]
+fn agent_signed_data_example_verifies_with_agent_sign_cert() {
+    let pvr = parse_pvr();
+    let pub_key = agent_pub_key(&pvr);
+
+    // the example only carries a kid, the key has to come from the agent-sign-cert
+    let verified = pvr
+        .details
+        .agent_signed_data
+        .unwrap()
+        .into_verifyable(JoseSignerVerifyer::default())
+        .verify(Some(BasicVeryingContext {
+            pub_key: Some(pub_key),
+        }))
+        .unwrap();
+
+    assert_eq!(verified.payload().data.serial_number, "0123456789");
+    assert_eq!(
+        verified.headers().key_id(),
+        Some("XpzlMKxlpA68cU5FQMXUvnIT6Qw=")
+    );
+}
+
+#[test]
+fn agent_signed_data_example_fails_with_wrong_key() {
+    let pvr = parse_pvr();
+    let registrar_pub_key = pvr
+        .details
+        .agent_provided_proximity_registrar_cert
+        .as_ref()
+        .unwrap()
+        .public_key()
+        .unwrap()
+        .public_key_to_der()
+        .unwrap();
+
+    let res = pvr
+        .details
+        .agent_signed_data
+        .unwrap()
+        .into_verifyable(JoseSignerVerifyer::default())
+        .verify(Some(BasicVeryingContext {
+            pub_key: Some(registrar_pub_key),
+        }));
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn agent_signed_data_example_fails_when_tampered() {
+    let pvr = parse_pvr();
+    let pub_key = agent_pub_key(&pvr);
+
+    let mut jws: serde_json::Value =
+        serde_json::from_slice(&pvr.details.agent_signed_data.unwrap().data()).unwrap();
+    let forged = AgentSignedData::new(
+        "2022-04-26T05:07:41.448Z".parse().unwrap(),
+        "9876543210".to_string(),
+    );
+    jws["payload"] = BASE64_URL_SAFE_NO_PAD
+        .encode(serde_json::to_vec(&forged).unwrap())
+        .into();
+
+    let res = RawSigned::<AgentSignedData>::new(serde_json::to_vec(&jws).unwrap())
+        .into_verifyable(JoseSignerVerifyer::default())
+        .verify(Some(BasicVeryingContext {
+            pub_key: Some(pub_key),
+        }));
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn agent_signed_data_payload_round_trips_byte_for_byte() {
+    let pvr = parse_pvr();
+
+    let jws: serde_json::Value =
+        serde_json::from_slice(&pvr.details.agent_signed_data.unwrap().data()).unwrap();
+    let payload = BASE64_URL_SAFE_NO_PAD
+        .decode(jws["payload"].as_str().unwrap())
+        .unwrap();
+
+    let parsed: AgentSignedData = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(parsed.data.serial_number, "0123456789");
+    assert_eq!(serde_json::to_vec(&parsed).unwrap(), payload);
+}
+
+#[test]
+fn voucher_status_example_round_trips_byte_for_byte() {
+    let status: VoucherStatus = serde_json::from_str(vectors::PRM_VOUCHER_STATUS).unwrap();
+
+    assert!(!status.status);
+    assert_eq!(
+        serde_json::to_string(&status).unwrap(),
+        minify(vectors::PRM_VOUCHER_STATUS)
+    );
+}
+
+#[test]
+fn enroll_status_example_round_trips_byte_for_byte() {
+    let status: PledgeEnrollStatus = serde_json::from_str(vectors::PRM_ENROLL_STATUS).unwrap();
+
+    assert!(status.status);
+    assert_eq!(status, PledgeEnrollStatus::default());
+    assert_eq!(
+        serde_json::to_string(&status).unwrap(),
+        minify(vectors::PRM_ENROLL_STATUS)
+    );
+}
@@ -0,0 +1,14 @@
+//! Conformance tests against the examples published in the BRSKI-PRM draft, RFC 8995 and RFC 8366.
+//!
+//! The examples are checked in under `vectors/`. See the README there for where each one comes from
+//! and which placeholder values had to be replaced.
+pub mod vectors;
+
+#[cfg(all(test, feature = "jws"))]
+mod brski_prm;
+#[cfg(all(test, feature = "jws"))]
+mod rfc8366;
+#[cfg(all(test, feature = "jws"))]
+mod rfc8995;
+#[cfg(all(test, feature = "jws"))]
+mod signatures;
@@ -0,0 +1,67 @@
+use base64::prelude::*;
+use brski_prm_artifacts::ietf_voucher::{
+    artifact::{VoucherArtifact, VoucherArtifactDetails},
+    assertion::Assertion,
+};
+use chrono::Datelike;
+use openssl::nid::Nid;
+
+use crate::vectors::{self, minify};
+
+#[test]
+fn it_parses_the_voucher_example() {
+    let voucher: VoucherArtifact = serde_json::from_str(vectors::RFC8366_VOUCHER).unwrap();
+    let details = voucher.details;
+
+    assert_eq!(details.assertion, Some(Assertion::Verified));
+    assert_eq!(details.serial_number, "JADA123456789");
+    assert_eq!(details.created_on.unwrap().day(), 7);
+    assert_eq!(details.expires_on.unwrap().day(), 21);
+    assert_eq!(details.last_renewal_date.unwrap().year(), 2017);
+    assert_eq!(
+        details.idevid_issuer.unwrap(),
+        BASE64_STANDARD
+            .decode("b6E9bnXtn+ixEIVOxx4/ryf3eyM=")
+            .unwrap()
+    );
+    assert!(details.domain_cert_revocation_checks);
+    assert!(details.nonce.is_none());
+
+    let pinned_domain_cert = details.pinned_domain_cert.unwrap();
+    let common_name = pinned_domain_cert
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .unwrap()
+        .data()
+        .as_utf8()
+        .unwrap()
+        .to_string();
+    assert_eq!(common_name, "DomainRegistrar");
+}
+
+#[test]
+fn voucher_example_round_trips_byte_for_byte() {
+    let voucher: VoucherArtifact = serde_json::from_str(vectors::RFC8366_VOUCHER).unwrap();
+
+    assert_eq!(
+        serde_json::to_string(&voucher).unwrap(),
+        minify(vectors::RFC8366_VOUCHER)
+    );
+}
+
+#[test]
+fn absent_leaves_are_omitted() {
+    // RFC 7951 has no encoding for an unset leaf, so they must not show up as null
+    let voucher = VoucherArtifact {
+        details: VoucherArtifactDetails {
+            serial_number: "JADA123456789".to_string(),
+            ..Default::default()
+        },
+    };
+
+    assert_eq!(
+        serde_json::to_string(&voucher).unwrap(),
+        r#"{"ietf-voucher:voucher":{"serial-number":"JADA123456789","domain-cert-revocation-checks":false}}"#
+    );
+}
@@ -0,0 +1,27 @@
+use brski_prm_artifacts::ietf_voucher::{assertion::Assertion, VoucherRequest};
+
+use crate::vectors;
+
+#[test]
+fn it_parses_the_registrar_voucher_request_example() {
+    let rvr: VoucherRequest = serde_json::from_str(vectors::RFC8995_RVR).unwrap();
+    let details = rvr.details;
+
+    assert_eq!(details.assertion, Some(Assertion::Proximity));
+    assert_eq!(details.serial_number, "JADA123456789");
+    assert_eq!(details.nonce.unwrap().len(), 24);
+    assert_eq!(details.idevid_issuer.unwrap().len(), 20);
+
+    // the prior signed voucher request is carried as opaque bytes
+    let prior: serde_json::Value =
+        serde_json::from_slice(&details.prior_signed_voucher_request.unwrap()).unwrap();
+    assert!(prior["signatures"].is_array());
+}
+
+#[test]
+fn registrar_voucher_request_example_round_trips() {
+    let rvr: VoucherRequest = serde_json::from_str(vectors::RFC8995_RVR).unwrap();
+
+    let expected: serde_json::Value = serde_json::from_str(vectors::RFC8995_RVR).unwrap();
+    assert_eq!(serde_json::to_value(&rvr).unwrap(), expected);
+}
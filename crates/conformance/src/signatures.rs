@@ -0,0 +1,190 @@
+//! Signing round-trips of the spec payloads. The specs publish no signed PVR or voucher we have checked in
+//! and no COSE example at all (see `vectors/README.md`), so these sign with certificates from `example-certs`.
+
+use brski_prm_artifacts::{
+    ietf_voucher::{artifact::VoucherArtifact, pki::X509, VoucherRequest},
+    issued_voucher::IssuedVoucher,
+    pvr::response::PledgeVoucherRequestResponse,
+    token_type::VoucherTokenType,
+};
+use example_certs::TestCerts;
+use signeable_payload::{signeable::unsigned::Unsigned, BasicSigningContext, RawSigned, Verified};
+
+use crate::vectors;
+
+/// Signs the BRSKI-PRM voucher-request payload as the generated pledge and verifies it again
+fn sign_and_verify_pvr(
+    certs: &TestCerts,
+    token_type: VoucherTokenType,
+) -> (Vec<u8>, Verified<VoucherRequest>) {
+    let (pledge_cert, pledge_key) = &certs.pledge;
+
+    let pvr: VoucherRequest = serde_json::from_str(vectors::PRM_PVR).unwrap();
+    let pledge_cert = X509::try_from(pledge_cert.der().to_vec()).unwrap();
+
+    let unsigned: Unsigned<VoucherRequest> =
+        PledgeVoucherRequestResponse::new(pvr.clone(), [pledge_cert], token_type)
+            .try_into()
+            .unwrap();
+
+    let signed = unsigned
+        .into_signeable_boxed(token_type.signature_type().get_sv().unwrap())
+        .sign(pledge_key.serialize_der(), BasicSigningContext::new())
+        .unwrap();
+
+    let verified = RawSigned::<VoucherRequest>::new(signed.data())
+        .into_verifyable_boxed(token_type.signature_type().get_sv().unwrap())
+        .verify(None)
+        .unwrap();
+
+    assert_eq!(verified.payload(), &pvr);
+
+    (signed.data(), verified)
+}
+
+/// Signs the RFC 8366 voucher payload as the generated MASA and verifies it again
+fn sign_and_verify_voucher(
+    certs: &TestCerts,
+    token_type: VoucherTokenType,
+) -> (Vec<u8>, Verified<VoucherArtifact>) {
+    let (vendor_cert, vendor_key) = &certs.vendor;
+
+    let voucher: VoucherArtifact = serde_json::from_str(vectors::RFC8366_VOUCHER).unwrap();
+
+    let unsigned: Unsigned<VoucherArtifact> =
+        IssuedVoucher::try_new(voucher.clone(), [vendor_cert.der().to_vec()], token_type)
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+    let signed = unsigned
+        .into_signeable_boxed(token_type.signature_type().get_sv().unwrap())
+        .sign(vendor_key.serialize_der(), BasicSigningContext::new())
+        .unwrap();
+
+    let verified = RawSigned::<VoucherArtifact>::new(signed.data())
+        .into_verifyable_boxed(token_type.signature_type().get_sv().unwrap())
+        .verify(None)
+        .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(verified.payload()).unwrap(),
+        serde_json::to_value(&voucher).unwrap()
+    );
+
+    (signed.data(), verified)
+}
+
+/// Checks that the end of the x5c chain was issued by the given certificate
+fn assert_issued_by(x5c: Vec<Vec<u8>>, issuer: &[u8]) {
+    let leaf = openssl::x509::X509::from_der(&x5c[0]).unwrap();
+    let issuer = openssl::x509::X509::from_der(issuer).unwrap();
+
+    assert!(leaf.verify(&issuer.public_key().unwrap()).unwrap());
+}
+
+mod jws {
+    use base64::prelude::*;
+
+    use super::*;
+
+    fn jws_payload(data: &[u8]) -> Vec<u8> {
+        let jws: serde_json::Value = serde_json::from_slice(data).unwrap();
+        BASE64_URL_SAFE_NO_PAD
+            .decode(jws["payload"].as_str().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn pvr_signs_and_verifies() {
+        let certs = example_certs::generate_certs();
+        let (data, verified) = sign_and_verify_pvr(&certs, VoucherTokenType::JWS);
+
+        assert_eq!(
+            jws_payload(&data),
+            serde_json::to_vec(verified.payload()).unwrap()
+        );
+        assert_issued_by(
+            verified.headers().x509_certificate_chain().unwrap(),
+            certs.vendor_ca.0.der(),
+        );
+    }
+
+    #[test]
+    fn voucher_signs_and_verifies() {
+        let certs = example_certs::generate_certs();
+        let (data, verified) = sign_and_verify_voucher(&certs, VoucherTokenType::JWS);
+
+        assert_eq!(
+            jws_payload(&data),
+            serde_json::to_vec(verified.payload()).unwrap()
+        );
+        assert_issued_by(
+            verified.headers().x509_certificate_chain().unwrap(),
+            certs.vendor_ca.0.der(),
+        );
+    }
+
+    #[test]
+    fn tampered_voucher_fails() {
+        let certs = example_certs::generate_certs();
+        let (data, _) = sign_and_verify_voucher(&certs, VoucherTokenType::JWS);
+
+        let mut jws: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        let mut payload: serde_json::Value = serde_json::from_slice(&jws_payload(&data)).unwrap();
+        payload["ietf-voucher:voucher"]["serial-number"] = "JADA000000000".into();
+        jws["payload"] = BASE64_URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&payload).unwrap())
+            .into();
+
+        let res = RawSigned::<VoucherArtifact>::new(serde_json::to_vec(&jws).unwrap())
+            .into_verifyable_boxed(VoucherTokenType::JWS.signature_type().get_sv().unwrap())
+            .verify(None);
+
+        assert!(res.is_err());
+    }
+}
+
+#[cfg(feature = "cose")]
+mod cose {
+    use coset::CborSerializable;
+
+    use super::*;
+
+    fn cose_payload(data: &[u8]) -> Vec<u8> {
+        coset::CoseSign1::from_slice(data).unwrap().payload.unwrap()
+    }
+
+    #[test]
+    fn pvr_signs_and_verifies() {
+        let certs = example_certs::generate_certs();
+        let (data, verified) = sign_and_verify_pvr(&certs, VoucherTokenType::COSE);
+
+        let mut encoded = Vec::new();
+        ciborium::into_writer(verified.payload(), &mut encoded).unwrap();
+        assert_eq!(cose_payload(&data), encoded);
+        assert_issued_by(
+            verified.headers().x509_certificate_chain().unwrap(),
+            certs.vendor_ca.0.der(),
+        );
+    }
+
+    #[test]
+    fn voucher_signs_and_verifies() {
+        let certs = example_certs::generate_certs();
+        let (data, verified) = sign_and_verify_voucher(&certs, VoucherTokenType::COSE);
+
+        let mut encoded = Vec::new();
+        ciborium::into_writer(verified.payload(), &mut encoded).unwrap();
+        assert_eq!(cose_payload(&data), encoded);
+    }
+
+    #[test]
+    fn sign1_structure_round_trips_byte_for_byte() {
+        let certs = example_certs::generate_certs();
+        let (data, _) = sign_and_verify_voucher(&certs, VoucherTokenType::COSE);
+
+        let sign1 = coset::CoseSign1::from_slice(&data).unwrap();
+        assert_eq!(sign1.to_vec().unwrap(), data);
+    }
+}
@@ -0,0 +1,42 @@
+/// Pledge voucher-request with an agent-proximity assertion, BRSKI-PRM
+pub const PRM_PVR: &str = include_str!("../vectors/brski-prm/pvr-agent-proximity.json");
+
+/// Pledge voucher status, BRSKI-PRM
+pub const PRM_VOUCHER_STATUS: &str = include_str!("../vectors/brski-prm/voucher-status.json");
+
+/// Pledge enroll status, BRSKI-PRM
+pub const PRM_ENROLL_STATUS: &str = include_str!("../vectors/brski-prm/enroll-status.json");
+
+/// Voucher, RFC 8366 Section 5.3
+pub const RFC8366_VOUCHER: &str = include_str!("../vectors/rfc8366/voucher.json");
+
+/// Registrar voucher-request, RFC 8995 Section 3.3
+pub const RFC8995_RVR: &str = include_str!("../vectors/rfc8995/registrar-voucher-request.json");
+
+/// Strips insignificant whitespace from a JSON document while keeping the member order intact,
+/// so a vector can be compared byte-for-byte against our own serialization.
+pub fn minify(json: &str) -> String {
+    let mut minified = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in json.chars() {
+        if in_string {
+            minified.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+            minified.push(c);
+        } else if !c.is_whitespace() {
+            minified.push(c);
+        }
+    }
+
+    minified
+}
@@ -10,6 +10,7 @@ pub struct TestCerts {
     pub vendor: (rcgen::Certificate, rcgen::KeyPair),
     pub registrar_ca: (rcgen::Certificate, rcgen::KeyPair),
     pub registrar: (rcgen::Certificate, rcgen::KeyPair),
+    pub registrar_agent_ca: (rcgen::Certificate, rcgen::KeyPair),
     pub registrar_agent: (rcgen::Certificate, rcgen::KeyPair),
     pub pledge: (rcgen::Certificate, rcgen::KeyPair),
 }
@@ -32,6 +33,10 @@ pub struct OpensslTestCerts {
         openssl::x509::X509,
         openssl::pkey::PKey<openssl::pkey::Private>,
     ),
+    pub registrar_agent_ca: (
+        openssl::x509::X509,
+        openssl::pkey::PKey<openssl::pkey::Private>,
+    ),
     pub registrar_agent: (
         openssl::x509::X509,
         openssl::pkey::PKey<openssl::pkey::Private>,
@@ -75,6 +80,11 @@ pub fn serialize_certs(certs: TestCerts, path: PathBuf) {
         &path.join("registrar/signing-authority"),
         &certs.registrar,
     );
+    serialize_certpair(
+        "registrar-agent-ca",
+        &path.join("registrar-agent"),
+        &certs.registrar_agent_ca,
+    );
     serialize_certpair(
         "registrar-agent",
         &path.join("registrar-agent"),
@@ -98,14 +108,17 @@ pub fn generate_certs() -> TestCerts {
         &registrar_ca_cert,
         &registrar_ca_keypair,
     );
+    // agents get their own CA, the registrar CA issues the LDevIDs of pledges
+    let (ra_ca_cert, ra_ca_keypair) =
+        registrar_cert::generate_owner_ca_cert("registrar-agent-ca.example.com Root CA");
     let (ra_cert, ra_keypair) = registrar_agent_cert::generate_regagt_cert(
         "registrar-agent.example.com",
-        &registrar_ca_cert,
-        &registrar_ca_keypair,
+        &ra_ca_cert,
+        &ra_ca_keypair,
     );
     let (idevid_cert, idevid_key) = pledge_cert::generate_idevid_cert(
         "00-D0-E5-F2-00-02",
-        "localhost:3000",
+        "http://localhost:3000",
         &vendor_ca_cert,
         &vendor_ca_keypair,
     );
@@ -115,6 +128,7 @@ pub fn generate_certs() -> TestCerts {
         vendor: (vendor_cert, vendor_keypair),
         registrar_ca: (registrar_ca_cert, registrar_ca_keypair),
         registrar: (registrar_cert, registrar_keypair),
+        registrar_agent_ca: (ra_ca_cert, ra_ca_keypair),
         registrar_agent: (ra_cert, ra_keypair),
         pledge: (idevid_cert, idevid_key),
     }
@@ -141,6 +155,7 @@ impl From<TestCerts> for OpensslTestCerts {
             vendor: convert_pair(value.vendor),
             registrar_ca: convert_pair(value.registrar_ca),
             registrar: convert_pair(value.registrar),
+            registrar_agent_ca: convert_pair(value.registrar_agent_ca),
             registrar_agent: convert_pair(value.registrar_agent),
             pledge: convert_pair(value.pledge),
         }
@@ -50,6 +50,10 @@ pub fn generate_vendor_cert(
     params.distinguished_name = dn;
     // what
     params.is_ca = rcgen::IsCa::ExplicitNoCa;
+    // the MASA also serves TLS with this certificate
+    params.subject_alt_names = vec![rcgen::SanType::DnsName(
+        rcgen::Ia5String::try_from("localhost").unwrap(),
+    )];
 
     // key pair
 
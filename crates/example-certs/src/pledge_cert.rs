@@ -25,7 +25,7 @@ pub fn generate_idevid_cert(
     params.is_ca = rcgen::IsCa::ExplicitNoCa;
     params.custom_extensions = vec![rcgen::CustomExtension::from_oid_content(
         &[1, 3, 6, 1, 5, 5, 7, 1, 32],
-        masa_url.as_bytes().to_vec(),
+        ia5_string_der(masa_url),
     )];
 
     // key pair
@@ -35,3 +35,22 @@ pub fn generate_idevid_cert(
 
     (cert, key_pair)
 }
+
+/// DER encoding of MASAURLSyntax, an IA5String (RFC 8995 2.3.2)
+fn ia5_string_der(value: &str) -> Vec<u8> {
+    let len = value.len();
+    let mut der = vec![0x16];
+    if len < 0x80 {
+        der.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        der.push(0x80 | len_bytes.len() as u8);
+        der.extend(len_bytes);
+    }
+    der.extend(value.as_bytes());
+    der
+}
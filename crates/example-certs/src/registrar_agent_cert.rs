@@ -33,8 +33,8 @@ pub fn generate_regagt_cert(
     );
 
     params.distinguished_name = dn;
-    // TODO we currently *must* errorusly set this to include a CA, or rcgen will not include the subject key identifier...
-    params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Constrained(0));
+    // rcgen only includes the subject key identifier if basic constraints are written, TLS needs cA to be false
+    params.is_ca = rcgen::IsCa::ExplicitNoCa;
 
     // todo find out what OID enabled CMS signing
     params.extended_key_usages = vec![
@@ -1,6 +1,6 @@
 use rcgen::{Certificate, CertificateParams};
 
-use rcgen::KeyPair;
+use rcgen::{Ia5String, KeyPair};
 use time::OffsetDateTime;
 
 pub fn generate_owner_ca_cert(common_name: &str) -> (Certificate, KeyPair) {
@@ -74,9 +74,15 @@ pub fn generate_owner_cert(
 
     params.key_usages = vec![rcgen::KeyUsagePurpose::KeyCertSign];
 
-    params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::Other(
-        [1, 3, 6, 1, 5, 5, 7, 3, 28].to_vec(),
-    )];
+    params.extended_key_usages = vec![
+        rcgen::ExtendedKeyUsagePurpose::Other([1, 3, 6, 1, 5, 5, 7, 3, 28].to_vec()),
+        // the registrar also serves TLS to registrar-agents
+        rcgen::ExtendedKeyUsagePurpose::ServerAuth,
+    ];
+    params.subject_alt_names = vec![
+        rcgen::SanType::DnsName(Ia5String::try_from(common_name).unwrap()),
+        rcgen::SanType::DnsName(Ia5String::try_from("localhost").unwrap()),
+    ];
 
     // key pair
 
@@ -1,9 +1,13 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
 pub use registrar_agent::{ParsedConfig, ServerState, PledgeCtx};
 
 use super::ffiblecommunicator::FFIBLECommunicator;
 
 pub struct Bootstrapper {
-    state: ServerState
+    state: ServerState,
+    /// Context and IDevID of every pledge bootstrapped, its status is queried with them
+    bootstrapped: Mutex<BTreeMap<String, (PledgeCtx, Vec<u8>)>>,
 }
 
 impl Bootstrapper {
@@ -13,19 +17,33 @@ impl Bootstrapper {
         let state = registrar_agent::get_state(&config, Box::new(communicator)).unwrap();
 
         Bootstrapper {
-            state
+            state,
+            bootstrapped: Mutex::default(),
         }
     }
 }
 
 impl Bootstrapper { 
+    /// Returns the DER encoded IDevID of the pledge, needed to query its status
     #[tracing::instrument(skip(self, serial_number), target = "RegistrarAgent", name="bootstrap")]
-    pub async fn bootstrap(self, serial_number: String) -> anyhow::Result<()> {
-        let ctx = PledgeCtx { ctx: "".to_string(), pledge_serial: serial_number, pledge_url: "".to_string(), ..Default::default() };
+    pub async fn bootstrap(&self, serial_number: String) -> anyhow::Result<Vec<u8>> {
+        let ctx = PledgeCtx { ctx: "".to_string(), pledge_serial: serial_number.clone(), pledge_url: "".to_string(), ..Default::default() };
         let res = registrar_agent::bootstrap_pledge(&self.state, &ctx).await;
-        if let Err(e) = res {   
-            return Err(anyhow::Error::new(e));
-        }
-        Ok(())
+        let idevid = match res {
+            Ok(bootstrap) => bootstrap.idevid.ok_or(anyhow::anyhow!("Pledge did not sign its PVR with an IDevID"))?,
+            Err(e) => return Err(anyhow::Error::new(e)),
+        };
+        self.bootstrapped.lock().unwrap().insert(serial_number, (ctx, idevid.clone()));
+        Ok(idevid)
     }
-}
\ No newline at end of file
+
+    /// Queries the signed status of a pledge bootstrapped through [Self::bootstrap], reports it to the registrar and returns it, e.g. `enroll-success`.
+    /// The pledge is reached through the context it was bootstrapped with and the status has to be signed with its IDevID.
+    #[tracing::instrument(skip(self, serial_number), target = "RegistrarAgent", name="query_status")]
+    pub async fn query_status(&self, serial_number: String) -> anyhow::Result<String> {
+        let (ctx, idevid) = self.bootstrapped.lock().unwrap().get(&serial_number).cloned()
+            .ok_or(anyhow::anyhow!("Pledge {} was not bootstrapped", serial_number))?;
+        let status = registrar_agent::report_pledge_status(&self.state, &ctx, &idevid).await.map_err(anyhow::Error::new)?;
+        Ok(status.status.to_string())
+    }
+}
@@ -15,6 +15,7 @@ pub struct FFIBLECommunicatorBuilder {
     pub ffi_send_enroll_response: Option<Arc<Box<dyn Fn(Vec<u8>, PledgeCtx) -> DartFnFuture<Vec<u8>> + Sync + Send>>>,
     pub ffi_get_data_interchange_format: Option<Arc<Box<dyn Fn(DiscoveredPledge) -> DartFnFuture<String> + Sync + Send>>>,
     pub ffi_get_pledge_info: Option<Arc<Box<dyn Fn(DiscoveredPledge, DataInterchangeFormat) -> DartFnFuture<Vec<u8>> + Sync + Send>>>,
+    pub ffi_query_pledge_status: Option<Arc<Box<dyn Fn(Vec<u8>, PledgeCtx) -> DartFnFuture<Vec<u8>> + Sync + Send>>>,
 }
 
 impl FFIBLECommunicatorBuilder {
@@ -71,6 +72,13 @@ impl FFIBLECommunicatorBuilder {
         }
     } 
 
+    pub fn set_pledge_status_ffi(self, callback: impl Fn(Vec<u8>, PledgeCtx) -> DartFnFuture<Vec<u8>> + Sync + Send + 'static) -> Self {
+        FFIBLECommunicatorBuilder {
+            ffi_query_pledge_status: Some(Arc::new(Box::new(callback))),
+            ..self
+        }
+    }
+
     pub fn build (self) -> FFIBLECommunicator {
         FFIBLECommunicator {
             ffi_send_pvr_trigger: self.ffi_send_pvr_trigger.expect("ffi_send_pvr_trigger is required"),
@@ -80,6 +88,8 @@ impl FFIBLECommunicatorBuilder {
             ffi_send_enroll_response: self.ffi_send_enroll_response.expect("ffi_send_enroll_response is required"),
             ffi_get_data_interchange_format: self.ffi_get_data_interchange_format.expect("ffi_get_data_interchange_format is required"),
             ffi_get_pledge_info: self.ffi_get_pledge_info.expect("ffi_get_pledge_info is required"),
+            // optional, apps that never query the pledge status need not provide it
+            ffi_query_pledge_status: self.ffi_query_pledge_status,
         }
     }
 
@@ -96,6 +106,7 @@ pub struct FFIBLECommunicator {
     ffi_send_enroll_response: Arc<Box<dyn Fn(Vec<u8>, PledgeCtx) -> DartFnFuture<Vec<u8>> + Sync + Send>>,
     ffi_get_data_interchange_format: Arc<Box<dyn Fn(DiscoveredPledge) -> DartFnFuture<String> + Sync + Send>>,
     ffi_get_pledge_info: Arc<Box<dyn Fn(DiscoveredPledge, DataInterchangeFormat) -> DartFnFuture<Vec<u8>> + Sync + Send>>,
+    ffi_query_pledge_status: Option<Arc<Box<dyn Fn(Vec<u8>, PledgeCtx) -> DartFnFuture<Vec<u8>> + Sync + Send>>>,
 }
 
 #[async_trait::async_trait]
@@ -130,6 +141,15 @@ impl PledgeCommunicator for FFIBLECommunicator {
         Ok(result)
     }
 
+    #[tracing::instrument(skip(self, ctx), target = "RegistrarAgent", name="query_pledge_status")]
+    async fn query_pledge_status(&self, query: Vec<u8>, ctx: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+        let Some(ffi_query_pledge_status) = &self.ffi_query_pledge_status else {
+            return Err(ServerError::BadRequestWithReason("No pledge status callback was set".to_string()));
+        };
+        let result = (ffi_query_pledge_status)(query, ctx).await;
+        Ok(result)
+    }
+
     #[tracing::instrument(skip(self), target = "RegistrarAgent", name="send_enroll_response")]
     async fn get_data_interchange_format(&self, pledge: DiscoveredPledge) -> Result<String, ServerError> {
         let result = (self.ffi_get_data_interchange_format)(pledge).await;
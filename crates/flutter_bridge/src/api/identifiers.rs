@@ -28,6 +28,7 @@ pub struct BleIdentifiers {
     pub voucher: Identifier,
     pub ca_certs: Identifier,
     pub enroll_response: Identifier,
+    pub pledge_status: Identifier,
 } 
 
 impl BleIdentifiers {
@@ -58,6 +59,11 @@ impl BleIdentifiers {
                 read_uuid: ENROLL_RESPONSE_READ_UUID.to_string(),
                 write_uuid: ENROLL_RESPONSE_WRITE_UUID.to_string(),
             },
+            pledge_status: Identifier {
+                uuid: PLEDGE_STATUS_UUID.to_string(),
+                read_uuid: PLEDGE_STATUS_READ_UUID.to_string(),
+                write_uuid: PLEDGE_STATUS_WRITE_UUID.to_string(),
+            },
         }
     }
 
@@ -80,6 +86,10 @@ impl BleIdentifiers {
     pub fn get_enroll_response(&self) -> Identifier {
         self.enroll_response.clone()
     }
+
+    pub fn get_pledge_status(&self) -> Identifier {
+        self.pledge_status.clone()
+    }
 }
 
 pub fn get_identifiers() -> BleIdentifiers {
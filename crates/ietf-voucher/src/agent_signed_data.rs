@@ -1,7 +1,7 @@
 use core::{fmt::Display, ops::Deref};
 use std::marker::PhantomData;
 
-use crate::error::VoucherError;
+use crate::{error::VoucherError, protocol_version::ProtocolVersion};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumIs};
@@ -20,18 +20,114 @@ pub struct AgentData {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "AgentSignedDataWire", into = "AgentSignedDataWire")]
 pub struct AgentSignedData {
-    #[serde(rename = "ietf-voucher-request-prm:agent-signed-data")]
     pub data: AgentData,
+
+    /// The revision the root member name is taken from. See [ProtocolVersion].
+    pub protocol_version: ProtocolVersion,
+}
+
+/// The encoded form of [AgentSignedData]. Exactly one of the members is set.
+#[derive(Serialize, Deserialize)]
+struct AgentSignedDataWire {
+    #[serde(
+        rename = "ietf-voucher-request-prm:agent-signed-data",
+        skip_serializing_if = "Option::is_none"
+    )]
+    draft: Option<AgentData>,
+
+    #[serde(
+        rename = "ietf-voucher-request:agent-signed-data",
+        skip_serializing_if = "Option::is_none"
+    )]
+    rfc: Option<AgentData>,
+}
+
+impl From<AgentSignedData> for AgentSignedDataWire {
+    fn from(value: AgentSignedData) -> Self {
+        match value.protocol_version {
+            ProtocolVersion::Draft => Self {
+                draft: Some(value.data),
+                rfc: None,
+            },
+            ProtocolVersion::Rfc => Self {
+                draft: None,
+                rfc: Some(value.data),
+            },
+        }
+    }
+}
+
+impl TryFrom<AgentSignedDataWire> for AgentSignedData {
+    type Error = VoucherError;
+
+    fn try_from(value: AgentSignedDataWire) -> Result<Self, Self::Error> {
+        match (value.draft, value.rfc) {
+            (Some(data), None) => Ok(Self {
+                data,
+                protocol_version: ProtocolVersion::Draft,
+            }),
+            (None, Some(data)) => Ok(Self {
+                data,
+                protocol_version: ProtocolVersion::Rfc,
+            }),
+            (None, None) => Err(VoucherError::MalformedAgentSignedData(
+                "Missing agent-signed-data root".to_string(),
+            )),
+            (Some(_), Some(_)) => Err(VoucherError::MalformedAgentSignedData(
+                "Agent-signed-data root present in more than one revision".to_string(),
+            )),
+        }
+    }
 }
 
 impl AgentSignedData {
     pub fn new(created_on: DateTime<Utc>, serial_number: String) -> Self {
+        Self::with_protocol_version(created_on, serial_number, ProtocolVersion::default())
+    }
+
+    pub fn with_protocol_version(
+        created_on: DateTime<Utc>,
+        serial_number: String,
+        protocol_version: ProtocolVersion,
+    ) -> Self {
         Self {
             data: AgentData {
                 created_on,
                 serial_number,
             },
+            protocol_version,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn it_round_trips_both_revisions() {
+        let draft = r#"{"ietf-voucher-request-prm:agent-signed-data":{"created-on":"2022-04-26T05:07:41.448Z","serial-number":"0123456789"}}"#;
+        let rfc = r#"{"ietf-voucher-request:agent-signed-data":{"created-on":"2022-04-26T05:07:41.448Z","serial-number":"0123456789"}}"#;
+
+        let parsed_draft: AgentSignedData = serde_json::from_str(draft).unwrap();
+        let parsed_rfc: AgentSignedData = serde_json::from_str(rfc).unwrap();
+
+        assert_eq!(parsed_draft.protocol_version, ProtocolVersion::Draft);
+        assert_eq!(parsed_rfc.protocol_version, ProtocolVersion::Rfc);
+        assert_eq!(parsed_draft.data, parsed_rfc.data);
+
+        assert_eq!(serde_json::to_string(&parsed_draft).unwrap(), draft);
+        assert_eq!(serde_json::to_string(&parsed_rfc).unwrap(), rfc);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn it_rejects_a_missing_root() {
+        let json = r#"{"agent-signed-data":{"created-on":"2022-04-26T05:07:41.448Z","serial-number":"0123456789"}}"#;
+
+        assert!(serde_json::from_str::<AgentSignedData>(json).is_err());
+    }
+}
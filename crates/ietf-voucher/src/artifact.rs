@@ -29,6 +29,7 @@ impl VoucherArtifact {
 pub struct VoucherArtifactDetails {
     /// A value indicating the date this voucher was created.
     /// This node is primarily for human consumption and auditing.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub created_on: Option<DateTime<Utc>>,
 
     /// A value indicating when this voucher expires.
@@ -36,9 +37,11 @@ pub struct VoucherArtifactDetails {
     /// If this field exists, then the pledges MUST ensure that the expires-on time has not yet passed.
     /// A pledge without an accurate clock cannot meet this requirement.
     /// The expires-on value MUST NOT exceed the expiration date of any of the listed ’pinned-domain-cert’ certificates.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_on: Option<DateTime<Utc>>,
 
     /// Indicates that the ownership has been positively verified by the MASA (e.g., through sales channel integration)."
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub assertion: Option<Assertion>,
 
     /// The serial-number of the hardware.
@@ -52,12 +55,15 @@ pub struct VoucherArtifactDetails {
     /// When processing a voucher, a pledge MUST ensure that its IDevID Authority Key Identifier matches this value.
     /// If no match occurs, then the pledge MUST NOT process this voucher.
     /// When issuing a voucher, the MASA MUST ensure that this field is populated for serial-numbers that are not otherwise unique within the scope of the MASA.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub idevid_issuer: Option<Vec<u8>>,
 
     /// An X.509 v3 certificate structure, as specified by RFC 5280, using Distinguished Encoding Rules (DER) encoding, as defined in ITU-T X.690.
     /// This certificate is used by a pledge to trust a Public Key Infrastructure in order to verify a domain certificate supplied to the pledge separately by the bootstrapping protocol.
     /// The domain certificate MUST have this certificate somewhere in its chain of certificates.
     /// This certificate MAY be an end-entity certificate, including a self-signed entity.    
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub pinned_domain_cert: Option<crate::util::pki::X509>,
 
@@ -71,6 +77,7 @@ pub struct VoucherArtifactDetails {
     /// When present, the pledge MUST compare the provided nonce value with another value that the pledge randomly generated and sent to a bootstrap server in an earlier bootstrapping message.
     /// If the values do not match, then the pledge MUST NOT process this voucher.
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub nonce: Option<Vec<u8>>,
 
@@ -79,6 +86,7 @@ pub struct VoucherArtifactDetails {
     /// This field is encoded as a Subject Public Key Info block as specified in RFC7250, in section 3.
     /// The ECDSA algorithm MUST be supported. The EdDSA algorithm as specified in draft-ietf-tls-rfc4492bis-17 SHOULD be supported.
     /// Support for the DSA algorithm is not recommended. Support for the RSA algorithm is a MAY.
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub pinned_domain_pubk: Option<crate::util::pki::Pkey>,
 
@@ -86,6 +94,7 @@ pub struct VoucherArtifactDetails {
     /// In many cases the public key of the domain has already been transmitted during the key agreement process, and it is wastefu to transmit the public key another two times.
     /// The use of a hash of public key info, at 32-bytes for sha256 is a significant savings compared to an RSA public key, but is only a minor savings compared to a 256-bit ECDSA public-key.
     /// Algorithm agility is provided by extensions to this specification which can define a new leaf for another hash type.
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub pinned_domain_pubk_sha256: Option<Vec<u8>>,
 
@@ -93,17 +102,20 @@ pub struct VoucherArtifactDetails {
     /// This field is merely informative; it is not processed by pledges.
     /// Circumstances may occur after a voucher is generated that may alter a voucher’s validity period.
     /// For instance, a vendor may associate validity periods with support contracts, which may be terminated or extended over time.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_renewal_date: Option<DateTime<Utc>>,
 
     // TODO convert to ietf:uri
     // The est-domain is a URL to which the Pledge should continue doing enrollment rather than with the Cloud Registrar.
     // The pinned-domain-cert contains a trust-anchor which is to be used to authenticate the server found at this URI
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub est_domain: Option<String>,
 
     // TODO convert to ietf:uri
     // The additional-configuration attribute contains a URL to which the Pledge can retrieve additional configuration information.
     // The contents of this URL are vendor specific.
     // This is intended to do things like configure a VoIP phone to point to the correct hosted PBX, for example.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub additional_configuration: Option<String>,
 }
 
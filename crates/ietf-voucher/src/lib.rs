@@ -21,6 +21,7 @@ pub mod target;
 pub mod verified;
 
 pub mod agent_signed_data;
+pub mod protocol_version;
 
 mod util;
 pub use util::pki;
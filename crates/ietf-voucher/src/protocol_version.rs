@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+/// The BRSKI-PRM revision an artifact is encoded for.
+///
+/// BRSKI-PRM renamed several members between its drafts and the published document.
+/// Artifacts affected by this remember the revision they were read in and are written back in the same one.
+/// Deserialization accepts the member names of every revision, so only the sending side has to pick one.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Display, EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum ProtocolVersion {
+    /// Member names of the earlier drafts:
+    /// `agent-signed-proximity-cert` in the voucher-request trigger,
+    /// `ietf-voucher-request-prm:agent-signed-data` as the agent-signed-data root
+    /// and the CSR of an enroll-request wrapped in `ietf-ztp-types`.
+    #[default]
+    Draft,
+
+    /// Member names of the published document:
+    /// `agent-provided-proximity-registrar-cert` in the voucher-request trigger,
+    /// `ietf-voucher-request:agent-signed-data` as the agent-signed-data root
+    /// and a bare `p10-csr` in the enroll-request.
+    Rfc,
+}
@@ -51,6 +51,7 @@ pub struct VoucherRequestArtifactDetails {
     /// If no match occurs, then the pledge MUST NOT process this voucher.
     /// When issuing a voucher, the MASA MUST ensure that this field is populated for serial-numbers that are not otherwise unique within the scope of the MASA.
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub idevid_issuer: Option<Vec<u8>>,
 
     /// A pinned domain certificate is not valid in a voucher equest, and any occurence must be ignored. To facilitate this, this field is non-public. It will also *not* be deserialized
@@ -118,6 +119,7 @@ pub struct VoucherRequestArtifactDetails {
     /// For example this information could be useful to a MASA to determine that both pledge and registrar agree on proximity assertions.
     /// The MASA SHOULD remove all prior-signed-voucher-request information when signing a voucher for imprinting so as to minimize the final voucher size."
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "json", serde_as(as = "Option<Base64>"))]
     pub prior_signed_voucher_request: Option<Vec<u8>>,
 
     /// An X.509 v3 certificate structure as specified by RFC 5280, Section 4 encoded using the ASN.1 distinguished encoding rules (DER), as specified in [ITU.X690.1994].
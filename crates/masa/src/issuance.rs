@@ -0,0 +1,144 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use brski_prm_artifacts::{
+    audit_log::AuditLogEvent,
+    ietf_voucher::{
+        artifact::{VoucherArtifact, VoucherArtifactDetails},
+        assertion::Assertion,
+        pki::X509,
+    },
+    issued_voucher::IssuedVoucher,
+    token_type::VoucherTokenType,
+};
+use chrono::{DateTime, Utc};
+use common::{server_error::ServerError, util::domain_id};
+use signeable_payload::signeable::{
+    signed::Signed, signing_context::BasicSigningContext, unsigned::Unsigned,
+};
+use tracing::{event, Level};
+
+use crate::{
+    ownership::{choose_assertion, owner_domain_ids},
+    storage::LoggedVoucher,
+    tenants::Tenant,
+};
+
+/// Builds the voucher pinning `cert_to_pin` for the pledge, returns it with the domainID of the pinned certificate.
+/// `requested` is the assertion of the verified PVR, if there was one.
+pub(crate) async fn voucher_details(
+    tenant: &Tenant,
+    serial_number: String,
+    nonce: Option<Vec<u8>>,
+    cert_to_pin: X509,
+    requested: Option<&Assertion>,
+    now: DateTime<Utc>,
+) -> Result<(VoucherArtifactDetails, String), ServerError> {
+    let registrar_domain_id = domain_id(&cert_to_pin)?;
+
+    let owner = tenant.storage.get_owner(&serial_number).await?;
+    let former_owners = tenant.storage.former_owners(&serial_number).await?;
+    let assertion = choose_assertion(
+        &serial_number,
+        owner.as_ref(),
+        &former_owners,
+        &owner_domain_ids(&cert_to_pin)?,
+        requested,
+        tenant.config.unowned_devices,
+    )?;
+    event!(
+        Level::INFO,
+        "Issuing voucher with assertion {:?}",
+        assertion
+    );
+
+    event!(Level::INFO, "Building voucher");
+    let mut voucher_details = VoucherArtifactDetails::default();
+
+    voucher_details.assertion = Some(assertion);
+    voucher_details.serial_number = serial_number;
+    voucher_details.nonce = nonce;
+    voucher_details.created_on = Some(now);
+
+    // Without nonce the voucher could be replayed forever, so it expires and has to be renewed
+    if voucher_details.nonce.is_none() {
+        tenant
+            .config
+            .lifetime
+            .issue(&mut voucher_details, &cert_to_pin, now)?;
+        event!(
+            Level::INFO,
+            "Issuing nonceless voucher expiring on {:?}",
+            voucher_details.expires_on
+        );
+    }
+    voucher_details.pinned_domain_cert = Some(cert_to_pin);
+
+    Ok((voucher_details, registrar_domain_id))
+}
+
+/// Signs the voucher for the tenant and records it in the audit log of the pledge and the transparency log
+pub(crate) async fn issue_voucher(
+    tenant: &Tenant,
+    voucher_details: VoucherArtifactDetails,
+    voucher_token_type: VoucherTokenType,
+    domain_id: String,
+) -> Result<Signed<VoucherArtifact>, ServerError> {
+    // RFC 8995 5.8.1, every issued voucher ends up in the audit log of the pledge
+    let issuance = AuditLogEvent {
+        date: chrono::Utc::now(),
+        domain_id,
+        nonce: voucher_details
+            .nonce
+            .as_ref()
+            .map(|nonce| URL_SAFE_NO_PAD.encode(nonce)),
+        assertion: voucher_details.assertion.clone(),
+        truncated: None,
+    };
+    let serial_number = voucher_details.serial_number.clone();
+
+    let voucher_artifact = VoucherArtifact {
+        details: voucher_details,
+    };
+
+    let issued_voucher = IssuedVoucher::try_new(
+        voucher_artifact,
+        tenant.signing_chain(),
+        voucher_token_type.clone(),
+    )?;
+
+    event!(Level::INFO, "Built Voucher");
+    event!(Level::DEBUG, "Issued Voucher: {:#?}", issued_voucher);
+
+    let unsigned_va: Unsigned<VoucherArtifact> = issued_voucher.try_into()?;
+
+    let signer = voucher_token_type
+        .signature_type()
+        .get_sv::<VoucherArtifact>()?;
+
+    let signeable_va = unsigned_va.into_signeable_boxed(signer);
+
+    let ctx = BasicSigningContext::new();
+
+    event!(Level::INFO, "Signing voucher");
+    let signed = signeable_va.sign(tenant.config.masa_key.clone(), ctx)?;
+
+    // Assertion::Logged promises the issuance can be detected, the transparency log makes it so
+    let leaf_index = tenant
+        .storage
+        .record_issuance(
+            &serial_number,
+            issuance,
+            LoggedVoucher {
+                content_type: voucher_token_type.as_content_type().to_string(),
+                voucher: signed.data(),
+            },
+        )
+        .await?;
+    tenant.log.catch_up(tenant.storage.as_ref()).await?;
+
+    event!(
+        Level::INFO,
+        "Issued voucher, entry {} of the transparency log",
+        leaf_index
+    );
+    Ok(signed)
+}
@@ -1,12 +1,25 @@
+mod issuance;
+mod lifetime;
+mod offline;
+mod ownership;
 mod parsed_config;
+mod registrars;
+mod rvr;
 mod server;
+mod storage;
+mod tenants;
+mod tls;
+mod transparency;
 
 use cli::config::MasaConfig;
-use common::error::AppError;
+use common::{error::AppError, tls::ClientCertAcceptor};
 use parsed_config::parse_config;
+use registrars::TrustedRegistrars;
 use tokio::task::JoinHandle;
 use tracing::{event, Level};
 
+pub use offline::issue;
+
 #[tracing::instrument(target = "MASA", skip(config), name = "MASA::start")]
 pub async fn start(config: MasaConfig) -> anyhow::Result<JoinHandle<()>, AppError> {
     let address = "0.0.0.0:".to_owned() + &config.port;
@@ -17,11 +30,29 @@ pub async fn start(config: MasaConfig) -> anyhow::Result<JoinHandle<()>, AppErro
 
     let parsed_config = parse_config(config)?;
 
-    let app = server::get_app(&parsed_config).await?;
-
-    let listener = tokio::net::TcpListener::bind(parsed_address).await?;
-
-    let server_handle = tokio::spawn(async { axum::serve(listener, app).await.unwrap() });
+    let registrars = TrustedRegistrars::load(&parsed_config)?;
+
+    let app = server::get_app(&parsed_config, registrars.clone()).await?;
+
+    let server_handle = match parsed_config.config.use_tls {
+        true => {
+            event!(
+                Level::INFO,
+                "Serving TLS, client authentication required: {}",
+                parsed_config.config.require_client_auth
+            );
+            let tls_config = tls::server_config(&parsed_config, registrars)?;
+            let server =
+                axum_server::bind(*parsed_address).acceptor(ClientCertAcceptor::new(tls_config));
+
+            tokio::spawn(async { server.serve(app.into_make_service()).await.unwrap() })
+        }
+        false => {
+            let listener = tokio::net::TcpListener::bind(parsed_address).await?;
+
+            tokio::spawn(async { axum::serve(listener, app).await.unwrap() })
+        }
+    };
 
     Ok(server_handle)
 }
@@ -0,0 +1,190 @@
+use brski_prm_artifacts::ietf_voucher::{artifact::VoucherArtifactDetails, assertion::Assertion};
+use chrono::{DateTime, Duration, Utc};
+use cli::config::NoncelessVoucherPolicy;
+use common::server_error::ServerError;
+use openssl::{asn1::Asn1Time, x509::X509Ref};
+
+/// Why the MASA does not issue or renew a nonceless voucher
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum LifetimeError {
+    #[error("Nonceless vouchers are not issued for {0:?} assertions")]
+    NoncelessNotAllowed(Assertion),
+
+    #[error("Pinned domain certificate has expired")]
+    PinnedCertificateExpired,
+
+    #[error("Can not read the expiry of the pinned domain certificate: {0}")]
+    InvalidPinnedCertificate(String),
+
+    #[error("Only nonceless vouchers with last-renewal-date can be renewed")]
+    NotRenewable,
+
+    #[error("Voucher could be renewed until {0}")]
+    RenewalPeriodOver(DateTime<Utc>),
+}
+
+impl From<LifetimeError> for ServerError {
+    fn from(value: LifetimeError) -> Self {
+        match value {
+            LifetimeError::NotRenewable => ServerError::BadRequestWithReason(value.to_string()),
+            _ => ServerError::Forbidden(value.to_string()),
+        }
+    }
+}
+
+/// How long nonceless vouchers are valid and renewable
+#[derive(Debug, Clone)]
+pub(crate) struct VoucherLifetime {
+    policy: NoncelessVoucherPolicy,
+    lifetime: Duration,
+    renewal_period: Duration,
+}
+
+impl VoucherLifetime {
+    /// `voucher_lifetime` and `renewal_period` are in seconds
+    pub(crate) fn new(
+        policy: NoncelessVoucherPolicy,
+        voucher_lifetime: u64,
+        renewal_period: u64,
+    ) -> Self {
+        Self {
+            policy,
+            lifetime: Duration::seconds(voucher_lifetime as i64),
+            renewal_period: Duration::seconds(renewal_period as i64),
+        }
+    }
+
+    /// Sets `expires-on` and `last-renewal-date` of a voucher without nonce issued `now`
+    pub(crate) fn issue(
+        &self,
+        details: &mut VoucherArtifactDetails,
+        pinned: &X509Ref,
+        now: DateTime<Utc>,
+    ) -> Result<(), LifetimeError> {
+        self.allow(details.assertion.as_ref())?;
+        let not_after = not_after(pinned)?;
+
+        let last_renewal_date = (now + self.renewal_period).min(not_after);
+        details.expires_on = Some(self.expires_on(now, not_after)?);
+        details.last_renewal_date = Some(last_renewal_date);
+        Ok(())
+    }
+
+    /// Moves `expires-on` of a previously issued nonceless voucher, its `last-renewal-date` stays
+    pub(crate) fn renew(
+        &self,
+        details: &mut VoucherArtifactDetails,
+        pinned: &X509Ref,
+        now: DateTime<Utc>,
+    ) -> Result<(), LifetimeError> {
+        let last_renewal_date = match (&details.nonce, details.last_renewal_date) {
+            (None, Some(last_renewal_date)) => last_renewal_date,
+            _ => return Err(LifetimeError::NotRenewable),
+        };
+        if now > last_renewal_date {
+            return Err(LifetimeError::RenewalPeriodOver(last_renewal_date));
+        }
+        self.allow(details.assertion.as_ref())?;
+
+        details.expires_on = Some(self.expires_on(now, not_after(pinned)?)?);
+        Ok(())
+    }
+
+    fn allow(&self, assertion: Option<&Assertion>) -> Result<(), LifetimeError> {
+        let assertion = assertion.cloned().unwrap_or(Assertion::Logged);
+        match (self.policy, &assertion) {
+            (NoncelessVoucherPolicy::Always, _)
+            | (NoncelessVoucherPolicy::Owned, Assertion::Verified) => Ok(()),
+            _ => Err(LifetimeError::NoncelessNotAllowed(assertion)),
+        }
+    }
+
+    /// A voucher is valid for one lifetime, but never outlives the pinned certificate
+    fn expires_on(
+        &self,
+        now: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, LifetimeError> {
+        if not_after <= now {
+            return Err(LifetimeError::PinnedCertificateExpired);
+        }
+        Ok((now + self.lifetime).min(not_after))
+    }
+}
+
+fn not_after(certificate: &X509Ref) -> Result<DateTime<Utc>, LifetimeError> {
+    let invalid =
+        |e: openssl::error::ErrorStack| LifetimeError::InvalidPinnedCertificate(e.to_string());
+    let since_epoch = Asn1Time::from_unix(0)
+        .map_err(invalid)?
+        .diff(certificate.not_after())
+        .map_err(invalid)?;
+
+    Ok(DateTime::UNIX_EPOCH
+        + Duration::days(since_epoch.days as i64)
+        + Duration::seconds(since_epoch.secs as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use cli::config::MasaConfig;
+    use example_certs::{generate_certs, OpensslTestCerts};
+
+    use super::*;
+
+    #[test]
+    fn it_bounds_nonceless_vouchers() {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let pinned = &certs.registrar_ca.0;
+        let config = MasaConfig::default();
+        let lifetime = VoucherLifetime::new(
+            config.nonceless_vouchers,
+            config.voucher_lifetime,
+            config.renewal_period,
+        );
+        let now = Utc::now();
+
+        let mut details = VoucherArtifactDetails::default();
+        details.assertion = Some(Assertion::Logged);
+        assert!(matches!(
+            lifetime.issue(&mut details, pinned, now),
+            Err(LifetimeError::NoncelessNotAllowed(Assertion::Logged))
+        ));
+
+        details.assertion = Some(Assertion::Verified);
+        lifetime.issue(&mut details, pinned, now).unwrap();
+        let not_after = not_after(pinned).unwrap();
+        let last_renewal_date = details.last_renewal_date.unwrap();
+        assert_eq!(
+            last_renewal_date,
+            (now + Duration::days(365)).min(not_after)
+        );
+        assert_eq!(
+            details.expires_on.unwrap(),
+            (now + Duration::days(14)).min(not_after)
+        );
+
+        let later = now + Duration::days(7);
+        lifetime.renew(&mut details, pinned, later).unwrap();
+        assert_eq!(
+            details.expires_on.unwrap(),
+            (later + Duration::days(14)).min(not_after)
+        );
+        assert_eq!(details.last_renewal_date, Some(last_renewal_date));
+
+        assert!(matches!(
+            lifetime.renew(
+                &mut details,
+                pinned,
+                last_renewal_date + Duration::seconds(1)
+            ),
+            Err(LifetimeError::RenewalPeriodOver(_))
+        ));
+
+        details.nonce = Some(b"nonce".to_vec());
+        assert!(matches!(
+            lifetime.renew(&mut details, pinned, later),
+            Err(LifetimeError::NotRenewable)
+        ));
+    }
+}
@@ -0,0 +1,275 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use brski_prm_artifacts::{ietf_voucher::artifact::VoucherArtifact, token_type::VoucherTokenType};
+use chrono::{DateTime, Utc};
+use cli::{config::MasaConfig, IssueArgs, VoucherFormat};
+use common::{error::AppError, server_error::ServerError, util::voucher_file_name};
+use openssl::x509::X509;
+use signeable_payload::signeable::signed::Signed;
+use tracing::{event, Level};
+
+use crate::{
+    issuance::{issue_voucher, voucher_details},
+    parsed_config::parse_config,
+    registrars::TrustedRegistrars,
+    rvr::{RvrVerifier, VerifiedRequest},
+    tenants::{Tenant, Tenants},
+};
+
+/// What a voucher is pre-issued for, read from the input file of `open-brski masa issue`
+enum OfflineRequest {
+    /// A base64 encoded RVR of a registrar trusted by the MASA
+    Rvr(String),
+    /// The serial number of a pledge, the voucher pins `domain_cert`
+    Serial {
+        serial_number: String,
+        domain_cert: X509,
+    },
+}
+
+/// Issues nonceless vouchers for sites that never reach the MASA and writes them to `args.output`.
+/// Every voucher is recorded in the audit and transparency log like one issued online.
+#[tracing::instrument(target = "MASA", skip(config), name = "MASA::issue")]
+pub async fn issue(config: MasaConfig, args: IssueArgs) -> anyhow::Result<(), AppError> {
+    let parsed_config = parse_config(config)?;
+    let registrars = TrustedRegistrars::load(&parsed_config)?;
+    let tenants = Tenants::open(&parsed_config)?;
+    let verifier = RvrVerifier {
+        tenants: &tenants,
+        registrars: &registrars,
+    };
+
+    let token_type = match args.format {
+        VoucherFormat::Jws => VoucherTokenType::JWS,
+        VoucherFormat::Cose => VoucherTokenType::COSE,
+    };
+
+    let requests = match (&args.rvrs, &args.serials) {
+        (Some(rvrs), _) => read_lines(rvrs, OfflineRequest::Rvr)?,
+        (None, Some(serials)) => {
+            let domain_cert = args
+                .domain_cert
+                .as_ref()
+                .ok_or(anyhow!("--serials requires --domain-cert"))?;
+            let domain_cert = X509::from_pem(&std::fs::read(domain_cert)?)?;
+            read_lines(serials, |serial_number| OfflineRequest::Serial {
+                serial_number,
+                domain_cert: domain_cert.clone(),
+            })?
+        }
+        (None, None) => return Err(anyhow!("Either --rvrs or --serials is required").into()),
+    };
+    let tenant = tenants
+        .get(args.tenant.as_deref())
+        .map_err(|_| anyhow!("Unknown tenant {:?}", args.tenant))?;
+
+    std::fs::create_dir_all(&args.output)?;
+
+    let mut failed = 0;
+    for request in &requests {
+        let issued = match request {
+            OfflineRequest::Rvr(rvr) => issue_for_rvr(&verifier, rvr, token_type, Utc::now()).await,
+            OfflineRequest::Serial {
+                serial_number,
+                domain_cert,
+            } => {
+                issue_for_serial(
+                    tenant,
+                    serial_number,
+                    domain_cert.clone(),
+                    token_type,
+                    Utc::now(),
+                )
+                .await
+            }
+        };
+
+        if let Err(e) = issued.and_then(|(serial_number, voucher)| {
+            write_voucher(&args.output, &serial_number, token_type, voucher)
+        }) {
+            event!(Level::ERROR, "Could not pre-issue voucher: {}", e);
+            failed += 1;
+        }
+    }
+
+    event!(
+        Level::INFO,
+        "Pre-issued {} of {} vouchers into {:?}",
+        requests.len() - failed,
+        requests.len(),
+        args.output
+    );
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} vouchers could not be issued",
+            failed,
+            requests.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// One request per non-empty line, lines starting with `#` are comments
+fn read_lines(
+    path: &Path,
+    request: impl Fn(String) -> OfflineRequest,
+) -> anyhow::Result<Vec<OfflineRequest>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| request(line.to_string()))
+        .collect())
+}
+
+/// A voucher for the pledge of the RVR, nonceless even if the PVR carried a nonce,
+/// as it is only presented to the pledge in a later onboarding
+async fn issue_for_rvr(
+    verifier: &RvrVerifier<'_>,
+    rvr: &str,
+    token_type: VoucherTokenType,
+    now: DateTime<Utc>,
+) -> Result<(String, Signed<VoucherArtifact>), ServerError> {
+    let rvr = STANDARD
+        .decode(rvr)
+        .map_err(|e| ServerError::BadRequestWithReason(e.to_string()))?;
+    let VerifiedRequest { rvr, pvr, tenant } =
+        verifier.verify(&rvr, token_type.signature_type(), None, now)?;
+
+    let cert_to_pin = rvr.details.agent_provided_proximity_registrar_cert.ok_or(
+        ServerError::BadRequestWithReason(
+            "Registrar did not provide certificate to pin".to_string(),
+        ),
+    )?;
+    let serial_number = rvr.details.serial_number;
+    let (details, domain_id) = voucher_details(
+        tenant,
+        serial_number.clone(),
+        None,
+        cert_to_pin,
+        pvr.details.assertion.as_ref(),
+        now,
+    )
+    .await?;
+
+    let voucher = issue_voucher(tenant, details, token_type, domain_id).await?;
+    Ok((serial_number, voucher))
+}
+
+/// A nonceless voucher pinning `domain_cert`, without PVR only ownership can justify it
+async fn issue_for_serial(
+    tenant: &Tenant,
+    serial_number: &str,
+    domain_cert: X509,
+    token_type: VoucherTokenType,
+    now: DateTime<Utc>,
+) -> Result<(String, Signed<VoucherArtifact>), ServerError> {
+    let (details, domain_id) = voucher_details(
+        tenant,
+        serial_number.to_string(),
+        None,
+        domain_cert.into(),
+        None,
+        now,
+    )
+    .await?;
+
+    let voucher = issue_voucher(tenant, details, token_type, domain_id).await?;
+    Ok((serial_number.to_string(), voucher))
+}
+
+fn write_voucher(
+    output: &Path,
+    serial_number: &str,
+    token_type: VoucherTokenType,
+    voucher: Signed<VoucherArtifact>,
+) -> Result<(), ServerError> {
+    let file_name = voucher_file_name(serial_number, &token_type).ok_or_else(|| {
+        ServerError::BadRequestWithReason(format!(
+            "Serial number {} can not be a file name",
+            serial_number
+        ))
+    })?;
+    std::fs::write(output.join(&file_name), voucher.data())?;
+
+    event!(Level::INFO, "Wrote voucher {}", file_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use example_certs::{generate_certs, OpensslTestCerts};
+    use signeable_payload::signeable::raw_signed::RawSigned;
+
+    use super::*;
+    use crate::storage::OwnershipRecord;
+
+    #[tokio::test]
+    async fn it_pre_issues_nonceless_vouchers() {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let (vendor, vendor_key) = &certs.vendor;
+        let tenant = Tenant::in_memory(
+            "default",
+            certs.vendor_ca.0.clone(),
+            vendor.clone(),
+            vendor_key,
+        );
+        let domain_cert = certs.registrar.0.clone();
+        tenant
+            .storage
+            .set_owners(vec![OwnershipRecord {
+                serial_number: "00-D0-E5-F2-00-02".to_string(),
+                domain_id: common::util::domain_id(&domain_cert).unwrap(),
+                owner: None,
+                updated_at: Utc::now(),
+            }])
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        let (serial_number, voucher) = issue_for_serial(
+            &tenant,
+            "00-D0-E5-F2-00-02",
+            domain_cert.clone(),
+            VoucherTokenType::JWS,
+            now,
+        )
+        .await
+        .unwrap();
+        assert_eq!(serial_number, "00-D0-E5-F2-00-02");
+
+        let verified = RawSigned::<VoucherArtifact>::new(voucher.data())
+            .into_verifyable_boxed(VoucherTokenType::JWS.signature_type().get_sv().unwrap())
+            .verify(None)
+            .unwrap();
+        let details = &verified.payload().details;
+        assert_eq!(details.nonce, None);
+        assert!(details.expires_on.unwrap() <= now + Duration::days(14));
+        assert_eq!(
+            tenant
+                .storage
+                .log_entries(&serial_number)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // without ownership nothing vouches for the pledge
+        assert!(matches!(
+            issue_for_serial(
+                &tenant,
+                "00-D0-E5-F2-00-03",
+                domain_cert,
+                VoucherTokenType::JWS,
+                now
+            )
+            .await,
+            Err(ServerError::Forbidden(_))
+        ));
+    }
+}
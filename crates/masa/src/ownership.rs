@@ -0,0 +1,312 @@
+use brski_prm_artifacts::{audit_log, ietf_voucher::assertion::Assertion, token_type::JSON};
+use chrono::{DateTime, Utc};
+use cli::config::UnownedDeviceAction;
+use common::{server_error::ServerError, util};
+use openssl::x509::{X509Ref, X509};
+use serde::Deserialize;
+
+use crate::storage::{FormerOwner, OwnershipRecord};
+
+pub(crate) const CSV: &str = "text/csv";
+
+/// Why ownership records were not imported or a voucher is not issued
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum OwnershipError {
+    #[error("Invalid sales record: {0}")]
+    InvalidRecord(String),
+
+    #[error("Sales records have to be {} or {}", JSON, CSV)]
+    UnsupportedFormat,
+
+    #[error("Pledge {0} is owned by another domain")]
+    OwnedByOtherDomain(String),
+
+    #[error("Pledge {0} has no known owner")]
+    UnknownOwner(String),
+
+    #[error("Pledge {0} was transferred away from the requesting domain")]
+    FormerOwner(String),
+}
+
+impl From<OwnershipError> for ServerError {
+    fn from(value: OwnershipError) -> Self {
+        match value {
+            OwnershipError::InvalidRecord(_) => {
+                ServerError::BadRequestWithReason(value.to_string())
+            }
+            OwnershipError::UnsupportedFormat => ServerError::UnsupportedMediaType,
+            _ => ServerError::Forbidden(value.to_string()),
+        }
+    }
+}
+
+/// A pledge sold to a customer, identified either by the domainID or by the registrar CA of the customer
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SalesRecord {
+    pub(crate) serial_number: String,
+    #[serde(default)]
+    pub(crate) domain_id: Option<String>,
+    /// PEM encoded
+    #[serde(default)]
+    pub(crate) registrar_ca: Option<String>,
+    #[serde(default)]
+    pub(crate) owner: Option<String>,
+}
+
+impl SalesRecord {
+    pub(crate) fn into_ownership(
+        self,
+        now: DateTime<Utc>,
+    ) -> Result<OwnershipRecord, OwnershipError> {
+        let invalid = |reason: &str| {
+            OwnershipError::InvalidRecord(format!("{}: {}", self.serial_number, reason))
+        };
+        let non_empty = |value: &Option<String>| value.clone().filter(|value| !value.is_empty());
+
+        if self.serial_number.is_empty() {
+            return Err(OwnershipError::InvalidRecord(
+                "serial_number cannot be empty".to_string(),
+            ));
+        }
+
+        let domain_id = match (non_empty(&self.domain_id), non_empty(&self.registrar_ca)) {
+            (Some(domain_id), None) => domain_id,
+            (None, Some(registrar_ca)) => X509::from_pem(registrar_ca.as_bytes())
+                .ok()
+                .and_then(|registrar_ca| util::domain_id(&registrar_ca).ok())
+                .ok_or_else(|| invalid("can not read registrar_ca"))?,
+            (Some(_), Some(_)) => return Err(invalid("give either domain_id or registrar_ca")),
+            (None, None) => return Err(invalid("domain_id or registrar_ca is required")),
+        };
+
+        Ok(OwnershipRecord {
+            owner: non_empty(&self.owner),
+            serial_number: self.serial_number,
+            domain_id,
+            updated_at: now,
+        })
+    }
+}
+
+/// Parses a JSON array or a CSV file with a header row into ownership records
+pub(crate) fn parse_sales_records(
+    content_type: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Vec<OwnershipRecord>, OwnershipError> {
+    let records: Vec<SalesRecord> = match content_type {
+        JSON => serde_json::from_slice(body)
+            .map_err(|e| OwnershipError::InvalidRecord(e.to_string()))?,
+        CSV => csv::Reader::from_reader(body)
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|e| OwnershipError::InvalidRecord(e.to_string()))?,
+        _ => return Err(OwnershipError::UnsupportedFormat),
+    };
+
+    records
+        .into_iter()
+        .map(|record| record.into_ownership(now))
+        .collect()
+}
+
+/// The domainIDs a domain pinning `pinned_domain_cert` may be recorded under, that of the certificate itself
+/// and, by its AuthorityKeyIdentifier, that of the issuing CA. Registrars pin their own certificate or their CA.
+pub(crate) fn owner_domain_ids(pinned_domain_cert: &X509Ref) -> Result<Vec<String>, ServerError> {
+    let mut domain_ids = vec![util::domain_id(pinned_domain_cert)?];
+    if let Some(akid) = pinned_domain_cert.authority_key_id() {
+        domain_ids.push(audit_log::domain_id(akid.as_slice()));
+    }
+    Ok(domain_ids)
+}
+
+/// The assertion of a voucher for `serial_number` requested by the domain known by `domain_ids`,
+/// see [owner_domain_ids]. `requested` is the assertion of the verified PVR.
+pub(crate) fn choose_assertion(
+    serial_number: &str,
+    owner: Option<&OwnershipRecord>,
+    former_owners: &[FormerOwner],
+    domain_ids: &[String],
+    requested: Option<&Assertion>,
+    unowned_devices: UnownedDeviceAction,
+) -> Result<Assertion, OwnershipError> {
+    match (owner, unowned_devices) {
+        (Some(owner), _) if domain_ids.contains(&owner.domain_id) => Ok(Assertion::Verified),
+        (Some(_), _) => Err(OwnershipError::OwnedByOtherDomain(
+            serial_number.to_string(),
+        )),
+        // a revoked owner must not get the pledge back as an unowned device
+        (None, _)
+            if former_owners
+                .iter()
+                .any(|former| domain_ids.contains(&former.domain_id)) =>
+        {
+            Err(OwnershipError::FormerOwner(serial_number.to_string()))
+        }
+        (None, UnownedDeviceAction::Refuse) => {
+            Err(OwnershipError::UnknownOwner(serial_number.to_string()))
+        }
+        (None, UnownedDeviceAction::Log) => Ok(Assertion::Logged),
+        (None, UnownedDeviceAction::Proximity) => Ok(match requested {
+            Some(Assertion::Proximity) => Assertion::Proximity,
+            Some(Assertion::AgentProximity) => Assertion::AgentProximity,
+            _ => Assertion::Logged,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use example_certs::{generate_certs, OpensslTestCerts};
+
+    use super::*;
+
+    #[test]
+    fn it_picks_the_assertion_by_owner() {
+        let owner = OwnershipRecord {
+            serial_number: "pledge".to_string(),
+            domain_id: "ours".to_string(),
+            owner: None,
+            updated_at: Utc::now(),
+        };
+        let requested = Some(&Assertion::AgentProximity);
+        let (ours, theirs) = (["ours".to_string()], ["theirs".to_string()]);
+
+        assert_eq!(
+            choose_assertion(
+                "pledge",
+                Some(&owner),
+                &[],
+                &ours,
+                requested,
+                UnownedDeviceAction::Refuse
+            )
+            .unwrap(),
+            Assertion::Verified
+        );
+        assert!(matches!(
+            choose_assertion(
+                "pledge",
+                Some(&owner),
+                &[],
+                &theirs,
+                requested,
+                UnownedDeviceAction::Proximity
+            ),
+            Err(OwnershipError::OwnedByOtherDomain(_))
+        ));
+        assert!(matches!(
+            choose_assertion(
+                "pledge",
+                None,
+                &[],
+                &ours,
+                requested,
+                UnownedDeviceAction::Refuse
+            ),
+            Err(OwnershipError::UnknownOwner(_))
+        ));
+        assert_eq!(
+            choose_assertion(
+                "pledge",
+                None,
+                &[],
+                &ours,
+                requested,
+                UnownedDeviceAction::Log
+            )
+            .unwrap(),
+            Assertion::Logged
+        );
+        assert_eq!(
+            choose_assertion(
+                "pledge",
+                None,
+                &[],
+                &ours,
+                requested,
+                UnownedDeviceAction::Proximity
+            )
+            .unwrap(),
+            Assertion::AgentProximity
+        );
+        assert_eq!(
+            choose_assertion(
+                "pledge",
+                None,
+                &[],
+                &ours,
+                Some(&Assertion::Verified),
+                UnownedDeviceAction::Proximity
+            )
+            .unwrap(),
+            Assertion::Logged
+        );
+
+        let former_owners = [FormerOwner {
+            serial_number: "pledge".to_string(),
+            domain_id: "ours".to_string(),
+            owner: None,
+            released_at: Utc::now(),
+        }];
+        assert!(matches!(
+            choose_assertion(
+                "pledge",
+                None,
+                &former_owners,
+                &ours,
+                requested,
+                UnownedDeviceAction::Proximity
+            ),
+            Err(OwnershipError::FormerOwner(_))
+        ));
+        assert_eq!(
+            choose_assertion(
+                "pledge",
+                None,
+                &former_owners,
+                &theirs,
+                requested,
+                UnownedDeviceAction::Log
+            )
+            .unwrap(),
+            Assertion::Logged
+        );
+    }
+
+    #[test]
+    fn it_matches_owners_by_the_pinned_certificate_or_its_ca() {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let (registrar_ca, registrar) = (&certs.registrar_ca.0, &certs.registrar.0);
+
+        let domain_ids = owner_domain_ids(registrar).unwrap();
+        assert!(domain_ids.contains(&util::domain_id(registrar).unwrap()));
+        assert!(domain_ids.contains(&util::domain_id(registrar_ca).unwrap()));
+        assert!(!owner_domain_ids(&certs.vendor_ca.0)
+            .unwrap()
+            .contains(&util::domain_id(registrar_ca).unwrap()));
+    }
+
+    #[test]
+    fn it_imports_sales_records() {
+        let csv = b"serial_number,domain_id,registrar_ca,owner\n\
+            00-D0-E5-F2-00-02,BduJhdHPpfhQLyponf48JzXSGZ8=,,ACME\n\
+            00-D0-E5-F2-00-03,BduJhdHPpfhQLyponf48JzXSGZ8=,,\n";
+        let records = parse_sales_records(CSV, csv, Utc::now()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].owner.as_deref(), Some("ACME"));
+        assert_eq!(records[1].owner, None);
+
+        let json = br#"[{"serial_number": "00-D0-E5-F2-00-02", "domain_id": "BduJhdHPpfhQLyponf48JzXSGZ8="}]"#;
+        assert_eq!(
+            parse_sales_records(JSON, json, Utc::now()).unwrap()[0].domain_id,
+            "BduJhdHPpfhQLyponf48JzXSGZ8="
+        );
+
+        let missing_owner = br#"[{"serial_number": "00-D0-E5-F2-00-02"}]"#;
+        assert!(matches!(
+            parse_sales_records(JSON, missing_owner, Utc::now()),
+            Err(OwnershipError::InvalidRecord(_))
+        ));
+    }
+}
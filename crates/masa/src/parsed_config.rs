@@ -1,5 +1,7 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::anyhow;
-use cli::config::MasaConfig;
+use cli::config::{MasaConfig, UnownedDeviceAction, DEFAULT_TENANT};
 use common::error::AppError;
 use openssl::{
     ec::{self, EcKey},
@@ -7,6 +9,8 @@ use openssl::{
     x509::X509,
 };
 
+use crate::lifetime::VoucherLifetime;
+
 #[derive(Clone, Debug)]
 pub(crate) struct ParsedConfig {
     pub(crate) config: MasaConfig,
@@ -14,6 +18,22 @@ pub(crate) struct ParsedConfig {
     pub(crate) ca_key: Vec<u8>,
     pub(crate) masa_certificate: X509,
     pub(crate) masa_key: Vec<u8>,
+    pub(crate) registrar_ee_certificate: X509,
+    /// The tenant of the `[masa]` section first, then the configured ones by name
+    pub(crate) tenants: Vec<ParsedTenant>,
+}
+
+/// Signing material and policy of a product line, see [cli::config::TenantConfig]
+#[derive(Clone, Debug)]
+pub(crate) struct ParsedTenant {
+    pub(crate) name: String,
+    pub(crate) ca_certificate: X509,
+    pub(crate) intermediate_certificates: Vec<X509>,
+    pub(crate) masa_certificate: X509,
+    pub(crate) masa_key: Vec<u8>,
+    pub(crate) database: PathBuf,
+    pub(crate) unowned_devices: UnownedDeviceAction,
+    pub(crate) lifetime: VoucherLifetime,
 }
 
 pub(crate) fn parse_config(config: MasaConfig) -> anyhow::Result<ParsedConfig, AppError> {
@@ -27,18 +47,79 @@ pub(crate) fn parse_config(config: MasaConfig) -> anyhow::Result<ParsedConfig, A
     let unparsed_masa_cert = std::fs::read(config.masa_certificate.relative())?;
     let masa_certificate = X509::from_pem(&unparsed_masa_cert)?;
 
-    let unparsed_masa_key = std::fs::read(config.masa_key.relative())?;
-    let masa_key = ec::EcKey::private_key_from_pem(&unparsed_masa_key)?;
-    let masa_key_pkcs8 = PKey::from_ec_key(masa_key)?.private_key_to_pkcs8()?;
+    let masa_key_pkcs8 = read_key(&config.masa_key.relative())?;
+
+    let unparsed_registrar_ee_cert = std::fs::read(config.registrar_ee_certificate.relative())?;
+    let registrar_ee_certificate = X509::from_pem(&unparsed_registrar_ee_cert)?;
 
     //assert!(masa_certificate.verify(&openssl::pkey::PKey::from_ec_key(ca_key.clone()).unwrap()).unwrap());
     //assert!(ca_certificate.verify(&openssl::pkey::PKey::from_ec_key(ca_key.clone()).unwrap()).unwrap());
 
+    let mut tenants = vec![ParsedTenant {
+        name: DEFAULT_TENANT.to_string(),
+        ca_certificate: ca_certificate.clone(),
+        intermediate_certificates: read_certificates(
+            config
+                .intermediate_certificates
+                .iter()
+                .map(|path| path.relative()),
+        )?,
+        masa_certificate: masa_certificate.clone(),
+        masa_key: masa_key_pkcs8.clone(),
+        database: config.database.relative(),
+        unowned_devices: config.unowned_devices,
+        lifetime: VoucherLifetime::new(
+            config.nonceless_vouchers,
+            config.voucher_lifetime,
+            config.renewal_period,
+        ),
+    }];
+
+    for (name, tenant) in &config.tenants {
+        tenants.push(ParsedTenant {
+            name: name.clone(),
+            ca_certificate: X509::from_pem(&std::fs::read(tenant.ca_certificate.relative())?)?,
+            intermediate_certificates: read_certificates(
+                tenant
+                    .intermediate_certificates
+                    .iter()
+                    .map(|path| path.relative()),
+            )?,
+            masa_certificate: X509::from_pem(&std::fs::read(tenant.masa_certificate.relative())?)?,
+            masa_key: read_key(&tenant.masa_key.relative())?,
+            database: tenant.database.relative(),
+            unowned_devices: tenant.unowned_devices.unwrap_or(config.unowned_devices),
+            lifetime: VoucherLifetime::new(
+                tenant
+                    .nonceless_vouchers
+                    .unwrap_or(config.nonceless_vouchers),
+                tenant.voucher_lifetime.unwrap_or(config.voucher_lifetime),
+                tenant.renewal_period.unwrap_or(config.renewal_period),
+            ),
+        });
+    }
+
     Ok(ParsedConfig {
         config,
         ca_certificate,
         ca_key: ca_key_pkcs8,
         masa_certificate,
         masa_key: masa_key_pkcs8,
+        registrar_ee_certificate,
+        tenants,
     })
 }
+
+fn read_certificates(paths: impl IntoIterator<Item = PathBuf>) -> anyhow::Result<Vec<X509>> {
+    paths
+        .into_iter()
+        .map(|path| Ok(X509::from_pem(&std::fs::read(path)?)?))
+        .collect()
+}
+
+/// Reads a PEM encoded EC key as PKCS#8
+fn read_key(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let key = EcKey::private_key_from_pem(&std::fs::read(path)?)
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    Ok(PKey::from_ec_key(key)?.private_key_to_pkcs8()?)
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::anyhow;
+use common::server_error::ServerError;
+use openssl::{sha::sha256, x509::X509};
+use serde::Serialize;
+use tracing::{event, Level};
+
+use crate::parsed_config::ParsedConfig;
+
+/// A registrar as listed by the admin API
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub(crate) struct RegistrarEntry {
+    /// Hex encoded SHA-256 of the DER encoded certificate
+    pub(crate) fingerprint: String,
+    pub(crate) subject: String,
+    /// Configured registrars can not be removed via the admin API
+    pub(crate) configured: bool,
+}
+
+/// The registrars the MASA accepts TLS client certificates from.
+/// Registrar certificates are usually not issued by a CA the MASA knows, so each one is trusted individually.
+#[derive(Clone, Debug)]
+pub(crate) struct TrustedRegistrars {
+    dir: PathBuf,
+    /// Certificates by fingerprint, and whether they are configured
+    certificates: Arc<RwLock<BTreeMap<String, (X509, bool)>>>,
+}
+
+impl TrustedRegistrars {
+    /// Loads the configured registrar and those registered via the admin API before
+    pub(crate) fn load(config: &ParsedConfig) -> anyhow::Result<Self> {
+        let dir = config.config.registrars_dir.relative();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut certificates = BTreeMap::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "pem") {
+                let certificate = X509::from_pem(&std::fs::read(&path)?)?;
+                certificates.insert(fingerprint(&certificate)?, (certificate, false));
+            }
+        }
+
+        let configured = config.registrar_ee_certificate.clone();
+        certificates.insert(fingerprint(&configured)?, (configured, true));
+
+        event!(Level::INFO, "Trusting {} registrars", certificates.len());
+
+        Ok(Self {
+            dir,
+            certificates: Arc::new(RwLock::new(certificates)),
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn trusting(certificate: X509) -> Self {
+        let mut certificates = BTreeMap::new();
+        certificates.insert(fingerprint(&certificate).unwrap(), (certificate, true));
+
+        Self {
+            dir: std::env::temp_dir(),
+            certificates: Arc::new(RwLock::new(certificates)),
+        }
+    }
+
+    pub(crate) fn is_trusted(&self, der: &[u8]) -> bool {
+        let fingerprint = hex(&sha256(der));
+        self.certificates
+            .read()
+            .is_ok_and(|certificates| certificates.contains_key(&fingerprint))
+    }
+
+    pub(crate) fn list(&self) -> Result<Vec<RegistrarEntry>, ServerError> {
+        self.certificates
+            .read()
+            .map_err(|_| anyhow!("Registrar store lock poisoned"))?
+            .iter()
+            .map(|(fingerprint, (certificate, configured))| {
+                entry(fingerprint, certificate, *configured)
+            })
+            .collect()
+    }
+
+    /// Trusts `certificate` from now on, persisting it in the registrars directory
+    pub(crate) fn register(&self, certificate: X509) -> Result<RegistrarEntry, ServerError> {
+        let fingerprint = fingerprint(&certificate)?;
+        let mut certificates = self
+            .certificates
+            .write()
+            .map_err(|_| anyhow!("Registrar store lock poisoned"))?;
+
+        if let Some((certificate, configured)) = certificates.get(&fingerprint) {
+            return entry(&fingerprint, certificate, *configured);
+        }
+
+        let pem = certificate.to_pem().map_err(anyhow::Error::from)?;
+        std::fs::write(self.path(&fingerprint), pem)?;
+        let registered = entry(&fingerprint, &certificate, false)?;
+        certificates.insert(fingerprint, (certificate, false));
+
+        event!(Level::INFO, "Registered registrar {}", registered.subject);
+        Ok(registered)
+    }
+
+    pub(crate) fn unregister(&self, fingerprint: &str) -> Result<(), ServerError> {
+        let mut certificates = self
+            .certificates
+            .write()
+            .map_err(|_| anyhow!("Registrar store lock poisoned"))?;
+
+        match certificates.get(fingerprint) {
+            None => return Err(ServerError::NotFound),
+            Some((_, true)) => {
+                return Err(ServerError::BadRequestWithReason(
+                    "Configured registrars can not be removed".to_string(),
+                ))
+            }
+            Some(_) => {}
+        }
+
+        // only fingerprints we computed ourselves end up in a path
+        std::fs::remove_file(self.path(fingerprint))?;
+        certificates.remove(fingerprint);
+
+        event!(Level::INFO, "Unregistered registrar {}", fingerprint);
+        Ok(())
+    }
+
+    fn path(&self, fingerprint: &str) -> PathBuf {
+        self.dir.join(format!("{}.pem", fingerprint))
+    }
+}
+
+fn fingerprint(certificate: &X509) -> anyhow::Result<String> {
+    Ok(hex(&sha256(&certificate.to_der()?)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn entry(
+    fingerprint: &str,
+    certificate: &X509,
+    configured: bool,
+) -> Result<RegistrarEntry, ServerError> {
+    let subject = certificate
+        .subject_name()
+        .entries()
+        .map(|entry| {
+            Ok(format!(
+                "{}={}",
+                entry.object().nid().short_name()?,
+                entry.data().as_utf8()?
+            ))
+        })
+        .collect::<Result<Vec<_>, openssl::error::ErrorStack>>()
+        .map_err(|e| anyhow!("Can not read registrar certificate subject: {}", e))?
+        .join(", ");
+
+    Ok(RegistrarEntry {
+        fingerprint: fingerprint.to_string(),
+        subject,
+        configured,
+    })
+}
@@ -0,0 +1,427 @@
+use brski_prm_artifacts::ietf_voucher::{
+    agent_signed_data::AgentSignedData, assertion::Assertion, VoucherRequest,
+};
+use chrono::{DateTime, Utc};
+use common::{
+    agent_signed_data::{verify_agent_signed_data, AgentSignedDataError},
+    server_error::ServerError,
+};
+use openssl::{nid::Nid, x509::X509};
+use signeable_payload::{signeable::raw_signed::RawSigned, SignatureType};
+use tracing::{event, Level};
+
+use crate::{
+    registrars::TrustedRegistrars,
+    tenants::{Tenant, Tenants},
+};
+
+/// Why the MASA refuses to act on an RVR
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum RvrVerificationError {
+    #[error("RVR signature is invalid: {0}")]
+    InvalidSignature(String),
+
+    #[error("RVR does not carry the registrar certificate")]
+    MissingRegistrarCertificate,
+
+    #[error("RVR was not signed by the authenticated registrar")]
+    RegistrarMismatch,
+
+    #[error("RVR was signed by an unknown registrar")]
+    UnknownRegistrar,
+
+    #[error("RVR does not contain agent-provided-proximity-registrar-cert")]
+    MissingPinnedDomainCert,
+
+    #[error("agent-provided-proximity-registrar-cert does not belong to the registrar")]
+    ForeignPinnedDomainCert,
+
+    #[error("RVR does not contain prior-signed-voucher-request")]
+    MissingPriorSignedVoucherRequest,
+
+    #[error("PVR signature is invalid: {0}")]
+    InvalidPvrSignature(String),
+
+    #[error("PVR does not carry the pledge IDevID")]
+    MissingIdevid,
+
+    #[error("Pledge IDevID is not issued by the vendor CA of any tenant: {0}")]
+    UntrustedIdevid(String),
+
+    #[error("PVR is for serial number {pvr}, the pledge IDevID for {idevid}")]
+    IdevidSerialMismatch { idevid: String, pvr: String },
+
+    #[error("RVR is for serial number {rvr}, the PVR for {pvr}")]
+    SerialMismatch { rvr: String, pvr: String },
+
+    #[error("RVR nonce does not match the PVR nonce")]
+    NonceMismatch,
+
+    #[error("PVR and RVR pin different registrar certificates")]
+    ProximityRegistrarMismatch,
+
+    #[error("RVR does not contain agent-sign-cert")]
+    MissingAgentSignCert,
+
+    #[error(transparent)]
+    AgentSignedData(#[from] AgentSignedDataError),
+}
+
+impl From<RvrVerificationError> for ServerError {
+    fn from(value: RvrVerificationError) -> Self {
+        match value {
+            RvrVerificationError::InvalidSignature(_)
+            | RvrVerificationError::MissingRegistrarCertificate
+            | RvrVerificationError::MissingPinnedDomainCert
+            | RvrVerificationError::MissingPriorSignedVoucherRequest
+            | RvrVerificationError::MissingIdevid
+            | RvrVerificationError::AgentSignedData(AgentSignedDataError::Missing)
+            | RvrVerificationError::MissingAgentSignCert => {
+                ServerError::BadRequestWithReason(value.to_string())
+            }
+            _ => ServerError::Forbidden(value.to_string()),
+        }
+    }
+}
+
+/// An RVR whose registrar, pledge and, for agent-proximity, agent checked out
+#[derive(Clone)]
+pub(crate) struct VerifiedRequest<'a> {
+    pub(crate) rvr: VoucherRequest,
+    /// The voucher request of the pledge nested in the RVR
+    pub(crate) pvr: VoucherRequest,
+    /// The tenant whose vendor CA issued the pledge IDevID
+    pub(crate) tenant: &'a Tenant,
+}
+
+/// What an RVR is checked against before the MASA issues a voucher for it
+pub(crate) struct RvrVerifier<'a> {
+    /// Pledge IDevIDs have to chain to the vendor CA of one of them
+    pub(crate) tenants: &'a Tenants,
+    pub(crate) registrars: &'a TrustedRegistrars,
+}
+
+impl<'a> RvrVerifier<'a> {
+    /// Verifies the RVR was signed by a known registrar, or the one that authenticated via TLS,
+    /// and that it carries a PVR of a pledge made by this vendor for the certificate the registrar wants pinned.
+    pub(crate) fn verify(
+        &self,
+        rvr: &[u8],
+        signature_type: SignatureType,
+        tls_certificate: Option<Vec<u8>>,
+        now: DateTime<Utc>,
+    ) -> Result<VerifiedRequest<'a>, RvrVerificationError> {
+        let verified = RawSigned::<VoucherRequest>::new(rvr.to_vec())
+            .into_verifyable_boxed(
+                signature_type
+                    .clone()
+                    .get_sv()
+                    .map_err(|e| RvrVerificationError::InvalidSignature(e.to_string()))?,
+            )
+            .verify(None)
+            .map_err(|e| RvrVerificationError::InvalidSignature(e.to_string()))?;
+
+        let registrar_der = verified
+            .headers()
+            .x509_certificate_chain()
+            .and_then(|chain| chain.into_iter().next())
+            .ok_or(RvrVerificationError::MissingRegistrarCertificate)?;
+
+        // A registrar that authenticated via TLS may only send RVRs it signed itself
+        if tls_certificate.is_some_and(|tls_certificate| tls_certificate != registrar_der) {
+            return Err(RvrVerificationError::RegistrarMismatch);
+        }
+        if !self.registrars.is_trusted(&registrar_der) {
+            return Err(RvrVerificationError::UnknownRegistrar);
+        }
+
+        let rvr = verified.payload().clone();
+        let registrar = X509::from_der(&registrar_der)
+            .map_err(|e| RvrVerificationError::InvalidSignature(e.to_string()))?;
+        let pinned = rvr
+            .details
+            .agent_provided_proximity_registrar_cert
+            .as_ref()
+            .ok_or(RvrVerificationError::MissingPinnedDomainCert)?;
+        if !belongs_to(pinned, &registrar) {
+            return Err(RvrVerificationError::ForeignPinnedDomainCert);
+        }
+
+        let (pvr, tenant) = self.verify_pvr(&rvr, signature_type.clone())?;
+
+        let agent_proximity = [&pvr, &rvr]
+            .iter()
+            .any(|request| request.details.assertion == Some(Assertion::AgentProximity));
+        if agent_proximity {
+            verify_agent_proximity(&pvr, &rvr, signature_type, now)?;
+        }
+
+        event!(
+            Level::INFO,
+            "Verified RVR for {} of tenant {}",
+            rvr.details.serial_number,
+            tenant.config.name
+        );
+        Ok(VerifiedRequest { rvr, pvr, tenant })
+    }
+
+    fn verify_pvr(
+        &self,
+        rvr: &VoucherRequest,
+        signature_type: SignatureType,
+    ) -> Result<(VoucherRequest, &'a Tenant), RvrVerificationError> {
+        let prior_signed_voucher_request = rvr
+            .details
+            .prior_signed_voucher_request
+            .clone()
+            .ok_or(RvrVerificationError::MissingPriorSignedVoucherRequest)?;
+
+        let verified = RawSigned::<VoucherRequest>::new(prior_signed_voucher_request)
+            .into_verifyable_boxed(
+                signature_type
+                    .get_sv()
+                    .map_err(|e| RvrVerificationError::InvalidPvrSignature(e.to_string()))?,
+            )
+            .verify(None)
+            .map_err(|e| RvrVerificationError::InvalidPvrSignature(e.to_string()))?;
+
+        let idevid_chain = verified
+            .headers()
+            .x509_certificate_chain()
+            .filter(|chain| !chain.is_empty())
+            .ok_or(RvrVerificationError::MissingIdevid)?;
+        let tenant = self
+            .tenants
+            .for_idevid(&idevid_chain)
+            .map_err(RvrVerificationError::UntrustedIdevid)?;
+
+        let pvr = verified.payload().clone();
+
+        let idevid_serial = X509::from_der(&idevid_chain[0])
+            .ok()
+            .and_then(|idevid| subject_serial_number(&idevid))
+            .unwrap_or_default();
+        if idevid_serial != pvr.details.serial_number {
+            return Err(RvrVerificationError::IdevidSerialMismatch {
+                idevid: idevid_serial,
+                pvr: pvr.details.serial_number,
+            });
+        }
+
+        if rvr.details.serial_number != pvr.details.serial_number {
+            return Err(RvrVerificationError::SerialMismatch {
+                rvr: rvr.details.serial_number.clone(),
+                pvr: pvr.details.serial_number,
+            });
+        }
+
+        if rvr.details.nonce != pvr.details.nonce {
+            return Err(RvrVerificationError::NonceMismatch);
+        }
+
+        Ok((pvr, tenant))
+    }
+}
+
+/// Checks that the agent named in the RVR signed the PVR's agent-signed-data for this pledge and pinned the same registrar
+fn verify_agent_proximity(
+    pvr: &VoucherRequest,
+    rvr: &VoucherRequest,
+    signature_type: SignatureType,
+    now: DateTime<Utc>,
+) -> Result<(), RvrVerificationError> {
+    let agent = rvr
+        .details
+        .agent_sign_cert
+        .as_ref()
+        .and_then(|certificates| certificates.first())
+        .ok_or(RvrVerificationError::MissingAgentSignCert)?;
+
+    let verifyer = signature_type
+        .get_sv::<AgentSignedData>()
+        .map_err(|e| AgentSignedDataError::InvalidSignature(e.to_string()))?;
+    verify_agent_signed_data(pvr, agent, verifyer, now)?;
+
+    if pvr.details.agent_provided_proximity_registrar_cert
+        != rvr.details.agent_provided_proximity_registrar_cert
+    {
+        return Err(RvrVerificationError::ProximityRegistrarMismatch);
+    }
+
+    Ok(())
+}
+
+/// A registrar may have its own certificate pinned or the CA that issued it
+pub(crate) fn belongs_to(pinned: &X509, registrar: &X509) -> bool {
+    if pinned.to_der().ok() == registrar.to_der().ok() {
+        return true;
+    }
+
+    pinned
+        .subject_name()
+        .try_cmp(registrar.issuer_name())
+        .is_ok_and(|o| o.is_eq())
+        && pinned
+            .public_key()
+            .and_then(|key| registrar.verify(&key))
+            .unwrap_or(false)
+}
+
+fn subject_serial_number(certificate: &X509) -> Option<String> {
+    certificate
+        .subject_name()
+        .entries_by_nid(Nid::SERIALNUMBER)
+        .next()
+        .and_then(|entry| entry.data().as_utf8().ok())
+        .map(|serial| serial.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use brski_prm_artifacts::ietf_voucher::agent_signed_data::AgentSignedData;
+    use example_certs::{generate_certs, OpensslTestCerts};
+    use openssl::pkey::{PKey, Private};
+    use signeable_payload::{
+        header::HeaderSet, BasicSigningContext, DefaultSignerVerifyer, Unsigned,
+    };
+
+    use super::*;
+
+    fn sign<T: serde::Serialize + serde::de::DeserializeOwned + 'static>(
+        payload: T,
+        certificate: Option<&X509>,
+        key: &PKey<Private>,
+    ) -> Vec<u8> {
+        let mut header = HeaderSet::new();
+        if let Some(certificate) = certificate {
+            header.set_x509_certificate_chain(&vec![certificate.clone().into()], true);
+        }
+
+        Unsigned::new(payload, header)
+            .into_signeable(DefaultSignerVerifyer::default())
+            .sign(
+                key.private_key_to_pkcs8().unwrap(),
+                BasicSigningContext::new(),
+            )
+            .unwrap()
+            .data()
+    }
+
+    struct Requests {
+        pvr: VoucherRequest,
+        rvr: VoucherRequest,
+    }
+
+    impl Requests {
+        fn new(certs: &OpensslTestCerts) -> Self {
+            let (agent, agent_key) = &certs.registrar_agent;
+            let asd = sign(
+                AgentSignedData::new(Utc::now(), "00-D0-E5-F2-00-02".to_string()),
+                None,
+                agent_key,
+            );
+
+            let mut pvr = VoucherRequest::default();
+            pvr.details.serial_number = "00-D0-E5-F2-00-02".to_string();
+            pvr.details.nonce = Some(b"nonce".to_vec());
+            pvr.details.assertion = Some(Assertion::AgentProximity);
+            pvr.details.agent_signed_data = Some(RawSigned::new(asd));
+            pvr.details.agent_provided_proximity_registrar_cert =
+                Some(certs.registrar_ca.0.clone().into());
+
+            let mut rvr = VoucherRequest::default();
+            rvr.details.serial_number = pvr.details.serial_number.clone();
+            rvr.details.nonce = pvr.details.nonce.clone();
+            rvr.details.assertion = pvr.details.assertion.clone();
+            rvr.details.agent_sign_cert = Some(vec![agent.clone().into()]);
+            rvr.details.agent_provided_proximity_registrar_cert =
+                pvr.details.agent_provided_proximity_registrar_cert.clone();
+
+            Self { pvr, rvr }
+        }
+
+        fn sign(mut self, certs: &OpensslTestCerts) -> Vec<u8> {
+            let (pledge, pledge_key) = &certs.pledge;
+            let (registrar, registrar_key) = &certs.registrar;
+            self.rvr.details.prior_signed_voucher_request =
+                Some(sign(self.pvr, Some(pledge), pledge_key));
+            sign(self.rvr, Some(registrar), registrar_key)
+        }
+    }
+
+    #[test]
+    fn it_verifies_rvrs() {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let registrars = TrustedRegistrars::trusting(certs.registrar.0.clone());
+        let tenants = Tenants::new(vec![Tenant::in_memory(
+            "default",
+            certs.vendor_ca.0.clone(),
+            certs.vendor.0.clone(),
+            &certs.vendor.1,
+        )]);
+        let verifier = RvrVerifier {
+            tenants: &tenants,
+            registrars: &registrars,
+        };
+        let verify = |rvr: Vec<u8>| verifier.verify(&rvr, SignatureType::COSE, None, Utc::now());
+
+        let verified = verify(Requests::new(&certs).sign(&certs)).unwrap();
+        assert_eq!(verified.pvr.details.serial_number, "00-D0-E5-F2-00-02");
+        assert_eq!(verified.tenant.config.name, "default");
+
+        let mut requests = Requests::new(&certs);
+        requests.rvr.details.nonce = Some(b"replayed".to_vec());
+        assert!(matches!(
+            verify(requests.sign(&certs)),
+            Err(RvrVerificationError::NonceMismatch)
+        ));
+
+        let mut requests = Requests::new(&certs);
+        requests.pvr.details.serial_number = "00-D0-E5-F2-00-03".to_string();
+        assert!(matches!(
+            verify(requests.sign(&certs)),
+            Err(RvrVerificationError::IdevidSerialMismatch { .. })
+        ));
+
+        let mut requests = Requests::new(&certs);
+        requests.pvr.details.agent_signed_data = None;
+        assert!(matches!(
+            verify(requests.sign(&certs)),
+            Err(RvrVerificationError::AgentSignedData(
+                AgentSignedDataError::Missing
+            ))
+        ));
+
+        let mut requests = Requests::new(&certs);
+        requests.rvr.details.agent_sign_cert = Some(vec![certs.registrar.0.clone().into()]);
+        assert!(matches!(
+            verify(requests.sign(&certs)),
+            Err(RvrVerificationError::AgentSignedData(
+                AgentSignedDataError::InvalidSignature(_)
+            ))
+        ));
+
+        let mut requests = Requests::new(&certs);
+        requests.rvr.details.agent_provided_proximity_registrar_cert =
+            Some(certs.vendor_ca.0.clone().into());
+        assert!(matches!(
+            verify(requests.sign(&certs)),
+            Err(RvrVerificationError::ForeignPinnedDomainCert)
+        ));
+
+        let untrusting = TrustedRegistrars::trusting(certs.vendor.0.clone());
+        assert!(matches!(
+            RvrVerifier {
+                tenants: &tenants,
+                registrars: &untrusting,
+            }
+            .verify(
+                &Requests::new(&certs).sign(&certs),
+                SignatureType::COSE,
+                None,
+                Utc::now()
+            ),
+            Err(RvrVerificationError::UnknownRegistrar)
+        ));
+    }
+}
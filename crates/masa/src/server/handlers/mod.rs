@@ -1,12 +1,81 @@
+mod owners;
+mod registrars;
+mod renewvoucher;
+mod requestauditlog;
 mod requestvoucher;
-use axum::{routing::post, Router};
+mod transparency;
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
 
 use super::server::ServerState;
 
 #[tracing::instrument(target = "MASA")]
 pub(crate) fn brski_routes() -> Router<ServerState> {
-    Router::new().route(
-        "/requestvoucher",
-        post(requestvoucher::handle_requestvoucher),
-    )
+    Router::new()
+        .route(
+            "/requestvoucher",
+            post(requestvoucher::handle_requestvoucher),
+        )
+        .route(
+            "/requestauditlog",
+            post(requestauditlog::handle_requestauditlog),
+        )
+        .route("/renewvoucher", post(renewvoucher::handle_renewvoucher))
+}
+
+/// The public transparency log of issued vouchers
+#[tracing::instrument(target = "MASA")]
+pub(crate) fn transparency_routes() -> Router<ServerState> {
+    Router::new()
+        .route("/tree-head", get(transparency::handle_get_tree_head))
+        .route(
+            "/proof/inclusion",
+            get(transparency::handle_get_inclusion_proof),
+        )
+        .route(
+            "/proof/consistency",
+            get(transparency::handle_get_consistency_proof),
+        )
+        .route(
+            "/entries/:serial_number",
+            get(transparency::handle_get_log_entries),
+        )
+}
+
+/// Operator endpoints, guarded by [common::middleware::require_admin_token]
+#[tracing::instrument(target = "MASA")]
+pub(crate) fn admin_routes() -> Router<ServerState> {
+    Router::new()
+        .route(
+            "/registrars",
+            get(registrars::handle_list_registrars).post(registrars::handle_register_registrar),
+        )
+        .route(
+            "/registrars/:fingerprint",
+            delete(registrars::handle_unregister_registrar),
+        )
+        .route(
+            "/owners",
+            get(owners::handle_list_owners).post(owners::handle_import_owners),
+        )
+        .route(
+            "/owners/:serial_number",
+            get(owners::handle_get_owner)
+                .put(owners::handle_set_owner)
+                .delete(owners::handle_remove_owner),
+        )
+        .route(
+            "/owners/:serial_number/transfer",
+            post(owners::handle_transfer_owner),
+        )
+        .route(
+            "/owners/:serial_number/revoke",
+            post(owners::handle_revoke_owner),
+        )
+        .route(
+            "/owners/:serial_number/history",
+            get(owners::handle_list_former_owners),
+        )
 }
@@ -0,0 +1,202 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    Json,
+};
+use common::server_error::ServerError;
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+    ownership::{parse_sales_records, SalesRecord},
+    server::server::ServerState,
+    storage::{FormerOwner, OwnershipRecord},
+    tenants::TenantQuery,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    imported: usize,
+}
+
+/// The owner a pledge was transferred from and the one it now belongs to
+#[derive(Debug, Serialize)]
+pub struct OwnershipTransfer {
+    previous: Option<OwnershipRecord>,
+    current: OwnershipRecord,
+}
+
+/// An owner for a single pledge, see [SalesRecord]
+#[derive(Debug, Deserialize)]
+pub struct OwnerUpdate {
+    #[serde(default)]
+    domain_id: Option<String>,
+    #[serde(default)]
+    registrar_ca: Option<String>,
+    #[serde(default)]
+    owner: Option<String>,
+}
+
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_list_owners(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+) -> Result<Json<Vec<OwnershipRecord>>, ServerError> {
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    Ok(Json(tenant.storage.list_owners().await?))
+}
+
+/// Imports sales records, a JSON array or CSV with a header row, replacing known owners of the pledges
+#[tracing::instrument(target = "MASA", skip(state, headers, bytes))]
+pub async fn handle_import_owners(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    headers: HeaderMap,
+    bytes: Bytes,
+) -> Result<Json<ImportSummary>, ServerError> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .ok_or(ServerError::UnsupportedMediaType)?
+        .to_str()?;
+
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    let records = parse_sales_records(content_type, &bytes, chrono::Utc::now())?;
+    let imported = records.len();
+    tenant.storage.set_owners(records).await?;
+
+    event!(
+        Level::INFO,
+        "Imported {} sales records for tenant {}",
+        imported,
+        tenant.config.name
+    );
+    Ok(Json(ImportSummary { imported }))
+}
+
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_get_owner(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    Path(serial_number): Path<String>,
+) -> Result<Json<OwnershipRecord>, ServerError> {
+    state
+        .tenants
+        .get(query.tenant.as_deref())?
+        .storage
+        .get_owner(&serial_number)
+        .await?
+        .map(Json)
+        .ok_or(ServerError::NotFound)
+}
+
+#[tracing::instrument(target = "MASA", skip(state, update))]
+pub async fn handle_set_owner(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    Path(serial_number): Path<String>,
+    Json(update): Json<OwnerUpdate>,
+) -> Result<Json<OwnershipRecord>, ServerError> {
+    let record = SalesRecord {
+        serial_number,
+        domain_id: update.domain_id,
+        registrar_ca: update.registrar_ca,
+        owner: update.owner,
+    }
+    .into_ownership(chrono::Utc::now())?;
+
+    state
+        .tenants
+        .get(query.tenant.as_deref())?
+        .storage
+        .set_owners(vec![record.clone()])
+        .await?;
+
+    event!(
+        Level::INFO,
+        "Pledge {} is now owned by {}",
+        record.serial_number,
+        record.domain_id
+    );
+    Ok(Json(record))
+}
+
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_remove_owner(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    Path(serial_number): Path<String>,
+) -> Result<StatusCode, ServerError> {
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    match tenant.storage.remove_owner(&serial_number).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(ServerError::NotFound),
+    }
+}
+
+/// Moves the pledge to a new owner, the previous owner is refused vouchers from now on
+#[tracing::instrument(target = "MASA", skip(state, update))]
+pub async fn handle_transfer_owner(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    Path(serial_number): Path<String>,
+    Json(update): Json<OwnerUpdate>,
+) -> Result<Json<OwnershipTransfer>, ServerError> {
+    let current = SalesRecord {
+        serial_number,
+        domain_id: update.domain_id,
+        registrar_ca: update.registrar_ca,
+        owner: update.owner,
+    }
+    .into_ownership(chrono::Utc::now())?;
+
+    let previous = state
+        .tenants
+        .get(query.tenant.as_deref())?
+        .storage
+        .transfer_owner(current.clone())
+        .await?;
+
+    event!(
+        Level::INFO,
+        "Pledge {} was transferred from {:?} to {}",
+        current.serial_number,
+        previous.as_ref().map(|previous| &previous.domain_id),
+        current.domain_id
+    );
+    Ok(Json(OwnershipTransfer { previous, current }))
+}
+
+/// Takes the pledge away from its owner, whose voucher requests are refused from now on
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_revoke_owner(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    Path(serial_number): Path<String>,
+) -> Result<Json<OwnershipRecord>, ServerError> {
+    let revoked = state
+        .tenants
+        .get(query.tenant.as_deref())?
+        .storage
+        .revoke_owner(&serial_number, chrono::Utc::now())
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    event!(
+        Level::INFO,
+        "Ownership of pledge {} by {} was revoked",
+        serial_number,
+        revoked.domain_id
+    );
+    Ok(Json(revoked))
+}
+
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_list_former_owners(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    Path(serial_number): Path<String>,
+) -> Result<Json<Vec<FormerOwner>>, ServerError> {
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    Ok(Json(tenant.storage.former_owners(&serial_number).await?))
+}
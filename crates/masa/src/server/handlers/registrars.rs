@@ -0,0 +1,44 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use common::server_error::ServerError;
+use openssl::x509::X509;
+
+use crate::{registrars::RegistrarEntry, server::server::ServerState};
+
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_list_registrars(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<RegistrarEntry>>, ServerError> {
+    Ok(Json(state.registrars.list()?))
+}
+
+/// Takes a PEM or DER encoded registrar certificate
+#[tracing::instrument(target = "MASA", skip(state, bytes))]
+pub async fn handle_register_registrar(
+    State(state): State<ServerState>,
+    bytes: Bytes,
+) -> Result<(StatusCode, Json<RegistrarEntry>), ServerError> {
+    let certificate = X509::from_pem(&bytes)
+        .or_else(|_| X509::from_der(&bytes))
+        .map_err(|_| {
+            ServerError::BadRequestWithReason("Can not parse registrar certificate".to_string())
+        })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(state.registrars.register(certificate)?),
+    ))
+}
+
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_unregister_registrar(
+    State(state): State<ServerState>,
+    Path(fingerprint): Path<String>,
+) -> Result<StatusCode, ServerError> {
+    state.registrars.unregister(&fingerprint)?;
+    Ok(StatusCode::NO_CONTENT)
+}
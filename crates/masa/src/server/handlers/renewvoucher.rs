@@ -0,0 +1,112 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderMap},
+    Extension,
+};
+use brski_prm_artifacts::{
+    ietf_voucher::artifact::VoucherArtifact,
+    token_type::{VoucherTokenType, COSE_VOUCHER, JWS_VOUCHER},
+};
+use common::{server_error::ServerError, tls::TlsClientIdentity, util::domain_id};
+use openssl::x509::X509;
+use signeable_payload::signeable::{raw_signed::RawSigned, signed::Signed};
+use tracing::{event, Level};
+
+use crate::{
+    issuance::issue_voucher,
+    ownership::{choose_assertion, owner_domain_ids},
+    rvr::belongs_to,
+    server::server::ServerState,
+};
+
+/// Renews a nonceless voucher this MASA issued, moving its `expires-on` while its `last-renewal-date` stays.
+/// Ownership is checked again, so vouchers for pledges sold on are not renewed for their former owner.
+#[tracing::instrument(target = "MASA", skip(state, headers, registrar, bytes))]
+pub async fn handle_renewvoucher(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    registrar: Option<Extension<TlsClientIdentity>>,
+    bytes: Bytes,
+) -> Result<Signed<VoucherArtifact>, ServerError> {
+    event!(Level::INFO, "Received renewvoucher request");
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .ok_or(ServerError::BadRequest)?
+        .to_str()?;
+    let voucher_token_type = match content_type {
+        JWS_VOUCHER | COSE_VOUCHER => VoucherTokenType::from_content_type(content_type),
+        _ => return Err(ServerError::UnsupportedMediaType),
+    };
+
+    let not_ours = |e: &dyn std::fmt::Display| {
+        ServerError::BadRequestWithReason(format!("Voucher was not issued by this MASA: {}", e))
+    };
+    // Vouchers carry the MASA certificate of the tenant that signed them, it is verified with that
+    let verified = RawSigned::<VoucherArtifact>::new(bytes.to_vec())
+        .into_verifyable_boxed(voucher_token_type.signature_type().get_sv()?)
+        .verify(None)
+        .map_err(|e| not_ours(&e))?;
+    let tenant = verified
+        .headers()
+        .x509_certificate_chain()
+        .and_then(|chain| chain.into_iter().next())
+        .and_then(|masa_certificate| state.tenants.for_masa_certificate(&masa_certificate))
+        .ok_or_else(|| not_ours(&"signed by an unknown certificate"))?;
+
+    let mut voucher_details = verified.payload().details.clone();
+    let serial_number = voucher_details.serial_number.clone();
+    let pinned =
+        voucher_details
+            .pinned_domain_cert
+            .clone()
+            .ok_or(ServerError::BadRequestWithReason(
+                "Voucher does not pin a domain certificate".to_string(),
+            ))?;
+
+    // A registrar that authenticated via TLS may only renew vouchers of its own domain
+    if let Some(registrar) = TlsClientIdentity::certificate_of(registrar) {
+        if !state.registrars.is_trusted(&registrar)
+            || !belongs_to(&pinned, &X509::from_der(&registrar)?)
+        {
+            return Err(ServerError::Forbidden(
+                "Voucher is pinned to another domain".to_string(),
+            ));
+        }
+    }
+
+    let registrar_domain_id = domain_id(&pinned)?;
+    let owner = tenant.storage.get_owner(&serial_number).await?;
+    let former_owners = tenant.storage.former_owners(&serial_number).await?;
+    let assertion = choose_assertion(
+        &serial_number,
+        owner.as_ref(),
+        &former_owners,
+        &owner_domain_ids(&pinned)?,
+        voucher_details.assertion.as_ref(),
+        tenant.config.unowned_devices,
+    )?;
+    voucher_details.assertion = Some(assertion);
+
+    let now = chrono::Utc::now();
+    voucher_details.created_on = Some(now);
+    tenant
+        .config
+        .lifetime
+        .renew(&mut voucher_details, &pinned, now)?;
+    event!(
+        Level::INFO,
+        "Renewing voucher for {} until {:?}",
+        serial_number,
+        voucher_details.expires_on
+    );
+
+    issue_voucher(
+        tenant,
+        voucher_details,
+        voucher_token_type,
+        registrar_domain_id,
+    )
+    .await
+}
@@ -0,0 +1,83 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderMap},
+    Extension, Json,
+};
+use brski_prm_artifacts::{
+    audit_log::AuditLog,
+    token_type::{TokenType, COSE_VOUCHER, JWS_VOUCHER},
+};
+use common::{server_error::ServerError, tls::TlsClientIdentity, util::domain_id};
+use tracing::{event, Level};
+
+use crate::{
+    rvr::{RvrVerifier, VerifiedRequest},
+    server::server::ServerState,
+};
+
+/// Returns the audit log of the pledge the posted RVR is for (RFC 8995 5.8).
+/// Only registrars of a domain that was issued a voucher for the pledge get to see it.
+#[tracing::instrument(target = "MASA", skip(state, headers, registrar, bytes))]
+pub async fn handle_requestauditlog(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    registrar: Option<Extension<TlsClientIdentity>>,
+    bytes: Bytes,
+) -> Result<Json<AuditLog>, ServerError> {
+    event!(Level::INFO, "Received requestauditlog request");
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .ok_or(ServerError::BadRequest)?
+        .to_str()?;
+    let token_type = match content_type {
+        JWS_VOUCHER | COSE_VOUCHER => TokenType::from_content_type(content_type),
+        _ => return Err(ServerError::UnsupportedMediaType),
+    };
+
+    let VerifiedRequest { rvr, tenant, .. } = RvrVerifier {
+        tenants: &state.tenants,
+        registrars: &state.registrars,
+    }
+    .verify(
+        &bytes,
+        token_type.signature_type(),
+        TlsClientIdentity::certificate_of(registrar),
+        chrono::Utc::now(),
+    )?;
+
+    let registrar_certificate = rvr
+        .details
+        .agent_provided_proximity_registrar_cert
+        .as_ref()
+        .ok_or(ServerError::BadRequestWithReason(
+            "RVR does not carry the registrar certificate".to_string(),
+        ))?;
+    let registrar_domain_id = domain_id(registrar_certificate)?;
+
+    let events = tenant.storage.audit_log(&rvr.details.serial_number).await?;
+
+    if !events
+        .iter()
+        .any(|event| event.domain_id == registrar_domain_id)
+    {
+        event!(
+            Level::ERROR,
+            "Domain {} was never issued a voucher for {}",
+            registrar_domain_id,
+            rvr.details.serial_number
+        );
+        return Err(ServerError::Forbidden(
+            "No voucher was issued to this domain for the pledge".to_string(),
+        ));
+    }
+
+    event!(
+        Level::INFO,
+        "Returning {} audit log entries for {}",
+        events.len(),
+        rvr.details.serial_number
+    );
+    Ok(Json(AuditLog::new(events)))
+}
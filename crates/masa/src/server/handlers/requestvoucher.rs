@@ -2,29 +2,28 @@ use axum::{
     body::Bytes,
     extract::State,
     http::{header::ACCEPT, HeaderMap},
+    Extension,
 };
 use brski_prm_artifacts::{
-    ietf_voucher::{
-        artifact::{VoucherArtifact, VoucherArtifactDetails},
-        VoucherRequest,
-    },
-    issued_voucher::IssuedVoucher,
+    ietf_voucher::artifact::VoucherArtifact,
     token_type::{TokenType, VoucherTokenType},
 };
-use common::server_error::ServerError;
-use signeable_payload::signeable::{
-    raw_signed::RawSigned, signed::Signed, signing_context::BasicSigningContext,
-    unsigned::Unsigned, verifyable::Verifyable,
-};
+use common::{server_error::ServerError, tls::TlsClientIdentity};
+use signeable_payload::signeable::signed::Signed;
 use tracing::{event, Level};
 
-use crate::server::server::ServerState;
+use crate::{
+    issuance::{issue_voucher, voucher_details},
+    rvr::{RvrVerifier, VerifiedRequest},
+    server::server::ServerState,
+};
 
 // We don't trust client's to supply just any base64 encoded data, so we parse it.
-#[tracing::instrument(target = "MASA", skip(state, headers, bytes))]
+#[tracing::instrument(target = "MASA", skip(state, headers, registrar, bytes))]
 pub async fn handle_requestvoucher(
     State(state): State<ServerState>,
     headers: HeaderMap,
+    registrar: Option<Extension<TlsClientIdentity>>,
     bytes: Bytes,
 ) -> Result<Signed<VoucherArtifact>, ServerError> {
     event!(Level::DEBUG, "Headers: {:#?}", headers);
@@ -41,15 +40,17 @@ pub async fn handle_requestvoucher(
 
     let token_type = TokenType::from_content_type(accept);
 
-    let verifyer = token_type.signature_type().get_sv::<VoucherRequest>()?;
-
-    event!(Level::INFO, "Parsing signed RVR from body");
-    let verifyable_rvr: Verifyable<VoucherRequest> =
-        RawSigned::new(bytes.to_vec()).into_verifyable_boxed(verifyer);
-
     event!(Level::INFO, "Verifying signed RVR");
-    let verified = verifyable_rvr.verify(None)?;
-    let rvr = verified.payload().clone();
+    let VerifiedRequest { rvr, pvr, tenant } = RvrVerifier {
+        tenants: &state.tenants,
+        registrars: &state.registrars,
+    }
+    .verify(
+        &bytes,
+        token_type.signature_type(),
+        TlsClientIdentity::certificate_of(registrar),
+        chrono::Utc::now(),
+    )?;
 
     event!(Level::DEBUG, "RVR: {:#?}", rvr);
 
@@ -63,45 +64,21 @@ pub async fn handle_requestvoucher(
         "Registrar requested cert to pin: {:#?}",
         cert_to_pin
     );
-
-    event!(Level::INFO, "Building voucher");
-    let mut voucher_details = VoucherArtifactDetails::default();
-
-    // skip verification for now
-    voucher_details.assertion = rvr.details.assertion;
-    voucher_details.serial_number = rvr.details.serial_number;
-    voucher_details.nonce = rvr.details.nonce;
-    voucher_details.created_on = Some(chrono::Utc::now());
-    voucher_details.pinned_domain_cert = Some(cert_to_pin);
-
-    let voucher_artifact = VoucherArtifact {
-        details: voucher_details,
-    };
-
-    let requested_voucher_token_type = VoucherTokenType::from_content_type(accept);
-
-    let issued_voucher = IssuedVoucher::try_new(
-        voucher_artifact,
-        [state.config.masa_certificate.clone().to_der()?],
-        requested_voucher_token_type.clone(),
-    )?;
-
-    event!(Level::INFO, "Built Voucher");
-    event!(Level::DEBUG, "Issued Voucher: {:#?}", issued_voucher);
-
-    let unsigned_va: Unsigned<VoucherArtifact> = issued_voucher.try_into()?;
-
-    let signer = requested_voucher_token_type
-        .signature_type()
-        .get_sv::<VoucherArtifact>()?;
-
-    let signeable_va = unsigned_va.into_signeable_boxed(signer);
-
-    let ctx = BasicSigningContext::new();
-
-    event!(Level::INFO, "Signing voucher");
-    let signed = signeable_va.sign(state.config.masa_key.clone(), ctx)?;
-
-    event!(Level::INFO, "Issued voucher!");
-    Ok(signed)
+    let (voucher_details, registrar_domain_id) = voucher_details(
+        tenant,
+        rvr.details.serial_number,
+        rvr.details.nonce,
+        cert_to_pin,
+        pvr.details.assertion.as_ref(),
+        chrono::Utc::now(),
+    )
+    .await?;
+
+    issue_voucher(
+        tenant,
+        voucher_details,
+        VoucherTokenType::from_content_type(accept),
+        registrar_domain_id,
+    )
+    .await
 }
@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::ACCEPT, HeaderMap},
+    Json,
+};
+use brski_prm_artifacts::{
+    token_type::{PlainTokenType, COSE, JOSE},
+    transparency_log::{
+        merkle::MerkleTree, ConsistencyProof, InclusionProof, LogEntry, TreeHead, TreeHeadResponse,
+    },
+};
+use common::server_error::ServerError;
+use serde::Deserialize;
+use signeable_payload::signeable::{
+    signed::Signed, signing_context::BasicSigningContext, unsigned::Unsigned,
+};
+use tracing::{event, Level};
+
+use crate::{server::server::ServerState, tenants::TenantQuery};
+
+#[derive(Debug, Deserialize)]
+pub struct InclusionQuery {
+    tenant: Option<String>,
+    leaf_index: u64,
+    /// Defaults to the current size of the log
+    tree_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsistencyQuery {
+    tenant: Option<String>,
+    first: u64,
+    /// Defaults to the current size of the log
+    second: Option<u64>,
+}
+
+/// The current size and root hash of the transparency log, signed by the MASA
+#[tracing::instrument(target = "MASA", skip(state, headers))]
+pub async fn handle_get_tree_head(
+    State(state): State<ServerState>,
+    Query(query): Query<TenantQuery>,
+    headers: HeaderMap,
+) -> Result<Signed<TreeHead>, ServerError> {
+    let token_type = match headers
+        .get(ACCEPT)
+        .map(|accept| accept.to_str())
+        .transpose()?
+    {
+        None => PlainTokenType::JOSE,
+        Some(accept @ (JOSE | COSE)) => PlainTokenType::from_content_type(accept),
+        Some(_) => return Err(ServerError::NotAcceptible),
+    };
+
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    let tree = tenant.log.tree(tenant.storage.as_ref()).await?;
+    let tree_size = tree.size() as u64;
+    let tree_head = TreeHead::new(tree_size, chrono::Utc::now(), &tree.root());
+    drop(tree);
+    event!(Level::INFO, "Signing tree head of size {}", tree_size);
+
+    let unsigned: Unsigned<TreeHead> =
+        TreeHeadResponse::new(tree_head, tenant.signing_chain(), token_type.clone()).try_into()?;
+    let signer = token_type.signature_type().get_sv::<TreeHead>()?;
+
+    Ok(unsigned
+        .into_signeable_boxed(signer)
+        .sign(tenant.config.masa_key.clone(), BasicSigningContext::new())?)
+}
+
+/// Proves an entry is part of the log, for auditors holding a voucher and a tree head
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_get_inclusion_proof(
+    State(state): State<ServerState>,
+    Query(query): Query<InclusionQuery>,
+) -> Result<Json<InclusionProof>, ServerError> {
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    let tree = tenant.log.tree(tenant.storage.as_ref()).await?;
+    let tree_size = tree_size(&tree, query.tree_size)?;
+
+    let audit_path = tree
+        .inclusion_proof(query.leaf_index as usize, tree_size as usize)
+        .ok_or(ServerError::BadRequestWithReason(format!(
+            "Log of size {} has no entry {}",
+            tree_size, query.leaf_index
+        )))?;
+
+    Ok(Json(InclusionProof::new(
+        query.leaf_index,
+        tree_size,
+        &audit_path,
+    )))
+}
+
+/// Proves the log only grew between two tree heads, entries once logged are never altered or removed
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_get_consistency_proof(
+    State(state): State<ServerState>,
+    Query(query): Query<ConsistencyQuery>,
+) -> Result<Json<ConsistencyProof>, ServerError> {
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    let tree = tenant.log.tree(tenant.storage.as_ref()).await?;
+    let second = tree_size(&tree, query.second)?;
+
+    let proof = tree
+        .consistency_proof(query.first as usize, second as usize)
+        .ok_or(ServerError::BadRequestWithReason(format!(
+            "No consistency proof between sizes {} and {}",
+            query.first, second
+        )))?;
+
+    Ok(Json(ConsistencyProof::new(query.first, second, &proof)))
+}
+
+/// Every voucher issued for the pledge, so owners can spot vouchers they did not ask for
+#[tracing::instrument(target = "MASA", skip(state))]
+pub async fn handle_get_log_entries(
+    State(state): State<ServerState>,
+    Path(serial_number): Path<String>,
+    Query(query): Query<TenantQuery>,
+) -> Result<Json<Vec<LogEntry>>, ServerError> {
+    let tenant = state.tenants.get(query.tenant.as_deref())?;
+    Ok(Json(tenant.storage.log_entries(&serial_number).await?))
+}
+
+/// The requested tree size, which cannot exceed the current size of the log
+fn tree_size(tree: &MerkleTree, requested: Option<u64>) -> Result<u64, ServerError> {
+    let size = tree.size() as u64;
+    match requested {
+        None => Ok(size),
+        Some(requested) if requested <= size => Ok(requested),
+        Some(requested) => Err(ServerError::BadRequestWithReason(format!(
+            "Log has only {} entries, not {}",
+            size, requested
+        ))),
+    }
+}
@@ -1,30 +1,49 @@
 use core::time::Duration;
 
-use crate::parsed_config::ParsedConfig;
+use crate::{parsed_config::ParsedConfig, registrars::TrustedRegistrars, tenants::Tenants};
 use axum::Router;
-use common::error::AppError;
+use common::{
+    error::AppError,
+    middleware::{require_admin_token, AdminToken},
+};
 use reqwest::Client;
 use tower_http::{classify::ServerErrorsFailureClass, trace::TraceLayer};
 use tracing::{error, error_span, Span};
 
-use super::handlers::brski_routes;
+use super::handlers::{admin_routes, brski_routes, transparency_routes};
 
 #[derive(Clone)]
 pub struct ServerState {
     pub config: ParsedConfig,
     pub client: reqwest::Client,
+    pub registrars: TrustedRegistrars,
+    pub tenants: Tenants,
 }
 
-pub async fn get_app(config: &ParsedConfig) -> anyhow::Result<Router<()>, AppError> {
+pub async fn get_app(
+    config: &ParsedConfig,
+    registrars: TrustedRegistrars,
+) -> anyhow::Result<Router<()>, AppError> {
     let client = Client::new();
+    let tenants = Tenants::open(config)?;
 
     let state = ServerState {
         config: config.clone(),
         client: client.clone(),
+        registrars,
+        tenants,
     };
 
     let routes = Router::new()
         .nest("/.well-known/brski", brski_routes())
+        .nest("/transparency", transparency_routes())
+        .nest(
+            "/admin",
+            admin_routes().route_layer(axum::middleware::from_fn_with_state(
+                AdminToken(config.config.admin_token.clone()),
+                require_admin_token,
+            )),
+        )
         .layer(TraceLayer::new_for_http().on_failure(
             |_error: ServerErrorsFailureClass, _latency: Duration, _span: &Span| {
                 let _ = _span.enter();
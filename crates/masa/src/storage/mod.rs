@@ -0,0 +1,96 @@
+use brski_prm_artifacts::{
+    audit_log::AuditLogEvent,
+    transparency_log::{merkle::Hash, LogEntry},
+};
+use chrono::{DateTime, Utc};
+use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
+
+mod sqlite;
+
+pub(crate) use sqlite::SqliteStorage;
+
+/// Who owns a pledge, as known from the sales channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct OwnershipRecord {
+    pub(crate) serial_number: String,
+    /// domainID of the owner's registrar CA, see [brski_prm_artifacts::audit_log::domain_id]
+    pub(crate) domain_id: String,
+    /// Name of the customer, for operators
+    pub(crate) owner: Option<String>,
+    pub(crate) updated_at: DateTime<Utc>,
+}
+
+/// A domain that owned a pledge until it was transferred or revoked, its voucher requests are refused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FormerOwner {
+    pub(crate) serial_number: String,
+    pub(crate) domain_id: String,
+    pub(crate) owner: Option<String>,
+    pub(crate) released_at: DateTime<Utc>,
+}
+
+/// A signed voucher as it is appended to the transparency log
+#[derive(Debug, Clone)]
+pub(crate) struct LoggedVoucher {
+    pub(crate) content_type: String,
+    pub(crate) voucher: Vec<u8>,
+}
+
+/// Persistence of the vouchers the MASA issued and of who owns which pledge
+#[async_trait::async_trait]
+pub(crate) trait MasaStorage: Send + Sync + DynClone {
+    /// Appends an issued voucher to the audit log of the pledge and to the transparency log,
+    /// returns its index in the transparency log
+    async fn record_issuance(
+        &self,
+        serial_number: &str,
+        event: AuditLogEvent,
+        voucher: LoggedVoucher,
+    ) -> anyhow::Result<u64>;
+
+    /// Every voucher issued for the pledge, oldest first
+    async fn audit_log(&self, serial_number: &str) -> anyhow::Result<Vec<AuditLogEvent>>;
+
+    /// Number of entries in the transparency log
+    async fn log_size(&self) -> anyhow::Result<u64>;
+
+    /// Leaf hashes of the transparency log entries from index `from` on
+    async fn log_leaves(&self, from: u64) -> anyhow::Result<Vec<Hash>>;
+
+    /// Transparency log entries of the pledge, oldest first
+    async fn log_entries(&self, serial_number: &str) -> anyhow::Result<Vec<LogEntry>>;
+
+    /// Records the owner of the pledges, replacing owners known before
+    async fn set_owners(&self, records: Vec<OwnershipRecord>) -> anyhow::Result<()>;
+
+    async fn get_owner(&self, serial_number: &str) -> anyhow::Result<Option<OwnershipRecord>>;
+
+    async fn list_owners(&self) -> anyhow::Result<Vec<OwnershipRecord>>;
+
+    /// Forgets the owner of the pledge, returns whether one was known
+    async fn remove_owner(&self, serial_number: &str) -> anyhow::Result<bool>;
+
+    /// Makes `record` the owner of its pledge, the previous owner becomes a former owner.
+    /// Returns the previous owner.
+    async fn transfer_owner(
+        &self,
+        record: OwnershipRecord,
+    ) -> anyhow::Result<Option<OwnershipRecord>>;
+
+    /// Forgets the owner of the pledge but keeps it as former owner, returns it
+    async fn revoke_owner(
+        &self,
+        serial_number: &str,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Option<OwnershipRecord>>;
+
+    /// Domains the pledge was transferred away from, oldest first
+    async fn former_owners(&self, serial_number: &str) -> anyhow::Result<Vec<FormerOwner>>;
+}
+
+impl Clone for Box<dyn MasaStorage> {
+    fn clone(&self) -> Self {
+        dyn_clone::clone_box(&**self)
+    }
+}
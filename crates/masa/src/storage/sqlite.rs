@@ -0,0 +1,596 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use brski_prm_artifacts::{
+    audit_log::AuditLogEvent,
+    ietf_voucher::assertion::Assertion,
+    transparency_log::{
+        merkle::{self, Hash},
+        LogEntry,
+    },
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row, Transaction};
+use tracing::{event, Level};
+
+use super::{FormerOwner, LoggedVoucher, MasaStorage, OwnershipRecord};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    serial_number TEXT NOT NULL,
+    date TEXT NOT NULL,
+    domain_id TEXT NOT NULL,
+    nonce TEXT,
+    assertion TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_serial_number ON audit_log (serial_number);
+
+CREATE TABLE IF NOT EXISTS transparency_log (
+    leaf_index INTEGER PRIMARY KEY NOT NULL,
+    serial_number TEXT NOT NULL,
+    date TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    voucher BLOB NOT NULL,
+    leaf_hash BLOB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS transparency_log_serial_number ON transparency_log (serial_number);
+
+CREATE TABLE IF NOT EXISTS owners (
+    serial_number TEXT PRIMARY KEY NOT NULL,
+    domain_id TEXT NOT NULL,
+    owner TEXT,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS former_owners (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    serial_number TEXT NOT NULL,
+    domain_id TEXT NOT NULL,
+    owner TEXT,
+    released_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS former_owners_serial_number ON former_owners (serial_number);
+";
+
+const AUDIT_LOG_COLUMNS: &str = "date, domain_id, nonce, assertion";
+
+const LOG_ENTRY_COLUMNS: &str = "leaf_index, serial_number, date, content_type, voucher";
+
+const OWNER_COLUMNS: &str = "serial_number, domain_id, owner, updated_at";
+
+const FORMER_OWNER_COLUMNS: &str = "serial_number, domain_id, owner, released_at";
+
+/// The default [MasaStorage], keeping everything in a single SQLite database
+#[derive(Clone)]
+pub(crate) struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating it and its parent directories if necessary
+    pub(crate) fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        event!(Level::INFO, "Opening MASA database at {}", path.display());
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> anyhow::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> anyhow::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `f` on the connection without blocking the async runtime
+    async fn with_connection<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow!("MASA database lock poisoned"))?;
+            Ok(f(&mut connection)?)
+        })
+        .await?
+    }
+}
+
+/// Assertions are stored by their YANG name
+fn assertion_to_sql(assertion: &Option<Assertion>) -> Option<String> {
+    serde_json::to_value(assertion)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+}
+
+fn audit_log_event(row: &Row) -> rusqlite::Result<AuditLogEvent> {
+    let assertion = row
+        .get::<_, Option<String>>(3)?
+        .map(|assertion| {
+            serde_json::from_value(serde_json::Value::String(assertion))
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, e.into()))
+        })
+        .transpose()?;
+
+    Ok(AuditLogEvent {
+        date: row.get(0)?,
+        domain_id: row.get(1)?,
+        nonce: row.get(2)?,
+        assertion,
+        truncated: None,
+    })
+}
+
+fn log_entry(row: &Row) -> rusqlite::Result<LogEntry> {
+    Ok(LogEntry {
+        leaf_index: row.get(0)?,
+        serial_number: row.get(1)?,
+        date: row.get(2)?,
+        content_type: row.get(3)?,
+        voucher: STANDARD.encode(row.get::<_, Vec<u8>>(4)?),
+    })
+}
+
+fn leaf_hash(row: &Row) -> rusqlite::Result<Hash> {
+    row.get::<_, Vec<u8>>(0)?.try_into().map_err(|_| {
+        rusqlite::Error::FromSqlConversionFailure(
+            0,
+            Type::Blob,
+            anyhow!("leaf hash is not 32 bytes long").into(),
+        )
+    })
+}
+
+fn ownership_record(row: &Row) -> rusqlite::Result<OwnershipRecord> {
+    Ok(OwnershipRecord {
+        serial_number: row.get(0)?,
+        domain_id: row.get(1)?,
+        owner: row.get(2)?,
+        updated_at: row.get(3)?,
+    })
+}
+
+fn former_owner(row: &Row) -> rusqlite::Result<FormerOwner> {
+    Ok(FormerOwner {
+        serial_number: row.get(0)?,
+        domain_id: row.get(1)?,
+        owner: row.get(2)?,
+        released_at: row.get(3)?,
+    })
+}
+
+/// Removes the owner of the pledge and records it as former owner, unless the pledge stays with `keep_domain_id`
+fn release_owner(
+    transaction: &Transaction,
+    serial_number: &str,
+    keep_domain_id: Option<&str>,
+    now: DateTime<Utc>,
+) -> rusqlite::Result<Option<OwnershipRecord>> {
+    let previous = transaction
+        .query_row(
+            &format!(
+                "SELECT {} FROM owners WHERE serial_number = ?1",
+                OWNER_COLUMNS
+            ),
+            params![serial_number],
+            ownership_record,
+        )
+        .optional()?;
+
+    if let Some(previous) = previous
+        .as_ref()
+        .filter(|previous| Some(previous.domain_id.as_str()) != keep_domain_id)
+    {
+        transaction.execute(
+            &format!(
+                "INSERT INTO former_owners ({}) VALUES (?1, ?2, ?3, ?4)",
+                FORMER_OWNER_COLUMNS
+            ),
+            params![serial_number, previous.domain_id, previous.owner, now],
+        )?;
+    }
+    transaction.execute(
+        "DELETE FROM owners WHERE serial_number = ?1",
+        params![serial_number],
+    )?;
+    Ok(previous)
+}
+
+#[async_trait::async_trait]
+impl MasaStorage for SqliteStorage {
+    async fn record_issuance(
+        &self,
+        serial_number: &str,
+        event: AuditLogEvent,
+        voucher: LoggedVoucher,
+    ) -> anyhow::Result<u64> {
+        let serial_number = serial_number.to_string();
+        let leaf_hash = merkle::leaf_hash(&voucher.voucher);
+
+        self.with_connection(move |connection| {
+            // the audit log and the transparency log never disagree about an issued voucher
+            let transaction = connection.transaction()?;
+            transaction.execute(
+                &format!(
+                    "INSERT INTO audit_log (serial_number, {}) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    AUDIT_LOG_COLUMNS
+                ),
+                params![
+                    serial_number,
+                    event.date,
+                    event.domain_id,
+                    event.nonce,
+                    assertion_to_sql(&event.assertion)
+                ],
+            )?;
+            transaction.execute(
+                &format!(
+                    "INSERT INTO transparency_log ({}, leaf_hash) \
+                     VALUES ((SELECT COUNT(*) FROM transparency_log), ?1, ?2, ?3, ?4, ?5)",
+                    LOG_ENTRY_COLUMNS
+                ),
+                params![
+                    serial_number,
+                    event.date,
+                    voucher.content_type,
+                    voucher.voucher,
+                    leaf_hash.to_vec()
+                ],
+            )?;
+            let leaf_index = transaction.last_insert_rowid() as u64;
+            transaction.commit()?;
+            Ok(leaf_index)
+        })
+        .await
+    }
+
+    async fn audit_log(&self, serial_number: &str) -> anyhow::Result<Vec<AuditLogEvent>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM audit_log WHERE serial_number = ?1 ORDER BY id",
+                AUDIT_LOG_COLUMNS
+            ))?;
+            let events = statement
+                .query_map(params![serial_number], audit_log_event)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(events)
+        })
+        .await
+    }
+
+    async fn log_size(&self) -> anyhow::Result<u64> {
+        self.with_connection(|connection| {
+            connection.query_row("SELECT COUNT(*) FROM transparency_log", [], |row| {
+                row.get(0)
+            })
+        })
+        .await
+    }
+
+    async fn log_leaves(&self, from: u64) -> anyhow::Result<Vec<Hash>> {
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT leaf_hash FROM transparency_log WHERE leaf_index >= ?1 ORDER BY leaf_index",
+            )?;
+            let leaves = statement
+                .query_map(params![from], leaf_hash)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(leaves)
+        })
+        .await
+    }
+
+    async fn log_entries(&self, serial_number: &str) -> anyhow::Result<Vec<LogEntry>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM transparency_log WHERE serial_number = ?1 ORDER BY leaf_index",
+                LOG_ENTRY_COLUMNS
+            ))?;
+            let entries = statement
+                .query_map(params![serial_number], log_entry)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(entries)
+        })
+        .await
+    }
+
+    async fn set_owners(&self, records: Vec<OwnershipRecord>) -> anyhow::Result<()> {
+        self.with_connection(move |connection| {
+            // a sales record import is applied completely or not at all
+            let transaction = connection.transaction()?;
+            for record in records {
+                transaction.execute(
+                    &format!(
+                        "INSERT OR REPLACE INTO owners ({}) VALUES (?1, ?2, ?3, ?4)",
+                        OWNER_COLUMNS
+                    ),
+                    params![
+                        record.serial_number,
+                        record.domain_id,
+                        record.owner,
+                        record.updated_at
+                    ],
+                )?;
+            }
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn get_owner(&self, serial_number: &str) -> anyhow::Result<Option<OwnershipRecord>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM owners WHERE serial_number = ?1",
+                        OWNER_COLUMNS
+                    ),
+                    params![serial_number],
+                    ownership_record,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    async fn list_owners(&self) -> anyhow::Result<Vec<OwnershipRecord>> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM owners ORDER BY serial_number",
+                OWNER_COLUMNS
+            ))?;
+            let records = statement
+                .query_map([], ownership_record)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(records)
+        })
+        .await
+    }
+
+    async fn remove_owner(&self, serial_number: &str) -> anyhow::Result<bool> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            let removed = connection.execute(
+                "DELETE FROM owners WHERE serial_number = ?1",
+                params![serial_number],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn transfer_owner(
+        &self,
+        record: OwnershipRecord,
+    ) -> anyhow::Result<Option<OwnershipRecord>> {
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+            let previous = release_owner(
+                &transaction,
+                &record.serial_number,
+                Some(&record.domain_id),
+                record.updated_at,
+            )?;
+            // a domain the pledge is transferred back to is no longer a former owner
+            transaction.execute(
+                "DELETE FROM former_owners WHERE serial_number = ?1 AND domain_id = ?2",
+                params![record.serial_number, record.domain_id],
+            )?;
+            transaction.execute(
+                &format!(
+                    "INSERT INTO owners ({}) VALUES (?1, ?2, ?3, ?4)",
+                    OWNER_COLUMNS
+                ),
+                params![
+                    record.serial_number,
+                    record.domain_id,
+                    record.owner,
+                    record.updated_at
+                ],
+            )?;
+            transaction.commit()?;
+            Ok(previous)
+        })
+        .await
+    }
+
+    async fn revoke_owner(
+        &self,
+        serial_number: &str,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Option<OwnershipRecord>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+            let previous = release_owner(&transaction, &serial_number, None, now)?;
+            transaction.commit()?;
+            Ok(previous)
+        })
+        .await
+    }
+
+    async fn former_owners(&self, serial_number: &str) -> anyhow::Result<Vec<FormerOwner>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM former_owners WHERE serial_number = ?1 ORDER BY id",
+                FORMER_OWNER_COLUMNS
+            ))?;
+            let former_owners = statement
+                .query_map(params![serial_number], former_owner)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(former_owners)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_keeps_an_audit_log_per_pledge() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let issuance = |domain_id: &str, nonce: Option<&str>| AuditLogEvent {
+            date: Utc::now(),
+            domain_id: domain_id.to_string(),
+            nonce: nonce.map(str::to_string),
+            assertion: Some(Assertion::AgentProximity),
+            truncated: None,
+        };
+        let voucher = |voucher: &[u8]| LoggedVoucher {
+            content_type: "application/voucher+cose".to_string(),
+            voucher: voucher.to_vec(),
+        };
+
+        for (serial_number, event, content, leaf_index) in [
+            (
+                "0123456789",
+                issuance("first", Some("bm9uY2U=")),
+                &b"first"[..],
+                0,
+            ),
+            ("0123456789", issuance("second", None), &b"second"[..], 1),
+            ("9876543210", issuance("other", None), &b"other"[..], 2),
+        ] {
+            assert_eq!(
+                storage
+                    .record_issuance(serial_number, event, voucher(content))
+                    .await
+                    .unwrap(),
+                leaf_index
+            );
+        }
+
+        let log = storage.audit_log("0123456789").await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].domain_id, "first");
+        assert_eq!(log[0].nonce.as_deref(), Some("bm9uY2U="));
+        assert_eq!(log[0].assertion, Some(Assertion::AgentProximity));
+        assert_eq!(log[1].nonce, None);
+
+        assert!(storage.audit_log("unknown").await.unwrap().is_empty());
+
+        assert_eq!(storage.log_size().await.unwrap(), 3);
+        assert_eq!(storage.log_leaves(0).await.unwrap().len(), 3);
+        assert_eq!(
+            storage.log_leaves(1).await.unwrap(),
+            vec![merkle::leaf_hash(b"second"), merkle::leaf_hash(b"other")]
+        );
+        let entries = storage.log_entries("9876543210").await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].leaf_index, 2);
+        assert_eq!(entries[0].voucher().unwrap(), b"other");
+    }
+
+    #[tokio::test]
+    async fn it_tracks_owners() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let record = |serial_number: &str, domain_id: &str| OwnershipRecord {
+            serial_number: serial_number.to_string(),
+            domain_id: domain_id.to_string(),
+            owner: Some("ACME".to_string()),
+            updated_at: Utc::now(),
+        };
+
+        storage
+            .set_owners(vec![
+                record("0123456789", "first"),
+                record("9876543210", "first"),
+            ])
+            .await
+            .unwrap();
+        storage
+            .set_owners(vec![record("0123456789", "second")])
+            .await
+            .unwrap();
+
+        let owner = storage.get_owner("0123456789").await.unwrap().unwrap();
+        assert_eq!(owner.domain_id, "second");
+        assert_eq!(storage.list_owners().await.unwrap().len(), 2);
+
+        assert!(storage.remove_owner("9876543210").await.unwrap());
+        assert!(!storage.remove_owner("9876543210").await.unwrap());
+        assert_eq!(storage.get_owner("9876543210").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn it_transfers_owners() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let record = |domain_id: &str| OwnershipRecord {
+            serial_number: "0123456789".to_string(),
+            domain_id: domain_id.to_string(),
+            owner: None,
+            updated_at: Utc::now(),
+        };
+
+        assert_eq!(storage.transfer_owner(record("first")).await.unwrap(), None);
+        let previous = storage.transfer_owner(record("second")).await.unwrap();
+        assert_eq!(previous.unwrap().domain_id, "first");
+        assert_eq!(
+            storage
+                .get_owner("0123456789")
+                .await
+                .unwrap()
+                .unwrap()
+                .domain_id,
+            "second"
+        );
+
+        let revoked = storage
+            .revoke_owner("0123456789", Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(revoked.unwrap().domain_id, "second");
+        assert_eq!(storage.get_owner("0123456789").await.unwrap(), None);
+        assert_eq!(
+            storage
+                .former_owners("0123456789")
+                .await
+                .unwrap()
+                .iter()
+                .map(|former| former.domain_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+
+        storage.transfer_owner(record("first")).await.unwrap();
+        assert_eq!(storage.former_owners("0123456789").await.unwrap().len(), 1);
+        assert_eq!(
+            storage
+                .revoke_owner("9876543210", Utc::now())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use common::{server_error::ServerError, util::verify_certificate_chain};
+use openssl::x509::X509;
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::{
+    parsed_config::{ParsedConfig, ParsedTenant},
+    storage::{MasaStorage, SqliteStorage},
+    transparency::TransparencyLog,
+};
+
+/// Selects a tenant in the admin and transparency APIs, the default tenant if unset
+#[derive(Debug, Deserialize)]
+pub struct TenantQuery {
+    pub tenant: Option<String>,
+}
+
+/// A product line with its own storage, so owners and logs are never shared between tenants
+#[derive(Clone)]
+pub(crate) struct Tenant {
+    pub(crate) config: ParsedTenant,
+    pub(crate) storage: Box<dyn MasaStorage>,
+    pub(crate) log: TransparencyLog,
+}
+
+impl Tenant {
+    /// The x5c of everything the tenant signs, the MASA certificate followed by the intermediate CAs
+    pub(crate) fn signing_chain(&self) -> Vec<X509> {
+        std::iter::once(&self.config.masa_certificate)
+            .chain(&self.config.intermediate_certificates)
+            .cloned()
+            .collect()
+    }
+
+    /// Checks that the vendor CA of the tenant issued the IDevID heading `idevid_chain`
+    pub(crate) fn issued(&self, idevid_chain: &[Vec<u8>]) -> Result<(), String> {
+        let mut chain = idevid_chain.to_vec();
+        for intermediate in &self.config.intermediate_certificates {
+            chain.push(intermediate.to_der().map_err(|e| e.to_string())?);
+        }
+        verify_certificate_chain(&self.config.ca_certificate, &chain)
+    }
+}
+
+/// All tenants of the MASA, the one configured by the `[masa]` section first
+#[derive(Clone)]
+pub(crate) struct Tenants {
+    tenants: Arc<Vec<Tenant>>,
+}
+
+impl Tenants {
+    /// Opens the database of every tenant
+    pub(crate) fn open(config: &ParsedConfig) -> anyhow::Result<Self> {
+        let tenants = config
+            .tenants
+            .iter()
+            .map(|tenant| {
+                Ok(Tenant {
+                    config: tenant.clone(),
+                    storage: Box::new(SqliteStorage::open(&tenant.database)?),
+                    log: TransparencyLog::default(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        event!(Level::INFO, "Serving {} tenants", tenants.len());
+        Ok(Self::new(tenants))
+    }
+
+    pub(crate) fn new(tenants: Vec<Tenant>) -> Self {
+        Self {
+            tenants: Arc::new(tenants),
+        }
+    }
+
+    pub(crate) fn default_tenant(&self) -> &Tenant {
+        &self.tenants[0]
+    }
+
+    /// The tenant by name, the default tenant if `name` is unset
+    pub(crate) fn get(&self, name: Option<&str>) -> Result<&Tenant, ServerError> {
+        match name {
+            None => Ok(self.default_tenant()),
+            Some(name) => self
+                .tenants
+                .iter()
+                .find(|tenant| tenant.config.name == name)
+                .ok_or(ServerError::NotFound),
+        }
+    }
+
+    /// The tenant whose vendor CA issued the pledge IDevID, or why none did
+    pub(crate) fn for_idevid(&self, idevid_chain: &[Vec<u8>]) -> Result<&Tenant, String> {
+        let mut reasons = vec![];
+        for tenant in self.tenants.iter() {
+            match tenant.issued(idevid_chain) {
+                Ok(()) => return Ok(tenant),
+                Err(reason) => reasons.push(format!("{}: {}", tenant.config.name, reason)),
+            }
+        }
+        Err(reasons.join(", "))
+    }
+
+    /// The tenant signing with the MASA certificate `der`
+    pub(crate) fn for_masa_certificate(&self, der: &[u8]) -> Option<&Tenant> {
+        self.tenants.iter().find(|tenant| {
+            tenant
+                .config
+                .masa_certificate
+                .to_der()
+                .is_ok_and(|masa_certificate| masa_certificate == der)
+        })
+    }
+}
+
+#[cfg(test)]
+impl Tenant {
+    /// A tenant with an in-memory database and the default policy
+    pub(crate) fn in_memory(
+        name: &str,
+        ca_certificate: X509,
+        masa_certificate: X509,
+        masa_key: &openssl::pkey::PKey<openssl::pkey::Private>,
+    ) -> Self {
+        let config = cli::config::MasaConfig::default();
+
+        Self {
+            config: ParsedTenant {
+                name: name.to_string(),
+                ca_certificate,
+                intermediate_certificates: vec![],
+                masa_certificate,
+                masa_key: masa_key.private_key_to_pkcs8().unwrap(),
+                database: std::path::PathBuf::new(),
+                unowned_devices: config.unowned_devices,
+                lifetime: crate::lifetime::VoucherLifetime::new(
+                    config.nonceless_vouchers,
+                    config.voucher_lifetime,
+                    config.renewal_period,
+                ),
+            },
+            storage: Box::new(SqliteStorage::in_memory().unwrap()),
+            log: TransparencyLog::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use example_certs::{generate_certs, OpensslTestCerts};
+
+    use super::*;
+
+    #[test]
+    fn it_selects_tenants() {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let (vendor, vendor_key) = &certs.vendor;
+        let default = Tenant::in_memory(
+            "default",
+            certs.registrar_ca.0.clone(),
+            certs.registrar.0.clone(),
+            &certs.registrar.1,
+        );
+        let tenants = Tenants::new(vec![
+            default.clone(),
+            Tenant::in_memory(
+                "lighting",
+                certs.vendor_ca.0.clone(),
+                vendor.clone(),
+                vendor_key,
+            ),
+        ]);
+
+        let idevid = vec![certs.pledge.0.to_der().unwrap()];
+        assert_eq!(tenants.for_idevid(&idevid).unwrap().config.name, "lighting");
+        assert!(Tenants::new(vec![default]).for_idevid(&idevid).is_err());
+
+        assert_eq!(
+            tenants
+                .for_masa_certificate(&vendor.to_der().unwrap())
+                .unwrap()
+                .config
+                .name,
+            "lighting"
+        );
+        assert_eq!(tenants.get(None).unwrap().config.name, "default");
+        assert!(matches!(
+            tenants.get(Some("unknown")),
+            Err(ServerError::NotFound)
+        ));
+        assert_eq!(tenants.default_tenant().signing_chain().len(), 1);
+    }
+}
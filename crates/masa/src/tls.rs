@@ -0,0 +1,75 @@
+use std::{sync::Arc, time::SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+use openssl::{asn1::Asn1Time, x509::X509};
+use rustls::{
+    server::{ClientCertVerified, ClientCertVerifier},
+    Certificate, CertificateError, DistinguishedName, PrivateKey, ServerConfig,
+};
+
+use crate::{parsed_config::ParsedConfig, registrars::TrustedRegistrars};
+
+/// Serves the MASA certificate, asking clients for the certificate of a trusted registrar
+pub(crate) fn server_config(
+    config: &ParsedConfig,
+    registrars: TrustedRegistrars,
+) -> anyhow::Result<RustlsConfig> {
+    let chain = vec![
+        Certificate(config.masa_certificate.to_der()?),
+        Certificate(config.ca_certificate.to_der()?),
+    ];
+
+    let verifier = RegistrarCertVerifier {
+        registrars,
+        mandatory: config.config.require_client_auth,
+    };
+
+    let mut server_config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(Arc::new(verifier))
+        .with_single_cert(chain, PrivateKey(config.masa_key.clone()))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(RustlsConfig::from_config(Arc::new(server_config)))
+}
+
+/// Accepts exactly the certificates in [TrustedRegistrars] while they are valid
+struct RegistrarCertVerifier {
+    registrars: TrustedRegistrars,
+    mandatory: bool,
+}
+
+impl ClientCertVerifier for RegistrarCertVerifier {
+    fn client_auth_mandatory(&self) -> bool {
+        self.mandatory
+    }
+
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        // registrars are trusted individually, there is no CA to hint at
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        if !self.registrars.is_trusted(&end_entity.0) {
+            return Err(CertificateError::ApplicationVerificationFailure.into());
+        }
+
+        let certificate =
+            X509::from_der(&end_entity.0).map_err(|_| CertificateError::BadEncoding)?;
+        let now = Asn1Time::days_from_now(0)
+            .map_err(|_| CertificateError::ApplicationVerificationFailure)?;
+        if certificate.not_before() > now {
+            return Err(CertificateError::NotValidYet.into());
+        }
+        if certificate.not_after() < now {
+            return Err(CertificateError::Expired.into());
+        }
+
+        Ok(ClientCertVerified::assertion())
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use brski_prm_artifacts::transparency_log::merkle::MerkleTree;
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::storage::MasaStorage;
+
+/// The Merkle tree of a tenant's transparency log, read from storage once and extended as vouchers are logged,
+/// so tree heads and proofs do not reload and rehash every entry
+#[derive(Clone, Default)]
+pub(crate) struct TransparencyLog {
+    tree: Arc<RwLock<Option<MerkleTree>>>,
+}
+
+impl TransparencyLog {
+    /// The tree of every entry logged so far.
+    /// Vouchers may be logged by other processes, e.g. `open-brski masa issue`, so the tree is compared
+    /// against the stored log and extended if it fell behind.
+    pub(crate) async fn tree(
+        &self,
+        storage: &dyn MasaStorage,
+    ) -> anyhow::Result<RwLockReadGuard<'_, MerkleTree>> {
+        let size = storage.log_size().await? as usize;
+        loop {
+            if let Ok(tree) = RwLockReadGuard::try_map(self.tree.read().await, |tree| {
+                tree.as_ref().filter(|tree| tree.size() >= size)
+            }) {
+                return Ok(tree);
+            }
+            self.catch_up(storage).await?;
+        }
+    }
+
+    /// Appends the entries logged since the tree was last read from storage.
+    /// Entries are read back in log order, so concurrent issuances cannot reorder the tree.
+    pub(crate) async fn catch_up(&self, storage: &dyn MasaStorage) -> anyhow::Result<()> {
+        let mut tree = self.tree.write().await;
+        let tree = tree.get_or_insert_with(MerkleTree::new);
+        for leaf in storage.log_leaves(tree.size() as u64).await? {
+            tree.append(leaf);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use brski_prm_artifacts::{
+        audit_log::AuditLogEvent,
+        transparency_log::merkle::{leaf_hash, root},
+    };
+
+    use super::*;
+    use crate::storage::{LoggedVoucher, SqliteStorage};
+
+    #[tokio::test]
+    async fn it_extends_the_tree_as_vouchers_are_logged() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let log_voucher = |voucher: &'static [u8]| {
+            storage.record_issuance(
+                "0123456789",
+                AuditLogEvent {
+                    date: chrono::Utc::now(),
+                    domain_id: "domain".to_string(),
+                    nonce: None,
+                    assertion: None,
+                    truncated: None,
+                },
+                LoggedVoucher {
+                    content_type: "application/voucher+cose".to_string(),
+                    voucher: voucher.to_vec(),
+                },
+            )
+        };
+
+        log_voucher(b"first").await.unwrap();
+        let log = TransparencyLog::default();
+        assert_eq!(
+            log.tree(&storage).await.unwrap().root(),
+            root(&[leaf_hash(b"first")])
+        );
+
+        // logged without catching up, as offline issuance does from another process
+        log_voucher(b"second").await.unwrap();
+        let tree = log.tree(&storage).await.unwrap();
+        assert_eq!(tree.size(), 2);
+        assert_eq!(
+            tree.root(),
+            root(&[leaf_hash(b"first"), leaf_hash(b"second")])
+        );
+    }
+}
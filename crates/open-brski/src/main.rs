@@ -23,6 +23,24 @@ async fn main() -> anyhow::Result<(), AppError> {
         return Ok(());
     }
 
+    let masa_command = match &cli.command {
+        cli::Command::Masa(masa_cli) => masa_cli.command.clone(),
+        _ => None,
+    };
+    if let Some(cli::MasaCommand::Issue(args)) = masa_command {
+        masa::issue(config.masa, args).await?;
+        return Ok(());
+    }
+
+    let registrar_agent_command = match &cli.command {
+        cli::Command::RegistrarAgent(registrar_agent_cli) => registrar_agent_cli.command.clone(),
+        _ => None,
+    };
+    if let Some(command) = registrar_agent_command {
+        registrar_agent::run_command(config.registrar_agent, command).await?;
+        return Ok(());
+    }
+
     let mut tasks: Vec<JoinHandle<_>> = match &cli.command {
         cli::Command::RegistrarAgent(_) => vec![registrar_agent::start(config.registrar_agent)
             .await
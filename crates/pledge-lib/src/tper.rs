@@ -1,10 +1,8 @@
 use anyhow::Result;
 use brski_prm_artifacts::{
     ietf_voucher::pki::{X509Req, X509},
-    per::{
-        response::PledgeEnrollRequestResponse,
-        response_payload::{PledgeEnrollRequest, ResponsePayloadInner},
-    },
+    per::{response::PledgeEnrollRequestResponse, response_payload::PledgeEnrollRequest},
+    protocol_version::ProtocolVersion,
     token_type::PlainTokenType,
 };
 use signeable_payload::{BasicSigningContext, Signed, Unsigned};
@@ -14,19 +12,17 @@ pub struct TransformPerArgs {
     pub signature_type: PlainTokenType,
     pub pledge_idevid_key: Vec<u8>,
     pub pledge_idevid_chain: Vec<X509>,
+    pub protocol_version: ProtocolVersion,
 }
 
 pub fn create_per(
     x509_req: impl Into<X509Req>,
     pledge_idevid_certs: impl IntoIterator<Item = impl Into<X509>>,
     signature_type: PlainTokenType,
+    protocol_version: ProtocolVersion,
 ) -> PledgeEnrollRequestResponse {
     info!("Building tPER response payload");
-    let payload = PledgeEnrollRequest {
-        csr: ResponsePayloadInner {
-            p10_csr: x509_req.into(),
-        },
-    };
+    let payload = PledgeEnrollRequest::with_protocol_version(x509_req.into(), protocol_version);
     PledgeEnrollRequestResponse::new(payload, pledge_idevid_certs, signature_type)
 }
 pub fn transform_per(args: TransformPerArgs) -> Result<Signed<PledgeEnrollRequest>> {
@@ -35,6 +31,7 @@ pub fn transform_per(args: TransformPerArgs) -> Result<Signed<PledgeEnrollReques
         args.x509_req,
         args.pledge_idevid_chain,
         args.signature_type.clone(),
+        args.protocol_version,
     );
     let unsinged_per: Unsigned<PledgeEnrollRequest> = per.try_into()?;
     info!("Built tPER response");
@@ -66,7 +66,7 @@ fn create_voucher_requst(args: CreateVoucherRequestArgs) -> VoucherRequest {
     voucher_request_details.serial_number = args.serial_number.clone();
     voucher_request_details.assertion = Some(requested_assertion);
     voucher_request_details.agent_provided_proximity_registrar_cert =
-        Some(args.trigger.agent_signed_proximity_cert);
+        Some(args.trigger.agent_provided_proximity_registrar_cert);
     voucher_request_details.agent_signed_data = Some(args.trigger.agent_signed_data.into());
 
     let voucher_request =
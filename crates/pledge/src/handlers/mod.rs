@@ -1,6 +1,7 @@
 mod dif;
 mod pi;
 mod qps;
+mod reset;
 mod scac;
 mod ser;
 mod svr;
@@ -25,3 +26,9 @@ pub(crate) fn brski_routes() -> Router<ServerState> {
         .route("/dif", get(dif::handle_dif))
         .route("/pi", get(pi::handle_pi))
 }
+
+/// Stands in for the reset button of a real device, guarded by [common::middleware::require_admin_token]
+#[tracing::instrument(target = "Pledge")]
+pub(crate) fn admin_routes() -> Router<ServerState> {
+    Router::new().route("/factory-reset", post(reset::handle_factory_reset))
+}
@@ -0,0 +1,138 @@
+use axum::{extract::State, http::StatusCode};
+use tracing::{event, Level};
+
+use crate::server::ServerState;
+
+/// Clears the installed voucher, trust anchor, CA certificates and LDevID, the pledge can then be onboarded again
+#[tracing::instrument(target = "Pledge", skip(state))]
+pub async fn handle_factory_reset(State(state): State<ServerState>) -> StatusCode {
+    state.write().await.factory_reset();
+
+    event!(Level::INFO, "Pledge was factory reset");
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{
+            header::{ACCEPT, CONTENT_TYPE},
+            Request, StatusCode,
+        },
+        Router,
+    };
+    use brski_prm_artifacts::{
+        ietf_voucher::{artifact::VoucherArtifact, pki::X509},
+        issued_voucher::IssuedVoucher,
+        token_type::{VoucherTokenType, JOSE, JWS_VOUCHER},
+    };
+    use cli::config::PledgeConfig;
+    use signeable_payload::{BasicSigningContext, DefaultSignerVerifyer, Unsigned};
+    use tower::util::ServiceExt;
+
+    use crate::util::{get_test_app, get_test_app_with_state};
+
+    fn factory_reset(token: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/admin/factory-reset");
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    /// Delivers a voucher pinning `domain` to the pledge, signed by the example MASA
+    async fn svr(app: &Router, domain: &openssl::x509::X509) -> StatusCode {
+        let certs: example_certs::OpensslTestCerts = example_certs::generate_certs().into();
+
+        let mut voucher = VoucherArtifact::default();
+        voucher.details.created_on = Some(chrono::Utc::now());
+        voucher.details.serial_number = "00-D0-E5-F2-00-02".to_string();
+        voucher.details.pinned_domain_cert = Some(domain.clone().into());
+        let unsigned: Unsigned<VoucherArtifact> = IssuedVoucher::try_new(
+            voucher,
+            [certs.vendor.0.to_der().unwrap()],
+            VoucherTokenType::JWS,
+        )
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let signed = unsigned
+            .into_signeable(DefaultSignerVerifyer::default())
+            .sign(
+                certs.vendor.1.private_key_to_pkcs8().unwrap(),
+                BasicSigningContext::new(),
+            )
+            .unwrap();
+
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/.well-known/brski/svr")
+                    .header(CONTENT_TYPE, JWS_VOUCHER)
+                    .header(ACCEPT, JOSE)
+                    .body(Body::from(signed.data()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_factory_reset() {
+        let (app, state) = get_test_app_with_state(PledgeConfig {
+            admin_token: Some("secret".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let certs: example_certs::OpensslTestCerts = example_certs::generate_certs().into();
+        let (domain, other_domain) = (certs.registrar_ca.0, certs.vendor_ca.0);
+
+        assert_eq!(svr(&app, &domain).await, StatusCode::OK);
+        {
+            let mut state = state.write().await;
+            state.cacerts = Some(vec![X509::from(domain.clone())]);
+            state.ldevid_cert = Some(X509::from(certs.pledge.0.clone()));
+        }
+
+        // an onboarded pledge keeps its domain
+        assert_eq!(svr(&app, &other_domain).await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            state.read().await.trust_anchor,
+            Some(X509::from(domain.clone()))
+        );
+
+        let response = app
+            .clone()
+            .oneshot(factory_reset(Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        {
+            let state = state.read().await;
+            assert!(state.voucher.is_none());
+            assert!(state.trust_anchor.is_none());
+            assert!(state.cacerts.is_none());
+            assert!(state.ldevid_cert.is_none());
+        }
+
+        assert_eq!(svr(&app, &other_domain).await, StatusCode::OK);
+        assert_eq!(
+            state.read().await.trust_anchor,
+            Some(X509::from(other_domain))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_factory_reset_is_disabled_by_default() {
+        let app = get_test_app().await.unwrap();
+
+        let response = app.oneshot(factory_reset(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
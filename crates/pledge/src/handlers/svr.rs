@@ -62,10 +62,26 @@ pub async fn handle_svr(
     let trust_anchor = voucher
         .details
         .pinned_domain_cert
+        .clone()
         .ok_or(ServerError::BadRequest)?;
 
     // Install the trust anchor, whatever that means...
-    state.write().await.trust_anchor = Some(trust_anchor);
+    {
+        let mut state = state.write().await;
+        // An onboarded pledge only moves to another domain after a factory reset
+        if state
+            .trust_anchor
+            .as_ref()
+            .is_some_and(|installed| *installed != trust_anchor)
+        {
+            return Err(ServerError::Forbidden(
+                "Pledge is onboarded to another domain, it has to be factory reset first"
+                    .to_string(),
+            ));
+        }
+        state.trust_anchor = Some(trust_anchor);
+        state.voucher = Some(voucher);
+    }
 
     let pledge_idevid_cert = state.read().await.config.idevid_certificate.clone();
     let pledge_idevid_key = state.read().await.config.idevid_privkey.clone();
@@ -90,6 +90,7 @@ pub async fn handle_tper(
         signature_type: requested_token_type,
         pledge_idevid_key: private_key,
         pledge_idevid_chain: vec![x509],
+        protocol_version: cloned_state.config.pledge_info.protocol_version,
     };
 
     let signed = pledge_lib::tper::transform_per(args)?;
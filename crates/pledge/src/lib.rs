@@ -5,7 +5,7 @@ mod server;
 use parsed_config::parse_config;
 
 use cli::config::PledgeConfig;
-use common::error::AppError;
+use common::{dns_sd::PledgeAnnouncer, error::AppError};
 use tokio::task::JoinHandle;
 use tracing::{event, Level};
 mod util;
@@ -27,7 +27,21 @@ pub async fn start(config: PledgeConfig) -> anyhow::Result<JoinHandle<()>, AppEr
 
     event!(Level::INFO, "Starting Server on {}", address);
 
-    let server_handle = tokio::spawn(async { axum::serve(listener, app).await.unwrap() });
+    let announcer = if parsed_config.config.advertise {
+        Some(PledgeAnnouncer::announce(
+            &parsed_config.config.idev_id,
+            parsed_address.port(),
+            &parsed_config.pledge_info,
+        )?)
+    } else {
+        None
+    };
+
+    let server_handle = tokio::spawn(async move {
+        // the pledge stays announced for as long as it serves
+        let _announcer = announcer;
+        axum::serve(listener, app).await.unwrap()
+    });
 
     Ok(server_handle)
 }
@@ -19,9 +19,10 @@ pub(crate) struct ParsedConfig {
     pub(crate) pledge_info: PledgeInfo,
 }
 
-#[tracing::instrument]
-fn get_pledge_info() -> PledgeInfo {
-    let res = PledgeInfo::simple_cbor();
+#[tracing::instrument(skip(config))]
+fn get_pledge_info(config: &PledgeConfig) -> PledgeInfo {
+    let mut res = PledgeInfo::simple_cbor();
+    res.protocol_version = config.protocol_version;
 
     info!("Pledge info: {:?}", res);
     res
@@ -37,9 +38,9 @@ pub(crate) fn parse_config(config: PledgeConfig) -> anyhow::Result<ParsedConfig,
     let unparsed_idevid_cert = std::fs::read(config.idevid_certificate.relative())?;
     let idevid_cert = X509::from_pem(&unparsed_idevid_cert)?;
     Ok(ParsedConfig {
+        pledge_info: get_pledge_info(&config),
         config,
         idevid_certificate: idevid_cert,
         idevid_privkey: ee_key,
-        pledge_info: get_pledge_info(),
     })
 }
@@ -3,11 +3,14 @@ use std::{fmt::Debug, sync::Arc};
 use crate::parsed_config::ParsedConfig;
 use axum::{response::Response, Router};
 use brski_prm_artifacts::ietf_voucher::{artifact::VoucherArtifact, pki::X509};
-use common::error::AppError;
+use common::{
+    error::AppError,
+    middleware::{require_admin_token, AdminToken},
+};
 use tower_http::trace::TraceLayer;
 use tracing::{event, info, Level, Span};
 
-use super::handlers::brski_routes;
+use super::handlers::{admin_routes, brski_routes};
 
 use tokio::{
     sync::RwLock,
@@ -20,6 +23,17 @@ pub struct State {
     pub cacerts: Option<Vec<X509>>,
     pub ldevid_cert: Option<X509>,
     pub trust_anchor: Option<X509>,
+    pub voucher: Option<VoucherArtifact>,
+}
+
+impl State {
+    /// Forgets everything installed during onboarding, so a new owner can onboard the pledge
+    pub fn factory_reset(&mut self) {
+        self.voucher = None;
+        self.trust_anchor = None;
+        self.cacerts = None;
+        self.ldevid_cert = None;
+    }
 }
 
 impl Debug for State {
@@ -34,16 +48,32 @@ impl Debug for State {
 
 pub type ServerState = Arc<RwLock<State>>;
 pub async fn get_app(config: &ParsedConfig) -> anyhow::Result<Router<()>, AppError> {
+    Ok(get_app_with_state(config).await?.0)
+}
+
+/// Like [get_app], also returning the state shared by the handlers
+pub async fn get_app_with_state(
+    config: &ParsedConfig,
+) -> anyhow::Result<(Router<()>, ServerState), AppError> {
     let state = State {
         config: config.clone(),
         cacerts: None,
         ldevid_cert: None,
         trust_anchor: None,
+        voucher: None,
     };
 
     let server_state = Arc::new(RwLock::new(state));
 
-    let routes = Router::new().nest("/.well-known/brski", brski_routes());
+    let routes = Router::new()
+        .nest("/.well-known/brski", brski_routes())
+        .nest(
+            "/admin",
+            admin_routes().route_layer(axum::middleware::from_fn_with_state(
+                AdminToken(config.config.admin_token.clone()),
+                require_admin_token,
+            )),
+        );
 
     let app = routes
         .with_state(Arc::clone(&server_state))
@@ -57,6 +87,7 @@ pub async fn get_app(config: &ParsedConfig) -> anyhow::Result<Router<()>, AppErr
             },
         ));
 
+    let logged_state = Arc::clone(&server_state);
     tokio::spawn(async move {
         let sleep = time::sleep(Duration::from_millis(10));
         tokio::pin!(sleep);
@@ -66,11 +97,11 @@ pub async fn get_app(config: &ParsedConfig) -> anyhow::Result<Router<()>, AppErr
                 () = &mut sleep => {
                     println!("timer elapsed");
                     sleep.as_mut().reset(Instant::now() + Duration::from_secs(30));
-                    event!(Level::INFO, "Server State: {:?}", server_state.read().await);
+                    event!(Level::INFO, "Server State: {:?}", logged_state.read().await);
                 },
             }
         }
     });
 
-    Ok(app)
+    Ok((app, server_state))
 }
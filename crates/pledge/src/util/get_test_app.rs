@@ -3,12 +3,21 @@ use brski_prm_artifacts::pledge_info::PledgeInfo;
 use cli::config::PledgeConfig;
 use common::error::AppError;
 
-use crate::{parsed_config::ParsedConfig, server::get_app};
+use crate::{
+    parsed_config::ParsedConfig,
+    server::{get_app_with_state, ServerState},
+};
 
 pub async fn get_test_app() -> anyhow::Result<Router<()>, AppError> {
+    Ok(get_test_app_with_state(PledgeConfig::default()).await?.0)
+}
+
+pub async fn get_test_app_with_state(
+    pledge_config: PledgeConfig,
+) -> anyhow::Result<(Router<()>, ServerState), AppError> {
     let certs: example_certs::OpensslTestCerts = example_certs::generate_certs().into();
 
-    let pledge_config = PledgeConfig::default();
+    let protocol_version = pledge_config.protocol_version;
 
     let config = ParsedConfig {
         idevid_certificate: certs.pledge.0,
@@ -21,9 +30,9 @@ pub async fn get_test_app() -> anyhow::Result<Router<()>, AppError> {
             data_interchance_format: brski_prm_artifacts::token_type::DataInterchangeFormat::JSON,
             supported_token_type: brski_prm_artifacts::token_type::PlainTokenType::JOSE,
             supported_voucher_type: brski_prm_artifacts::token_type::VoucherTokenType::JWS,
+            protocol_version,
         },
     };
 
-    let app = get_app(&config).await?;
-    Ok(app)
+    get_app_with_state(&config).await
 }
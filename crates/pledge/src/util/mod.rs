@@ -1,4 +1,4 @@
 #[cfg(test)]
 mod get_test_app;
 #[cfg(test)]
-pub use get_test_app::get_test_app;
+pub use get_test_app::{get_test_app, get_test_app_with_state};
@@ -0,0 +1,534 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use common::server_error::ServerError;
+use serde::Serialize;
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing::{event, Level};
+
+use super::{
+    registry::JobStatus,
+    state::{PledgeBootstrap, Stage},
+};
+use crate::{pledge_communicator::DiscoveredPledge, server::server::ServerState};
+
+/// Waited after the first failure of a step, doubled with every further one
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How bootstrapping a pledge ended
+#[derive(Serialize, Debug, Clone)]
+pub struct BootstrapOutcome {
+    pub serial: String,
+    /// The last stage the pledge reached
+    pub stage: Stage,
+    /// Steps taken, including the failed ones
+    pub attempts: u32,
+    /// Why the pledge was given up, None if it got as far as it should
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Part of bootstrapping that runs apart from the others, for agents without registrar connectivity at the pledges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Talks to the pledges only, collects PVR and PER
+    Collect,
+    /// Talks to the registrar only, forwards PVR and PER and later the statuses
+    Forward,
+    /// Talks to the pledges only, delivers what the registrar returned
+    Deliver,
+}
+
+impl Phase {
+    /// Whether the step after `stage` belongs to the phase
+    pub fn takes(&self, stage: Stage) -> bool {
+        match self {
+            Phase::Collect => stage == Stage::Discovered,
+            Phase::Forward => matches!(stage, Stage::Collected | Stage::Delivered),
+            Phase::Deliver => stage == Stage::Obtained,
+        }
+    }
+}
+
+/// Bootstraps many pledges concurrently, at most `bootstrap_workers` at a time.
+/// Steps are limited to `bootstrap_step_timeout` and retried `bootstrap_retries` times,
+/// a pledge that fails for good is given up without affecting the others.
+/// Progress is reported to the [crate::JobRegistry] of the agent,
+/// with a [crate::JobStore] every step taken is persisted and stored jobs are resumed.
+#[derive(Clone)]
+pub struct BootstrapEngine {
+    agent: ServerState,
+}
+
+impl BootstrapEngine {
+    pub fn new(agent: ServerState) -> Self {
+        Self { agent }
+    }
+
+    /// Bootstraps the pledges all the way, resuming their stored jobs.
+    /// The jobs are claimed in the [crate::JobRegistry] first, pledges already being bootstrapped are left alone.
+    #[tracing::instrument(skip_all, target = "RegistrarAgent", name = "BootstrapEngine::run")]
+    pub async fn run(&self, pledges: Vec<DiscoveredPledge>) -> Vec<BootstrapOutcome> {
+        let mut serials = Vec::new();
+        for pledge in pledges {
+            serials.push(pledge.serial.clone());
+            let job = self.job(pledge).await;
+            self.agent.jobs.discovered(job).await;
+        }
+
+        let jobs = match self.agent.jobs.claim(&serials).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                event!(Level::ERROR, "Could not claim the pledges: {}", e);
+                return vec![];
+            }
+        };
+        let claimed: Vec<String> = jobs.iter().map(|job| job.pledge.serial.clone()).collect();
+
+        let mut outcomes = self.run_jobs(jobs, None).await;
+        for serial in serials {
+            if claimed.contains(&serial) {
+                continue;
+            }
+            let Some(view) = self.agent.jobs.get(&serial).await else {
+                continue;
+            };
+            event!(
+                Level::INFO,
+                "Leaving pledge {} alone, it is {:?}",
+                serial,
+                view.status
+            );
+            outcomes.push(BootstrapOutcome {
+                serial,
+                stage: view.stage,
+                attempts: 0,
+                error: (view.status == JobStatus::Running)
+                    .then(|| "Pledge is being bootstrapped already".to_string()),
+            });
+        }
+        outcomes.sort_by(|a, b| a.serial.cmp(&b.serial));
+        outcomes
+    }
+
+    /// Bootstraps the jobs all the way from where they stand
+    #[tracing::instrument(skip_all, target = "RegistrarAgent", name = "BootstrapEngine::resume")]
+    pub async fn resume(&self, jobs: Vec<PledgeBootstrap>) -> Vec<BootstrapOutcome> {
+        self.run_jobs(jobs, None).await
+    }
+
+    /// Takes the steps of `phase` for every job, jobs with nothing to do in the phase are left out
+    #[tracing::instrument(
+        skip(self, jobs),
+        target = "RegistrarAgent",
+        name = "BootstrapEngine::run_phase"
+    )]
+    pub async fn run_phase(
+        &self,
+        phase: Phase,
+        jobs: Vec<PledgeBootstrap>,
+    ) -> Vec<BootstrapOutcome> {
+        let (jobs, skipped): (Vec<_>, Vec<_>) = jobs
+            .into_iter()
+            .partition(|job| phase.takes(job.state.stage()));
+        for job in skipped {
+            event!(
+                Level::DEBUG,
+                "Nothing to do for pledge {} at {:?}",
+                job.pledge.serial,
+                job.state.stage()
+            );
+        }
+        self.run_jobs(jobs, Some(phase)).await
+    }
+
+    async fn run_jobs(
+        &self,
+        jobs: Vec<PledgeBootstrap>,
+        phase: Option<Phase>,
+    ) -> Vec<BootstrapOutcome> {
+        let workers = Arc::new(Semaphore::new(self.agent.config.config.bootstrap_workers));
+        let mut tasks = JoinSet::new();
+
+        for job in jobs {
+            let engine = self.clone();
+            let workers = Arc::clone(&workers);
+            tasks.spawn(async move {
+                let _worker = workers.acquire_owned().await;
+                let serial = job.pledge.serial.clone();
+                let jobs = engine.agent.jobs.clone();
+                if !jobs.running(&job).await {
+                    event!(
+                        Level::INFO,
+                        "Pledge {} was cancelled before it started",
+                        serial
+                    );
+                    return BootstrapOutcome {
+                        stage: job.state.stage(),
+                        attempts: 0,
+                        error: Some("Cancelled".to_string()),
+                        serial,
+                    };
+                }
+
+                // a cancelled or panicking pledge only takes itself down
+                let running = tokio::spawn(async move { engine.bootstrap(job, phase).await });
+                jobs.attach(&serial, running.abort_handle()).await;
+                let (outcome, cancelled) = match running.await {
+                    Ok(outcome) => (outcome, false),
+                    Err(e) => {
+                        let last = jobs.get(&serial).await;
+                        let outcome = BootstrapOutcome {
+                            stage: last.as_ref().map_or(Stage::Discovered, |last| last.stage),
+                            attempts: last.as_ref().map_or(0, |last| last.attempts),
+                            error: Some(match e.is_cancelled() {
+                                true => "Cancelled".to_string(),
+                                false => e.to_string(),
+                            }),
+                            serial,
+                        };
+                        (outcome, e.is_cancelled())
+                    }
+                };
+                jobs.finished(&outcome, cancelled).await;
+                outcome
+            });
+        }
+
+        let mut outcomes = Vec::new();
+        while let Some(outcome) = tasks.join_next().await {
+            match outcome {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) => event!(Level::ERROR, "Bootstrapping task failed: {}", e),
+            }
+        }
+        outcomes.sort_by(|a, b| a.serial.cmp(&b.serial));
+
+        event!(
+            Level::INFO,
+            "{} of {} pledges got through",
+            outcomes.iter().filter(|o| o.error.is_none()).count(),
+            outcomes.len()
+        );
+        outcomes
+    }
+
+    /// The stored job of the pledge, or a new one
+    async fn job(&self, pledge: DiscoveredPledge) -> PledgeBootstrap {
+        let Some(store) = &self.agent.store else {
+            return PledgeBootstrap::new(pledge);
+        };
+        let max_age = self.agent.config.config.agent_signed_data_max_age;
+        match store
+            .load_or_restart(pledge.clone(), max_age, Utc::now())
+            .await
+        {
+            Ok(job) => {
+                event!(
+                    Level::INFO,
+                    "Bootstrapping pledge {} from {:?}",
+                    pledge.serial,
+                    job.state.stage()
+                );
+                job
+            }
+            Err(e) => {
+                event!(
+                    Level::WARN,
+                    "Could not load job of pledge {}, starting over: {}",
+                    pledge.serial,
+                    e
+                );
+                PledgeBootstrap::new(pledge)
+            }
+        }
+    }
+
+    async fn bootstrap(
+        &self,
+        mut bootstrap: PledgeBootstrap,
+        phase: Option<Phase>,
+    ) -> BootstrapOutcome {
+        let retries = self.agent.config.config.bootstrap_retries;
+        let mut attempts = 0;
+        let mut failures = 0;
+
+        while !bootstrap.is_done() && phase.is_none_or(|phase| phase.takes(bootstrap.state.stage()))
+        {
+            let stage = bootstrap.state.stage();
+            attempts += 1;
+
+            let error = match tokio::time::timeout(
+                self.step_timeout(stage),
+                bootstrap.advance(&self.agent),
+            )
+            .await
+            {
+                Ok(Ok(_)) => match self.persist(&bootstrap).await {
+                    Ok(()) => {
+                        failures = 0;
+                        self.agent.jobs.progressed(&bootstrap, attempts, None).await;
+                        continue;
+                    }
+                    // retrying would repeat a step already taken, so the pledge is given up
+                    Err(e) => {
+                        failures = retries;
+                        format!("Could not persist the job: {}", e)
+                    }
+                },
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => format!("Step after {:?} timed out", stage),
+            };
+            // a step that failed half way keeps what it got, so the retry does not ask for it again
+            if let Err(e) = self.persist(&bootstrap).await {
+                event!(
+                    Level::WARN,
+                    "Could not persist the job of pledge {}: {}",
+                    bootstrap.pledge.serial,
+                    e
+                );
+            }
+
+            failures += 1;
+            if failures > retries {
+                event!(
+                    Level::ERROR,
+                    "Giving up pledge {} at {:?}: {}",
+                    bootstrap.pledge.serial,
+                    bootstrap.state.stage(),
+                    error
+                );
+                return BootstrapOutcome {
+                    serial: bootstrap.pledge.serial,
+                    stage: bootstrap.state.stage(),
+                    attempts,
+                    error: Some(error),
+                };
+            }
+
+            self.agent
+                .jobs
+                .progressed(&bootstrap, attempts, Some(error.clone()))
+                .await;
+            let backoff = RETRY_BACKOFF * 2u32.pow(failures - 1);
+            event!(
+                Level::WARN,
+                "Step after {:?} failed for pledge {}, retrying in {:?}: {}",
+                stage,
+                bootstrap.pledge.serial,
+                backoff,
+                error
+            );
+            tokio::time::sleep(backoff).await;
+        }
+
+        BootstrapOutcome {
+            serial: bootstrap.pledge.serial,
+            stage: bootstrap.state.stage(),
+            attempts,
+            error: None,
+        }
+    }
+
+    async fn persist(&self, bootstrap: &PledgeBootstrap) -> Result<(), ServerError> {
+        match &self.agent.store {
+            Some(store) => store.save(bootstrap).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Forwarding the PVR includes waiting for a manual approval at the registrar
+    fn step_timeout(&self, stage: Stage) -> Duration {
+        let config = &self.agent.config.config;
+        let timeout = Duration::from_secs(config.bootstrap_step_timeout);
+        match stage {
+            Stage::Collected => timeout + Duration::from_secs(config.voucher_approval_timeout),
+            _ => timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use brski_prm_artifacts::{
+        ietf_voucher::VoucherRequest,
+        pledge_info::PledgeInfo,
+        token_type::{DataInterchangeFormat, VoucherTokenType, JSON},
+    };
+    use cli::config::RegistrarAgentConfig;
+    use example_certs::{generate_certs, OpensslTestCerts};
+    use signeable_payload::{
+        header::HeaderSet,
+        signeable::{signing_context::BasicSigningContext, unsigned::Unsigned},
+    };
+
+    use super::*;
+    use crate::{
+        parsed_config::ParsedConfig,
+        pledge_communicator::{PledgeCommunicator, PledgeCtx},
+        server::server::get_state,
+    };
+
+    /// Hands out PVRs and PERs, except for the pledge "broken", and tracks how many pledges it serves at once
+    #[derive(Clone, Default)]
+    struct CountingCommunicator {
+        active: Arc<AtomicUsize>,
+        max_active: Arc<AtomicUsize>,
+        pvr: Vec<u8>,
+    }
+
+    #[async_trait::async_trait]
+    impl PledgeCommunicator for CountingCommunicator {
+        async fn send_pvr_trigger(&self, _: Vec<u8>, _: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+            Ok(self.pvr.clone())
+        }
+
+        async fn send_per_trigger(&self, _: Vec<u8>, _: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+            Ok(b"per".to_vec())
+        }
+
+        async fn send_voucher(&self, _: Vec<u8>, _: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn send_ca_certs(&self, _: Vec<u8>, _: PledgeCtx) -> Result<(), ServerError> {
+            unreachable!()
+        }
+
+        async fn send_enroll_response(
+            &self,
+            _: Vec<u8>,
+            _: PledgeCtx,
+        ) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn query_pledge_status(
+            &self,
+            _: Vec<u8>,
+            _: PledgeCtx,
+        ) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn get_data_interchange_format(
+            &self,
+            pledge: DiscoveredPledge,
+        ) -> Result<String, ServerError> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            match pledge.serial.as_str() {
+                "broken" => Err(ServerError::NotFound),
+                _ => Ok(JSON.to_string()),
+            }
+        }
+
+        async fn get_pledge_info(
+            &self,
+            _: DiscoveredPledge,
+            _: DataInterchangeFormat,
+        ) -> Result<Vec<u8>, ServerError> {
+            Ok(serde_json::to_vec(&PledgeInfo::simple_json())?)
+        }
+    }
+
+    /// An agent whose registrar is unreachable, so every pledge fails at the registrar
+    fn agent() -> (ServerState, CountingCommunicator) {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let config = ParsedConfig {
+            config: RegistrarAgentConfig {
+                registrar_url: "http://127.0.0.1:9".to_string(),
+                bootstrap_workers: 2,
+                bootstrap_retries: 1,
+                ..Default::default()
+            },
+            ee_certificate: certs.registrar_agent.0.clone(),
+            ee_key: certs.registrar_agent.1.private_key_to_pkcs8().unwrap(),
+            registrar_certificate: certs.registrar.0.clone(),
+        };
+        let (idevid, idevid_key) = &certs.pledge;
+        let mut header = HeaderSet::new();
+        header.set_x509_certificate_chain(&vec![idevid.to_der().unwrap()], true);
+        let pvr = Unsigned::new(VoucherRequest::default(), header)
+            .into_signeable_boxed(
+                VoucherTokenType::JWS
+                    .signature_type()
+                    .get_sv::<VoucherRequest>()
+                    .unwrap(),
+            )
+            .sign(
+                idevid_key.private_key_to_pkcs8().unwrap(),
+                BasicSigningContext::new(),
+            )
+            .unwrap();
+        let communicator = CountingCommunicator {
+            pvr: pvr.data(),
+            ..Default::default()
+        };
+        let agent = get_state(&config, Box::new(communicator.clone())).unwrap();
+        (agent, communicator)
+    }
+
+    fn pledge(serial: &str) -> DiscoveredPledge {
+        DiscoveredPledge {
+            serial: serial.to_string(),
+            url: format!("http://{}", serial),
+            pledge_info: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_bootstraps_pledges_in_isolation() {
+        let (agent, communicator) = agent();
+
+        let pledges = ["broken", "pledge-1", "pledge-2", "pledge-3"]
+            .into_iter()
+            .map(pledge)
+            .collect();
+        let outcomes = BootstrapEngine::new(agent).run(pledges).await;
+
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes[0].serial, "broken");
+        assert_eq!(outcomes[0].stage, Stage::Discovered);
+        assert_eq!(outcomes[0].attempts, 2);
+        for outcome in &outcomes[1..] {
+            assert_eq!(outcome.stage, Stage::Collected);
+            assert_eq!(outcome.attempts, 3);
+            assert!(outcome.error.is_some());
+        }
+        assert!(communicator.max_active.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn it_leaves_pledges_being_bootstrapped_alone() {
+        let (agent, communicator) = agent();
+        agent
+            .jobs
+            .discovered(PledgeBootstrap::new(pledge("pledge-1")))
+            .await;
+        // e.g. started through the admin API
+        assert_eq!(
+            agent
+                .jobs
+                .claim(&["pledge-1".to_string()])
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let outcomes = BootstrapEngine::new(agent)
+            .run(vec![pledge("pledge-1")])
+            .await;
+
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].attempts, 0);
+        assert!(outcomes[0].error.is_some());
+        assert_eq!(communicator.max_active.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use common::{server_error::ServerError, util::serial_file_name};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use super::state::PledgeBootstrap;
+use crate::{parsed_config::ParsedConfig, pledge_communicator::DiscoveredPledge};
+
+const JOB_EXTENSION: &str = "json";
+const BUNDLE_VERSION: u32 = 1;
+
+/// Carries jobs between agents running different phases, see `open-brski registrar-agent export`.
+/// The registrar only accepts collected PVRs from an agent with the certificate of the collecting one,
+/// so every agent handling the bundle runs with the same `ee_certificate` and `ee_key`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JobBundle {
+    pub version: u32,
+    pub created_on: DateTime<Utc>,
+    pub jobs: Vec<PledgeBootstrap>,
+}
+
+/// One JSON file per pledge, written whenever bootstrapping it takes a step
+#[derive(Clone, Debug)]
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    /// The configured job store, None if there is none
+    pub fn from_config(config: &ParsedConfig) -> Result<Option<Self>, ServerError> {
+        match &config.config.job_store {
+            Some(dir) => Ok(Some(Self::open(dir.relative())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn open(dir: PathBuf) -> Result<Self, ServerError> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub async fn save(&self, job: &PledgeBootstrap) -> Result<(), ServerError> {
+        let path = self.path(&job.pledge.serial)?;
+
+        // written next to the job and renamed, so a crash never leaves half a job behind
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, serde_json::to_vec_pretty(job)?).await?;
+        tokio::fs::rename(&temporary, &path).await?;
+        Ok(())
+    }
+
+    pub async fn load(&self, serial: &str) -> Result<Option<PledgeBootstrap>, ServerError> {
+        match tokio::fs::read(self.path(serial)?).await {
+            Ok(job) => Ok(Some(serde_json::from_slice(&job)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The stored job of the pledge, or a new one if there is none or its PVR is older than `max_age` seconds
+    /// and has not been forwarded yet, the registrar would refuse it then
+    pub async fn load_or_restart(
+        &self,
+        pledge: DiscoveredPledge,
+        max_age: u64,
+        now: DateTime<Utc>,
+    ) -> Result<PledgeBootstrap, ServerError> {
+        match self.load(&pledge.serial).await? {
+            Some(job) if job.is_stale(max_age, now) => {
+                event!(
+                    Level::INFO,
+                    "Collecting pledge {} again, its PVR is too old to be forwarded",
+                    pledge.serial
+                );
+                Ok(PledgeBootstrap::new(pledge))
+            }
+            Some(job) => Ok(job),
+            None => Ok(PledgeBootstrap::new(pledge)),
+        }
+    }
+
+    /// All jobs, ordered by serial number
+    pub async fn list(&self) -> Result<Vec<PledgeBootstrap>, ServerError> {
+        let mut jobs = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(JOB_EXTENSION) {
+                continue;
+            }
+            jobs.push(serde_json::from_slice(&tokio::fs::read(&path).await?)?);
+        }
+        jobs.sort_by(|a: &PledgeBootstrap, b| a.pledge.serial.cmp(&b.pledge.serial));
+        Ok(jobs)
+    }
+
+    /// Writes all jobs into a bundle at `path`, returns how many
+    pub async fn export(&self, path: &Path) -> Result<usize, ServerError> {
+        let bundle = JobBundle {
+            version: BUNDLE_VERSION,
+            created_on: Utc::now(),
+            jobs: self.list().await?,
+        };
+        tokio::fs::write(path, serde_json::to_vec_pretty(&bundle)?).await?;
+        Ok(bundle.jobs.len())
+    }
+
+    /// Takes the jobs of the bundle at `path` that are further along than the stored ones, returns how many
+    pub async fn import(&self, path: &Path) -> Result<usize, ServerError> {
+        let bundle: JobBundle = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+        if bundle.version != BUNDLE_VERSION {
+            return Err(ServerError::BadRequestWithReason(format!(
+                "Unsupported bundle version {}",
+                bundle.version
+            )));
+        }
+
+        let mut taken = 0;
+        for job in bundle.jobs {
+            let stored = self.load(&job.pledge.serial).await?;
+            if stored.is_some_and(|stored| stored.state.stage() >= job.state.stage()) {
+                event!(
+                    Level::INFO,
+                    "Keeping stored job of pledge {}, it is as far along",
+                    job.pledge.serial
+                );
+                continue;
+            }
+            self.save(&job).await?;
+            taken += 1;
+        }
+        Ok(taken)
+    }
+
+    fn path(&self, serial: &str) -> Result<PathBuf, ServerError> {
+        let file_name = serial_file_name(serial, JOB_EXTENSION).ok_or_else(|| {
+            ServerError::BadRequestWithReason(format!(
+                "Serial number {} can not be a file name",
+                serial
+            ))
+        })?;
+        Ok(self.dir.join(file_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootstrapping::state::{PledgeState, Stage};
+
+    fn pledge(serial: &str) -> DiscoveredPledge {
+        DiscoveredPledge {
+            serial: serial.to_string(),
+            url: "http://localhost:3002".to_string(),
+            pledge_info: None,
+        }
+    }
+
+    fn job(serial: &str, state: PledgeState) -> PledgeBootstrap {
+        let mut job = PledgeBootstrap::new(pledge(serial));
+        job.state = state;
+        job
+    }
+
+    fn collected(collected_on: DateTime<Utc>) -> PledgeState {
+        PledgeState::Collected {
+            pvr: b"pvr".to_vec(),
+            per: b"per".to_vec(),
+            collected_on,
+            agent_certificate: b"agent".to_vec(),
+            voucher: None,
+            ldevid: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_merges_bundles_into_the_store() {
+        let base = std::env::temp_dir().join(format!("agent-jobs-{}", std::process::id()));
+        let field = JobStore::open(base.join("field")).unwrap();
+        let office = JobStore::open(base.join("office")).unwrap();
+
+        field
+            .save(&job("pledge-1", collected(Utc::now())))
+            .await
+            .unwrap();
+        field
+            .save(&job("pledge-2", PledgeState::Discovered))
+            .await
+            .unwrap();
+        office
+            .save(&job("pledge-2", PledgeState::StatusesForwarded))
+            .await
+            .unwrap();
+
+        let bundle = base.join("bundle.json");
+        assert_eq!(field.export(&bundle).await.unwrap(), 2);
+        assert_eq!(office.import(&bundle).await.unwrap(), 1);
+
+        let jobs = office.list().await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        let PledgeState::Collected { pvr, .. } = &jobs[0].state else {
+            panic!("expected the collected job");
+        };
+        assert_eq!(pvr, b"pvr");
+        assert!(jobs[1].is_done());
+        assert!(office.load("../pledge-1").await.is_err());
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_restarts_stale_jobs() {
+        let dir = std::env::temp_dir().join(format!("agent-stale-jobs-{}", std::process::id()));
+        let store = JobStore::open(dir.clone()).unwrap();
+        let now = Utc::now();
+
+        store
+            .save(&job(
+                "pledge-1",
+                collected(now - chrono::Duration::hours(2)),
+            ))
+            .await
+            .unwrap();
+        store.save(&job("pledge-2", collected(now))).await.unwrap();
+
+        let restarted = store
+            .load_or_restart(pledge("pledge-1"), 3600, now)
+            .await
+            .unwrap();
+        assert_eq!(restarted.state.stage(), Stage::Discovered);
+        let kept = store
+            .load_or_restart(pledge("pledge-2"), 3600, now)
+            .await
+            .unwrap();
+        assert_eq!(kept.state.stage(), Stage::Collected);
+        let new = store
+            .load_or_restart(pledge("pledge-3"), 3600, now)
+            .await
+            .unwrap();
+        assert_eq!(new.state.stage(), Stage::Discovered);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,9 @@
+mod engine;
+mod job_store;
+mod registry;
+mod state;
+
+pub use engine::{BootstrapEngine, BootstrapOutcome, Phase};
+pub use job_store::{JobBundle, JobStore};
+pub use registry::{JobRegistry, JobStatus, JobView};
+pub use state::{bootstrap_pledge, PledgeBootstrap, PledgeState, Stage};
@@ -0,0 +1,346 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use brski_prm_artifacts::status::pledge::status::PledgeStatus;
+use common::server_error::ServerError;
+use serde::Serialize;
+use tokio::{
+    sync::{broadcast, RwLock},
+    task::AbortHandle,
+};
+
+use super::{
+    engine::BootstrapOutcome,
+    state::{PledgeBootstrap, Stage},
+};
+use crate::pledge_communicator::DiscoveredPledge;
+
+/// Progress events kept for subscribers that fall behind
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    /// Discovered, bootstrapping was not started yet
+    Idle,
+    Running,
+    /// Got as far as it should, all the way or to the end of a phase
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// What the agent knows about a pledge, also sent to subscribers whenever it changes
+#[derive(Serialize, Debug, Clone)]
+pub struct JobView {
+    pub serial: String,
+    pub url: String,
+    pub stage: Stage,
+    pub status: JobStatus,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What the pledge answered to the last status query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pledge_status: Option<PledgeStatus>,
+}
+
+struct Entry {
+    job: PledgeBootstrap,
+    status: JobStatus,
+    attempts: u32,
+    error: Option<String>,
+    pledge_status: Option<PledgeStatus>,
+    running: Option<AbortHandle>,
+    /// Cancelled while claimed but still waiting for a worker
+    cancelled: bool,
+}
+
+impl Entry {
+    fn new(job: PledgeBootstrap) -> Self {
+        Self {
+            job,
+            status: JobStatus::Idle,
+            attempts: 0,
+            error: None,
+            pledge_status: None,
+            running: None,
+            cancelled: false,
+        }
+    }
+
+    fn view(&self) -> JobView {
+        JobView {
+            serial: self.job.pledge.serial.clone(),
+            url: self.job.pledge.url.clone(),
+            stage: self.job.state.stage(),
+            status: self.status,
+            attempts: self.attempts,
+            error: self.error.clone(),
+            pledge_status: self.pledge_status.clone(),
+        }
+    }
+}
+
+/// The jobs of the running agent, kept up to date by the [crate::BootstrapEngine]
+#[derive(Clone)]
+pub struct JobRegistry {
+    entries: Arc<RwLock<BTreeMap<String, Entry>>>,
+    events: broadcast::Sender<JobView>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+}
+
+impl JobRegistry {
+    pub async fn list(&self) -> Vec<JobView> {
+        self.entries
+            .read()
+            .await
+            .values()
+            .map(Entry::view)
+            .collect()
+    }
+
+    pub async fn get(&self, serial: &str) -> Option<JobView> {
+        self.entries.read().await.get(serial).map(Entry::view)
+    }
+
+    pub async fn job(&self, serial: &str) -> Option<PledgeBootstrap> {
+        self.entries
+            .read()
+            .await
+            .get(serial)
+            .map(|entry| entry.job.clone())
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobView> {
+        self.events.subscribe()
+    }
+
+    /// Adds a discovered pledge, a job already known is kept
+    pub async fn discovered(&self, job: PledgeBootstrap) -> JobView {
+        let mut entries = self.entries.write().await;
+        let entry = entries
+            .entry(job.pledge.serial.clone())
+            .or_insert_with(|| Entry::new(job));
+        entry.view()
+    }
+
+    /// Marks the jobs of `serials` as running and returns them, except the ones running or done already
+    pub async fn claim(&self, serials: &[String]) -> Result<Vec<PledgeBootstrap>, ServerError> {
+        let mut entries = self.entries.write().await;
+        if let Some(unknown) = serials.iter().find(|serial| !entries.contains_key(*serial)) {
+            return Err(ServerError::BadRequestWithReason(format!(
+                "Pledge {} was not discovered",
+                unknown
+            )));
+        }
+
+        let mut claimed = Vec::new();
+        for serial in serials {
+            let entry = entries.get_mut(serial).expect("checked above");
+            if entry.status == JobStatus::Running || entry.job.is_done() {
+                continue;
+            }
+            entry.status = JobStatus::Running;
+            entry.error = None;
+            entry.cancelled = false;
+            claimed.push(entry.job.clone());
+            let _ = self.events.send(entry.view());
+        }
+        Ok(claimed)
+    }
+
+    /// Aborts the running job of the pledge, its last step taken is kept.
+    /// A claimed job still waiting for a worker is not started at all.
+    pub async fn cancel(&self, serial: &str) -> Result<JobView, ServerError> {
+        let mut entries = self.entries.write().await;
+        let entry = entries.get_mut(serial).ok_or(ServerError::NotFound)?;
+        match &entry.running {
+            Some(running) => {
+                running.abort();
+                Ok(entry.view())
+            }
+            None if entry.status == JobStatus::Running => {
+                entry.cancelled = true;
+                entry.status = JobStatus::Cancelled;
+                entry.error = Some("Cancelled".to_string());
+                let _ = self.events.send(entry.view());
+                Ok(entry.view())
+            }
+            None => Err(ServerError::BadRequestWithReason(format!(
+                "Pledge {} is not being bootstrapped",
+                serial
+            ))),
+        }
+    }
+
+    pub(crate) async fn status_queried(&self, serial: &str, status: PledgeStatus) {
+        self.update(serial.to_string(), |entry| {
+            entry.pledge_status = Some(status);
+        })
+        .await;
+    }
+
+    /// The job got a worker, false if it was cancelled while waiting for one
+    pub(crate) async fn running(&self, job: &PledgeBootstrap) -> bool {
+        let mut started = true;
+        self.update(job.pledge.serial.clone(), |entry| {
+            if entry.cancelled {
+                entry.cancelled = false;
+                started = false;
+                return;
+            }
+            entry.job = job.clone();
+            entry.status = JobStatus::Running;
+            entry.error = None;
+        })
+        .await;
+        started
+    }
+
+    /// Lets the running job of the pledge be cancelled through `running`
+    pub(crate) async fn attach(&self, serial: &str, running: AbortHandle) {
+        if let Some(entry) = self.entries.write().await.get_mut(serial) {
+            entry.running = Some(running);
+        }
+    }
+
+    /// A step was taken, or failed with `error` and will be retried
+    pub(crate) async fn progressed(
+        &self,
+        job: &PledgeBootstrap,
+        attempts: u32,
+        error: Option<String>,
+    ) {
+        self.update(job.pledge.serial.clone(), |entry| {
+            entry.job = job.clone();
+            entry.attempts = attempts;
+            entry.error = error;
+        })
+        .await;
+    }
+
+    pub(crate) async fn finished(&self, outcome: &BootstrapOutcome, cancelled: bool) {
+        self.update(outcome.serial.clone(), |entry| {
+            entry.status = match (cancelled, &outcome.error) {
+                (true, _) => JobStatus::Cancelled,
+                (false, None) => JobStatus::Succeeded,
+                (false, Some(_)) => JobStatus::Failed,
+            };
+            entry.attempts = outcome.attempts;
+            entry.error = outcome.error.clone();
+            entry.running = None;
+        })
+        .await;
+    }
+
+    async fn update(&self, serial: String, update: impl FnOnce(&mut Entry)) {
+        let mut entries = self.entries.write().await;
+        // the engine may run jobs that were never discovered through the API
+        let entry = entries.entry(serial.clone()).or_insert_with(|| {
+            Entry::new(PledgeBootstrap::new(DiscoveredPledge {
+                serial,
+                ..Default::default()
+            }))
+        });
+        update(entry);
+        // nobody listening is fine
+        let _ = self.events.send(entry.view());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_tracks_and_cancels_jobs() {
+        let registry = JobRegistry::default();
+        let mut events = registry.subscribe();
+        let job = PledgeBootstrap::new(DiscoveredPledge {
+            serial: "00-D0-E5-F2-00-02".to_string(),
+            url: "http://localhost:3002".to_string(),
+            pledge_info: None,
+        });
+
+        registry.discovered(job.clone()).await;
+        assert!(registry.claim(&["unknown".to_string()]).await.is_err());
+        assert_eq!(
+            registry
+                .claim(&["00-D0-E5-F2-00-02".to_string()])
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        // running jobs are not claimed twice
+        assert!(registry
+            .claim(&["00-D0-E5-F2-00-02".to_string()])
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(events.recv().await.unwrap().status, JobStatus::Running);
+
+        let running = tokio::spawn(tokio::time::sleep(Duration::from_secs(60)));
+        registry.running(&job).await;
+        registry
+            .attach("00-D0-E5-F2-00-02", running.abort_handle())
+            .await;
+        registry.cancel("00-D0-E5-F2-00-02").await.unwrap();
+        assert!(running.await.unwrap_err().is_cancelled());
+
+        registry
+            .finished(
+                &BootstrapOutcome {
+                    serial: "00-D0-E5-F2-00-02".to_string(),
+                    stage: Stage::Discovered,
+                    attempts: 1,
+                    error: Some("Cancelled".to_string()),
+                },
+                true,
+            )
+            .await;
+        let view = registry.get("00-D0-E5-F2-00-02").await.unwrap();
+        assert_eq!(view.status, JobStatus::Cancelled);
+        assert!(registry.cancel("00-D0-E5-F2-00-02").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_cancels_jobs_waiting_for_a_worker() {
+        let registry = JobRegistry::default();
+        let job = PledgeBootstrap::new(DiscoveredPledge {
+            serial: "00-D0-E5-F2-00-02".to_string(),
+            url: "http://localhost:3002".to_string(),
+            pledge_info: None,
+        });
+        registry.discovered(job.clone()).await;
+        registry
+            .claim(&["00-D0-E5-F2-00-02".to_string()])
+            .await
+            .unwrap();
+
+        let view = registry.cancel("00-D0-E5-F2-00-02").await.unwrap();
+        assert_eq!(view.status, JobStatus::Cancelled);
+        assert!(!registry.running(&job).await);
+        assert_eq!(
+            registry.get("00-D0-E5-F2-00-02").await.unwrap().status,
+            JobStatus::Cancelled
+        );
+
+        // claimed again, it runs
+        registry
+            .claim(&["00-D0-E5-F2-00-02".to_string()])
+            .await
+            .unwrap();
+        assert!(registry.running(&job).await);
+    }
+}
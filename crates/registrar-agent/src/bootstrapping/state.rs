@@ -0,0 +1,575 @@
+use anyhow::anyhow;
+use brski_prm_artifacts::{ietf_voucher::pki::X509, rer::response::RegistrarEnrollRequestResponse};
+use chrono::{DateTime, Duration, Utc};
+use common::server_error::ServerError;
+use serde::{Deserialize, Serialize};
+use signeable_payload::signeable::raw_signed::RawSigned;
+use tracing::{event, Level};
+
+use crate::{
+    client,
+    pledge_communicator::{DiscoveredPledge, PledgeCtx},
+    server::server::ServerState,
+};
+
+/// The steps of bootstrapping a pledge, in the order they are reached
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    Discovered,
+    Collected,
+    Obtained,
+    Delivered,
+    StatusesForwarded,
+}
+
+/// Where bootstrapping a pledge stands, every state holds what the next step needs.
+/// Artifacts are kept as received, base64 encoded when serialized.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "stage", rename_all = "kebab-case")]
+pub enum PledgeState {
+    /// Nothing was requested from the pledge yet
+    Discovered,
+    /// The pledge handed out its PVR and PER. What the registrar already returned for either is kept,
+    /// so a failed forward only repeats the request that failed.
+    Collected {
+        #[serde(with = "base64_bytes")]
+        pvr: Vec<u8>,
+        #[serde(with = "base64_bytes")]
+        per: Vec<u8>,
+        /// When the agent-signed-data of the PVR was created, the registrar refuses it once it is too old
+        collected_on: DateTime<Utc>,
+        /// DER encoded certificate of the agent that signed the agent-signed-data, the registrar only accepts
+        /// the PVR from an agent with this certificate
+        #[serde(with = "base64_bytes")]
+        agent_certificate: Vec<u8>,
+        /// The voucher the registrar returned for the PVR
+        #[serde(
+            default,
+            with = "optional_base64_bytes",
+            skip_serializing_if = "Option::is_none"
+        )]
+        voucher: Option<Vec<u8>>,
+        /// DER encoded LDevID certificate
+        #[serde(
+            default,
+            with = "optional_base64_bytes",
+            skip_serializing_if = "Option::is_none"
+        )]
+        ldevid: Option<Vec<u8>>,
+    },
+    /// The registrar returned voucher, DER encoded LDevID certificate and CA certificates
+    Obtained {
+        #[serde(with = "base64_bytes")]
+        voucher: Vec<u8>,
+        #[serde(with = "base64_bytes")]
+        ldevid: Vec<u8>,
+        #[serde(with = "base64_bytes")]
+        cacerts: Vec<u8>,
+    },
+    /// The pledge took everything and answered with its voucher and enroll status
+    Delivered {
+        #[serde(with = "base64_bytes")]
+        voucher_status: Vec<u8>,
+        #[serde(with = "base64_bytes")]
+        enroll_status: Vec<u8>,
+    },
+    /// The registrar has both statuses, the pledge is bootstrapped
+    StatusesForwarded,
+}
+
+impl PledgeState {
+    pub fn stage(&self) -> Stage {
+        match self {
+            PledgeState::Discovered => Stage::Discovered,
+            PledgeState::Collected { .. } => Stage::Collected,
+            PledgeState::Obtained { .. } => Stage::Obtained,
+            PledgeState::Delivered { .. } => Stage::Delivered,
+            PledgeState::StatusesForwarded => Stage::StatusesForwarded,
+        }
+    }
+}
+
+/// The state machine of a single pledge, also the job kept for it in the [crate::JobStore].
+/// A failed step leaves the state as it was, apart from the parts of forwarding PVR and PER that succeeded,
+/// so the step can simply be taken again.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PledgeBootstrap {
+    pub pledge: DiscoveredPledge,
+    pub state: PledgeState,
+    /// DER encoded IDevID the pledge signed its PVR with, its statuses have to be signed with it too
+    #[serde(
+        default,
+        with = "optional_base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub idevid: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ctx: Option<PledgeCtx>,
+}
+
+impl PledgeBootstrap {
+    pub fn new(pledge: DiscoveredPledge) -> Self {
+        Self {
+            pledge,
+            state: PledgeState::Discovered,
+            idevid: None,
+            ctx: None,
+        }
+    }
+
+    /// For pledges whose PledgeInfo is already known, it is not requested again
+    pub fn with_ctx(ctx: PledgeCtx) -> Self {
+        Self {
+            pledge: DiscoveredPledge {
+                serial: ctx.pledge_serial.clone(),
+                url: ctx.pledge_url.clone(),
+                pledge_info: Some(ctx.pledge_info.clone()),
+            },
+            state: PledgeState::Discovered,
+            idevid: None,
+            ctx: Some(ctx),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state.stage() == Stage::StatusesForwarded
+    }
+
+    /// Whether the PVR still has to be forwarded but was collected more than `max_age` seconds ago,
+    /// the pledge then has to be collected again
+    pub fn is_stale(&self, max_age: u64, now: DateTime<Utc>) -> bool {
+        match &self.state {
+            PledgeState::Collected {
+                collected_on,
+                voucher: None,
+                ..
+            } => expired(collected_on, max_age, now),
+            _ => false,
+        }
+    }
+
+    /// Takes the next step and returns the stage reached
+    #[tracing::instrument(
+        skip(self, agent),
+        fields(serial = %self.pledge.serial),
+        target = "RegistrarAgent",
+        name = "advance"
+    )]
+    pub async fn advance(&mut self, agent: &ServerState) -> Result<Stage, ServerError> {
+        let next = match &mut self.state {
+            PledgeState::Discovered => {
+                let ctx = self.pledge_ctx(agent).await?;
+
+                let collected_on = Utc::now();
+                let pvr = client::trigger_pvr(agent, &ctx).await?;
+                let idevid = client::pvr_idevid(&pvr, &ctx)?;
+                let per = client::trigger_per(agent, &ctx).await?;
+                self.idevid = Some(idevid);
+
+                PledgeState::Collected {
+                    pvr: pvr.data(),
+                    per: per.data(),
+                    collected_on,
+                    agent_certificate: agent.config.ee_certificate.to_der()?,
+                    voucher: None,
+                    ldevid: None,
+                }
+            }
+            PledgeState::Collected {
+                pvr,
+                per,
+                collected_on,
+                agent_certificate,
+                voucher,
+                ldevid,
+            } => {
+                let ctx = ctx(&self.ctx, &self.pledge)?;
+
+                // every PVR forwarded makes the MASA issue and log another voucher
+                let voucher = match voucher {
+                    Some(voucher) => voucher.clone(),
+                    None => {
+                        let max_age = agent.config.config.agent_signed_data_max_age;
+                        if expired(collected_on, max_age, Utc::now()) {
+                            return Err(ServerError::Forbidden(format!(
+                                "PVR was collected on {}, collect the pledge again",
+                                collected_on
+                            )));
+                        }
+                        if *agent_certificate != agent.config.ee_certificate.to_der()? {
+                            // the registrar checks the agent-signed-data against the forwarding agent
+                            return Err(ServerError::Forbidden(
+                                "PVR was collected by another registrar-agent identity".to_string(),
+                            ));
+                        }
+
+                        let issued = client::send_pvr_to_registrar(
+                            &agent.config,
+                            RawSigned::new(pvr.clone()),
+                            &agent.client,
+                            ctx,
+                        )
+                        .await?
+                        .data();
+                        *voucher = Some(issued.clone());
+                        issued
+                    }
+                };
+                let ldevid = match ldevid {
+                    Some(ldevid) => ldevid.clone(),
+                    None => {
+                        let enrolled = client::send_per_to_registrar(
+                            &agent.config,
+                            RawSigned::new(per.clone()),
+                            &agent.client,
+                            ctx,
+                        )
+                        .await?
+                        .0
+                        .as_ref()
+                        .to_vec();
+                        *ldevid = Some(enrolled.clone());
+                        enrolled
+                    }
+                };
+                let cacerts =
+                    client::get_wrappedcacerts_from_registrar(&agent.config, &agent.client, ctx)
+                        .await?;
+
+                PledgeState::Obtained {
+                    voucher,
+                    ldevid,
+                    cacerts: cacerts.data(),
+                }
+            }
+            PledgeState::Obtained {
+                voucher,
+                ldevid,
+                cacerts,
+            } => {
+                let ctx = ctx(&self.ctx, &self.pledge)?;
+                let ldevid = RegistrarEnrollRequestResponse(
+                    X509::try_from(ldevid.clone()).map_err(|e| anyhow!(e))?,
+                );
+
+                let voucher_status =
+                    client::send_voucher_to_pledge(agent, RawSigned::new(voucher.clone()), ctx)
+                        .await?;
+                client::send_cacerts_to_pledge(agent, RawSigned::new(cacerts.clone()), ctx).await?;
+                let enroll_status =
+                    client::send_enroll_response_to_pledge(agent, ldevid, ctx).await?;
+
+                PledgeState::Delivered {
+                    voucher_status: voucher_status.data(),
+                    enroll_status: enroll_status.data(),
+                }
+            }
+            PledgeState::Delivered {
+                voucher_status,
+                enroll_status,
+            } => {
+                let ctx = ctx(&self.ctx, &self.pledge)?;
+
+                client::send_voucher_status_to_registrar(
+                    &agent.config,
+                    RawSigned::new(voucher_status.clone()),
+                    &agent.client,
+                    ctx,
+                )
+                .await?;
+                client::send_enroll_status_to_registrar(
+                    &agent.config,
+                    RawSigned::new(enroll_status.clone()),
+                    &agent.client,
+                    ctx,
+                )
+                .await?;
+
+                PledgeState::StatusesForwarded
+            }
+            PledgeState::StatusesForwarded => return Ok(Stage::StatusesForwarded),
+        };
+
+        event!(
+            Level::INFO,
+            "Pledge {} went from {:?} to {:?}",
+            self.pledge.serial,
+            self.state.stage(),
+            next.stage()
+        );
+        self.state = next;
+        Ok(self.state.stage())
+    }
+
+    /// The context of the pledge, requested from the pledge if it was not yet
+    pub(crate) async fn pledge_ctx(
+        &mut self,
+        agent: &ServerState,
+    ) -> Result<PledgeCtx, ServerError> {
+        if let Some(ctx) = &self.ctx {
+            return Ok(ctx.clone());
+        }
+        let ctx = client::request_pledge_ctx(agent, &self.pledge).await?;
+        self.ctx = Some(ctx.clone());
+        Ok(ctx)
+    }
+}
+
+fn expired(collected_on: &DateTime<Utc>, max_age: u64, now: DateTime<Utc>) -> bool {
+    now - *collected_on > Duration::seconds(max_age as i64)
+}
+
+/// The context of a pledge past discovery, taken apart from [PledgeBootstrap] so the state can be updated while it is used
+fn ctx<'a>(
+    ctx: &'a Option<PledgeCtx>,
+    pledge: &DiscoveredPledge,
+) -> Result<&'a PledgeCtx, ServerError> {
+    ctx.as_ref().ok_or_else(|| {
+        ServerError::InternalError(anyhow!(
+            "No PledgeInfo for pledge {} after discovery",
+            pledge.serial
+        ))
+    })
+}
+
+/// Bootstraps a pledge in a single pass without retries, see [crate::BootstrapEngine] for many pledges.
+/// Returns the finished job, whose IDevID later status queries are checked against.
+#[tracing::instrument(skip(state), target = "RegistrarAgent", name = "bootstrap_pledge")]
+pub async fn bootstrap_pledge(
+    state: &ServerState,
+    pledge: &PledgeCtx,
+) -> Result<PledgeBootstrap, ServerError> {
+    let mut bootstrap = PledgeBootstrap::with_ctx(pledge.clone());
+    while !bootstrap.is_done() {
+        bootstrap.advance(state).await?;
+    }
+    Ok(bootstrap)
+}
+
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+mod optional_base64_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::base64_bytes::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Bytes(#[serde(with = "super::base64_bytes")] Vec<u8>);
+
+        Ok(Option::<Bytes>::deserialize(deserializer)?.map(|Bytes(bytes)| bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{
+        http::{header::ACCEPT, header::CONTENT_TYPE, HeaderMap, StatusCode},
+        routing::post,
+        Router,
+    };
+    use cli::config::RegistrarAgentConfig;
+    use example_certs::{generate_certs, OpensslTestCerts};
+    use openssl::{
+        pkey::{PKey, Private},
+        x509::X509 as OpensslX509,
+    };
+
+    use super::*;
+    use crate::{
+        bootstrapping::JobStore, parsed_config::ParsedConfig,
+        pledge_communicator::http_communicator::HTTPCommunicator, server::server::get_state,
+    };
+
+    /// Voucher and enroll requests a registrar received
+    #[derive(Clone, Default)]
+    struct Requests {
+        vouchers: Arc<AtomicUsize>,
+        enrollments: Arc<AtomicUsize>,
+    }
+
+    /// A registrar issuing a voucher for every PVR and refusing every PER, returns its URL
+    async fn registrar(requests: &Requests) -> String {
+        let Requests {
+            vouchers,
+            enrollments,
+        } = requests.clone();
+        let registrar = Router::new()
+            .route(
+                "/.well-known/brski/requestvoucher",
+                post(move |headers: HeaderMap| async move {
+                    vouchers.fetch_add(1, Ordering::SeqCst);
+                    let content_type = headers.get(ACCEPT).unwrap().clone();
+                    ([(CONTENT_TYPE, content_type)], b"voucher".to_vec())
+                }),
+            )
+            .route(
+                "/.well-known/brski/requestenroll",
+                post(move || async move {
+                    enrollments.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let registrar_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async { axum::serve(listener, registrar).await.unwrap() });
+        registrar_url
+    }
+
+    fn agent(
+        registrar_url: &str,
+        (certificate, key): &(OpensslX509, PKey<Private>),
+        registrar: &OpensslX509,
+    ) -> ServerState {
+        let config = ParsedConfig {
+            config: RegistrarAgentConfig {
+                registrar_url: registrar_url.to_string(),
+                ..Default::default()
+            },
+            ee_certificate: certificate.clone(),
+            ee_key: key.private_key_to_pkcs8().unwrap(),
+            registrar_certificate: registrar.clone(),
+        };
+        get_state(
+            &config,
+            Box::new(HTTPCommunicator::new(reqwest::Client::new())),
+        )
+        .unwrap()
+    }
+
+    fn collected(
+        serial: &str,
+        agent: &OpensslX509,
+        collected_on: DateTime<Utc>,
+    ) -> PledgeBootstrap {
+        let mut bootstrap = PledgeBootstrap::with_ctx(PledgeCtx {
+            pledge_serial: serial.to_string(),
+            ..Default::default()
+        });
+        bootstrap.state = PledgeState::Collected {
+            pvr: b"pvr".to_vec(),
+            per: b"per".to_vec(),
+            collected_on,
+            agent_certificate: agent.to_der().unwrap(),
+            voucher: None,
+            ldevid: None,
+        };
+        bootstrap
+    }
+
+    #[tokio::test]
+    async fn it_forwards_the_pvr_once_when_the_per_fails() {
+        let requests = Requests::default();
+        let certs: OpensslTestCerts = generate_certs().into();
+        let agent = agent(
+            &registrar(&requests).await,
+            &certs.registrar_agent,
+            &certs.registrar.0,
+        );
+
+        let mut bootstrap = collected("pledge-1", &certs.registrar_agent.0, Utc::now());
+        for _ in 0..2 {
+            assert!(bootstrap.advance(&agent).await.is_err());
+        }
+        let PledgeState::Collected {
+            voucher, ldevid, ..
+        } = &bootstrap.state
+        else {
+            panic!("expected the pledge to stay collected");
+        };
+        assert_eq!(voucher.as_deref(), Some(&b"voucher"[..]));
+        assert_eq!(*ldevid, None);
+        assert_eq!(requests.vouchers.load(Ordering::SeqCst), 1);
+        assert_eq!(requests.enrollments.load(Ordering::SeqCst), 2);
+
+        let stored: PledgeBootstrap =
+            serde_json::from_slice(&serde_json::to_vec(&bootstrap).unwrap()).unwrap();
+        assert!(matches!(
+            stored.state,
+            PledgeState::Collected {
+                voucher: Some(_),
+                ldevid: None,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_forwards_bundles_collected_on_another_machine() {
+        let requests = Requests::default();
+        let registrar_url = registrar(&requests).await;
+        let certs: OpensslTestCerts = generate_certs().into();
+        let base = std::env::temp_dir().join(format!("agent-bundles-{}", std::process::id()));
+        let field = JobStore::open(base.join("field")).unwrap();
+        let office = JobStore::open(base.join("office")).unwrap();
+
+        let field_agent = &certs.registrar_agent.0;
+        let now = Utc::now();
+        for job in [
+            collected("same-agent", field_agent, now - Duration::hours(2)),
+            collected("other-agent", &certs.registrar.0, now),
+            collected("stale", field_agent, now - Duration::days(2)),
+        ] {
+            field.save(&job).await.unwrap();
+        }
+        let bundle = base.join("bundle.json");
+        field.export(&bundle).await.unwrap();
+        assert_eq!(office.import(&bundle).await.unwrap(), 3);
+
+        // the office runs with the identity of the field agent
+        let office_agent = agent(&registrar_url, &certs.registrar_agent, &certs.registrar.0);
+        let max_age = office_agent.config.config.agent_signed_data_max_age;
+
+        let mut job = office.load("same-agent").await.unwrap().unwrap();
+        assert!(!job.is_stale(max_age, now));
+        // forwarded, only the enrollment is refused by the mock registrar
+        assert!(job.advance(&office_agent).await.is_err());
+        assert_eq!(requests.vouchers.load(Ordering::SeqCst), 1);
+
+        for serial in ["other-agent", "stale"] {
+            let mut job = office.load(serial).await.unwrap().unwrap();
+            assert!(matches!(
+                job.advance(&office_agent).await,
+                Err(ServerError::Forbidden(_))
+            ));
+        }
+        assert!(office
+            .load("stale")
+            .await
+            .unwrap()
+            .unwrap()
+            .is_stale(max_age, now));
+        assert_eq!(requests.vouchers.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
@@ -1,20 +1,56 @@
-use std::default;
+use std::time::Duration;
 
-use common::{error::AppError, server_error::ServerError};
+use common::{dns_sd::browse_pledges, server_error::ServerError};
+use tracing::{event, Level};
 
-use crate::{
-    parsed_config::ParsedConfig,
-    pledge_communicator::{DiscoveredPledge, PledgeCtx},
-};
+use crate::{parsed_config::ParsedConfig, pledge_communicator::DiscoveredPledge};
 
-/// todo add discovery logic
-///
-///
-pub async fn discover_pledges(
-    _config: &ParsedConfig,
-) -> Result<Vec<DiscoveredPledge>, ServerError> {
-    Ok(vec![DiscoveredPledge {
-        serial: "00-D0-E5-F2-00-02".to_string(),
-        url: "http://0.0.0.0:3002".to_string(),
-    }])
+/// Browses for pledges over mDNS/DNS-SD if autodiscover is set, otherwise uses the configured ones.
+/// If `bootstrap_serials` is not empty, only the pledges listed there are returned.
+#[tracing::instrument(skip(config), target = "RegistrarAgent", name = "discover_pledges")]
+pub async fn discover_pledges(config: &ParsedConfig) -> Result<Vec<DiscoveredPledge>, ServerError> {
+    let config = &config.config;
+
+    let pledges: Vec<DiscoveredPledge> = if config.autodiscover {
+        event!(
+            Level::INFO,
+            "Browsing for pledges for {} seconds",
+            config.discovery_timeout
+        );
+        browse_pledges(Duration::from_secs(config.discovery_timeout))
+            .await?
+            .into_iter()
+            .map(|pledge| DiscoveredPledge {
+                serial: pledge.serial,
+                url: format!("http://{}", pledge.address),
+                pledge_info: pledge.pledge_info,
+            })
+            .collect()
+    } else {
+        config
+            .pledges
+            .iter()
+            .map(|(serial, url)| DiscoveredPledge {
+                serial: serial.clone(),
+                url: url.clone(),
+                pledge_info: None,
+            })
+            .collect()
+    };
+
+    Ok(pledges
+        .into_iter()
+        .filter(|pledge| {
+            let wanted = config.bootstrap_serials.is_empty()
+                || config.bootstrap_serials.contains(&pledge.serial);
+            if !wanted {
+                event!(
+                    Level::INFO,
+                    "Skipping pledge {}, it is not in bootstrap_serials",
+                    pledge.serial
+                );
+            }
+            wanted
+        })
+        .collect())
 }
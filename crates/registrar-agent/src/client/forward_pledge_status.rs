@@ -0,0 +1,53 @@
+use brski_prm_artifacts::status::pledge::status::PledgeStatus;
+use common::server_error::ServerError;
+use signeable_payload::signeable::raw_signed::RawSigned;
+use tracing::event;
+
+use crate::{parsed_config::ParsedConfig, PledgeCtx};
+
+use reqwest::{header::CONTENT_TYPE, Client};
+
+#[tracing::instrument(
+    skip(client, parsed_config),
+    target = "RegistrarAgent",
+    name = "send_pledge_status_to_registrar"
+)]
+pub async fn send_pledge_status_to_registrar(
+    parsed_config: &ParsedConfig,
+    pledge_status: RawSigned<PledgeStatus>,
+    client: &Client,
+    ctx: &PledgeCtx,
+) -> Result<(), ServerError> {
+    let data = pledge_status.data();
+
+    let pledge_status_registrar_url = format!(
+        "{}/.well-known/brski/pledge_status",
+        parsed_config.config.registrar_url
+    );
+
+    event!(
+        tracing::Level::INFO,
+        "Sending Pledge Status to registrar at: {}",
+        pledge_status_registrar_url
+    );
+
+    let response = client
+        .post(pledge_status_registrar_url)
+        .header(
+            CONTENT_TYPE,
+            ctx.pledge_info.supported_token_type.as_content_type(),
+        )
+        .body(data)
+        .send()
+        .await?;
+
+    event!(tracing::Level::INFO, "Received pledge_status response");
+
+    if !response.status().is_success() {
+        return Err(ServerError::BadResponse(
+            "Sending pledge_status to registrar failed".to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use brski_prm_artifacts::{ietf_voucher::VoucherRequest, issued_voucher::IssuedVoucher};
 use common::server_error::ServerError;
 use signeable_payload::signeable::raw_signed::RawSigned;
@@ -6,10 +8,13 @@ use tracing::event;
 use crate::{parsed_config::ParsedConfig, PledgeCtx};
 
 use reqwest::{
-    header::{ACCEPT, CONTENT_TYPE},
-    Client,
+    header::{ACCEPT, CONTENT_TYPE, RETRY_AFTER},
+    Client, Response, StatusCode,
 };
 
+/// Used if the registrar asks us to come back later without saying when
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
 #[tracing::instrument(
     skip(client, parsed_config, pvr),
     target = "RegistrarAgent",
@@ -33,20 +38,48 @@ pub async fn send_pvr_to_registrar(
     );
 
     let data = pvr.data();
+    let deadline = tokio::time::Instant::now()
+        + Duration::from_secs(parsed_config.config.voucher_approval_timeout);
+
+    // The registrar answers 202 while the pledge awaits manual approval, we then repeat the request
+    let response = loop {
+        let response = client
+            .post(&request_enroll_registrar_url)
+            .header(
+                ACCEPT,
+                ctx.pledge_info.supported_voucher_type.as_content_type(),
+            )
+            .header(
+                CONTENT_TYPE,
+                ctx.pledge_info.supported_voucher_type.as_content_type(),
+            )
+            .body(data.clone())
+            .send()
+            .await?;
+
+        if response.status() != StatusCode::ACCEPTED {
+            break response;
+        }
+
+        let retry_after = retry_after(&response);
+        if tokio::time::Instant::now() + retry_after > deadline {
+            event!(
+                tracing::Level::ERROR,
+                "Voucher request was not approved within {} seconds",
+                parsed_config.config.voucher_approval_timeout
+            );
+            return Err(ServerError::BadResponse(
+                "Timed out waiting for approval of the voucher request".to_string(),
+            ));
+        }
 
-    let response = client
-        .post(request_enroll_registrar_url)
-        .header(
-            ACCEPT,
-            ctx.pledge_info.supported_voucher_type.as_content_type(),
-        )
-        .header(
-            CONTENT_TYPE,
-            ctx.pledge_info.supported_voucher_type.as_content_type(),
-        )
-        .body(data)
-        .send()
-        .await?;
+        event!(
+            tracing::Level::INFO,
+            "Voucher request awaits approval, retrying in {} seconds",
+            retry_after.as_secs()
+        );
+        tokio::time::sleep(retry_after).await;
+    };
 
     event!(tracing::Level::INFO, "Received response");
     event!(tracing::Level::DEBUG, "Response: {:#?}", response);
@@ -106,3 +139,14 @@ pub async fn send_pvr_to_registrar(
 
     Ok(raw_signed_issued_voucher)
 }
+
+/// Reads the delay-seconds of a Retry-After header, HTTP-dates are not supported
+fn retry_after(response: &Response) -> Duration {
+    response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|seconds| Duration::from_secs(seconds.max(1)))
+        .unwrap_or(DEFAULT_RETRY_AFTER)
+}
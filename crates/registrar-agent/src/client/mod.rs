@@ -3,10 +3,12 @@ mod forward_cacerts;
 mod forward_enroll_response;
 mod forward_enroll_status;
 mod forward_per;
+mod forward_pledge_status;
 mod forward_pvr;
 mod forward_voucher;
 mod forward_voucher_status;
 mod get_wrappedcacerts;
+mod query_pledge_status;
 mod request_pledge_info;
 mod trigger_per;
 mod trigger_pvr;
@@ -16,14 +18,17 @@ pub use forward_cacerts::send_cacerts_to_pledge;
 pub use forward_enroll_response::send_enroll_response_to_pledge;
 pub use forward_enroll_status::send_enroll_status_to_registrar;
 pub use forward_per::send_per_to_registrar;
+pub use forward_pledge_status::send_pledge_status_to_registrar;
 pub use forward_pvr::send_pvr_to_registrar;
 pub use forward_voucher::send_voucher_to_pledge;
 pub use forward_voucher_status::send_voucher_status_to_registrar;
 pub use get_wrappedcacerts::get_wrappedcacerts_from_registrar;
+pub use query_pledge_status::{query_pledge_status, report_pledge_status};
 pub use request_pledge_info::request_pledge_ctx;
 pub use trigger_per::trigger_per;
-pub use trigger_pvr::trigger_pvr;
+pub use trigger_pvr::{pvr_idevid, trigger_pvr};
 
 // bridge
+pub use query_pledge_status::get_status_query;
 pub use trigger_per::get_per_trigger;
 pub use trigger_pvr::get_pvr_trigger;
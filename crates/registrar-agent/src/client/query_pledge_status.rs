@@ -0,0 +1,236 @@
+use brski_prm_artifacts::status::pledge::{
+    request::PledgeStatusQueryRequest,
+    status::{PledgeStatus, PledgeStatusQuery, QueryContext},
+};
+use common::server_error::ServerError;
+use signeable_payload::signeable::{
+    raw_signed::RawSigned, signed::Signed, signing_context::BasicSigningContext, unsigned::Unsigned,
+};
+use tracing::{event, Level};
+
+use super::send_pledge_status_to_registrar;
+use crate::{
+    parsed_config::ParsedConfig, pledge_communicator::PledgeCtx, server::server::ServerState,
+};
+
+#[tracing::instrument(
+    skip(parsed_config),
+    target = "RegistrarAgent",
+    name = "get_status_query"
+)]
+pub fn get_status_query(
+    parsed_config: &ParsedConfig,
+    ctx: &PledgeCtx,
+) -> Result<Signed<PledgeStatusQuery>, ServerError> {
+    let token_type = ctx.pledge_info.supported_token_type.clone();
+
+    let query = PledgeStatusQuery {
+        version: 1,
+        status: true,
+        reason: None,
+        reason_context: QueryContext {
+            pvs_details: "".to_string(),
+        },
+    };
+
+    let request = PledgeStatusQueryRequest::new(
+        query,
+        vec![parsed_config.ee_certificate.clone()],
+        token_type.clone(),
+    );
+    let unsigned: Unsigned<PledgeStatusQuery> = request.try_into()?;
+
+    let signer = token_type.signature_type().get_sv::<PledgeStatusQuery>()?;
+
+    let signed = unsigned
+        .into_signeable_boxed(signer)
+        .sign(parsed_config.ee_key.clone(), BasicSigningContext::new())?;
+
+    Ok(signed)
+}
+
+/// Queries the status of the pledge and verifies it was signed with `idevid`, the DER encoded IDevID
+/// the pledge signed its PVR with, returns the status as received along with its payload
+#[tracing::instrument(skip(state, idevid), target = "RegistrarAgent")]
+pub async fn query_pledge_status(
+    state: &ServerState,
+    ctx: &PledgeCtx,
+    idevid: &[u8],
+) -> Result<(RawSigned<PledgeStatus>, PledgeStatus), ServerError> {
+    let query = get_status_query(&state.config, ctx)?;
+
+    let response = state
+        .communicator
+        .query_pledge_status(query.data(), ctx.clone())
+        .await?;
+
+    event!(Level::INFO, "Received pledge status, verifying it");
+
+    let verifier = ctx
+        .pledge_info
+        .supported_token_type
+        .signature_type()
+        .get_sv::<PledgeStatus>()?;
+    let verified = RawSigned::<PledgeStatus>::new(response.clone())
+        .into_verifyable_boxed(verifier)
+        .verify(None)?;
+
+    // the signature was checked against the first certificate of the x5c, anyone can issue themselves
+    // a certificate with the serial number of the pledge, so it has to be the very IDevID of the PVR
+    let signer = verified
+        .headers()
+        .x509_certificate_chain()
+        .and_then(|chain| chain.into_iter().next());
+    if signer.as_deref() != Some(idevid) {
+        return Err(ServerError::BadResponse(format!(
+            "Pledge status is not signed with the IDevID of pledge {}",
+            ctx.pledge_serial
+        )));
+    }
+
+    let status = verified.payload().clone();
+    event!(
+        Level::INFO,
+        "Pledge {} reports {}",
+        ctx.pledge_serial,
+        status.status
+    );
+
+    Ok((RawSigned::new(response), status))
+}
+
+/// Queries the status of the pledge and forwards it to the registrar
+#[tracing::instrument(skip(state, idevid), target = "RegistrarAgent")]
+pub async fn report_pledge_status(
+    state: &ServerState,
+    ctx: &PledgeCtx,
+    idevid: &[u8],
+) -> Result<PledgeStatus, ServerError> {
+    let (raw, status) = query_pledge_status(state, ctx, idevid).await?;
+
+    send_pledge_status_to_registrar(&state.config, raw, &state.client, ctx).await?;
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use brski_prm_artifacts::token_type::{DataInterchangeFormat, PlainTokenType};
+    use cli::config::RegistrarAgentConfig;
+    use example_certs::{generate_certs, OpensslTestCerts};
+    use openssl::{
+        pkey::{PKey, Private},
+        x509::X509,
+    };
+    use signeable_payload::header::HeaderSet;
+
+    use super::*;
+    use crate::{
+        parsed_config::ParsedConfig,
+        pledge_communicator::{DiscoveredPledge, PledgeCommunicator},
+        server::server::get_state,
+    };
+
+    /// Answers status queries with a fixed signed status
+    #[derive(Clone)]
+    struct StatusCommunicator {
+        status: Vec<u8>,
+    }
+
+    #[async_trait::async_trait]
+    impl PledgeCommunicator for StatusCommunicator {
+        async fn send_pvr_trigger(&self, _: Vec<u8>, _: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn send_per_trigger(&self, _: Vec<u8>, _: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn send_voucher(&self, _: Vec<u8>, _: PledgeCtx) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn send_ca_certs(&self, _: Vec<u8>, _: PledgeCtx) -> Result<(), ServerError> {
+            unreachable!()
+        }
+
+        async fn send_enroll_response(
+            &self,
+            _: Vec<u8>,
+            _: PledgeCtx,
+        ) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+
+        async fn query_pledge_status(
+            &self,
+            _: Vec<u8>,
+            _: PledgeCtx,
+        ) -> Result<Vec<u8>, ServerError> {
+            Ok(self.status.clone())
+        }
+
+        async fn get_data_interchange_format(
+            &self,
+            _: DiscoveredPledge,
+        ) -> Result<String, ServerError> {
+            unreachable!()
+        }
+
+        async fn get_pledge_info(
+            &self,
+            _: DiscoveredPledge,
+            _: DataInterchangeFormat,
+        ) -> Result<Vec<u8>, ServerError> {
+            unreachable!()
+        }
+    }
+
+    fn status_signed_by((certificate, key): &(X509, PKey<Private>)) -> Vec<u8> {
+        let mut header = HeaderSet::new();
+        header.set_x509_certificate_chain(&vec![certificate.to_der().unwrap()], true);
+
+        Unsigned::new(PledgeStatus::default(), header)
+            .into_signeable_boxed(
+                PlainTokenType::JOSE
+                    .signature_type()
+                    .get_sv::<PledgeStatus>()
+                    .unwrap(),
+            )
+            .sign(
+                key.private_key_to_pkcs8().unwrap(),
+                BasicSigningContext::new(),
+            )
+            .unwrap()
+            .data()
+    }
+
+    #[tokio::test]
+    async fn it_only_accepts_statuses_signed_with_the_idevid_of_the_pvr() {
+        let certs: OpensslTestCerts = generate_certs().into();
+        let config = ParsedConfig {
+            config: RegistrarAgentConfig::default(),
+            ee_certificate: certs.registrar_agent.0.clone(),
+            ee_key: certs.registrar_agent.1.private_key_to_pkcs8().unwrap(),
+            registrar_certificate: certs.registrar.0.clone(),
+        };
+        let idevid = certs.pledge.0.to_der().unwrap();
+
+        for (signer, accepted) in [(&certs.pledge, true), (&certs.registrar_agent, false)] {
+            let state = get_state(
+                &config,
+                Box::new(StatusCommunicator {
+                    status: status_signed_by(signer),
+                }),
+            )
+            .unwrap();
+            assert_eq!(
+                query_pledge_status(&state, &PledgeCtx::default(), &idevid)
+                    .await
+                    .is_ok(),
+                accepted
+            );
+        }
+    }
+}
@@ -51,7 +51,7 @@ pub async fn request_pledge_ctx(
         .get_pledge_info(pledge.clone(), dif.clone())
         .await?;
 
-    let deserialized: PledgeInfo = match dif {
+    let mut deserialized: PledgeInfo = match dif {
         DataInterchangeFormat::JSON => serde_json::from_slice(&pledge_info)?,
         DataInterchangeFormat::CBOR => {
             ciborium::from_reader(pledge_info.as_slice()).map_err(|e| anyhow!(e))?
@@ -60,6 +60,14 @@ pub async fn request_pledge_ctx(
 
     info!("Received pledge info for pledge: {:?}", deserialized);
 
+    if let Some(protocol_version) = state.config.config.protocol_version {
+        info!(
+            "Overriding protocol version announced by pledge {}: {} -> {}",
+            pledge.serial, deserialized.protocol_version, protocol_version
+        );
+        deserialized.protocol_version = protocol_version;
+    }
+
     let ctx: PledgeCtx = PledgeCtx {
         ctx: "".to_string(),
         pledge_serial: pledge.serial.clone(),
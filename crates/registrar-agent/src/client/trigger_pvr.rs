@@ -23,8 +23,6 @@ use crate::{
     parsed_config::ParsedConfig, pledge_communicator::PledgeCtx, server::server::ServerState,
 };
 
-const SERVICE_GLOB: &'static str = "_brski-pledge._tcp.local";
-
 #[tracing::instrument(skip(parsed_config), target = "RegistrarAgent")]
 fn get_agent_signed_data(
     parsed_config: &ParsedConfig,
@@ -59,7 +57,11 @@ fn get_agent_signed_data(
 
     info!("Header: {:#?}", header);
     let agent_data = Unsigned::new(
-        agent_signed_data::AgentSignedData::new(created_on, ctx.pledge_serial.clone()),
+        agent_signed_data::AgentSignedData::with_protocol_version(
+            created_on,
+            ctx.pledge_serial.clone(),
+            ctx.pledge_info.protocol_version,
+        ),
         header,
     );
     info!("Creating unsigned ASD: {:?}", agent_data);
@@ -88,8 +90,9 @@ pub fn get_pvr_trigger(
     let agent_signed_data = get_agent_signed_data(parsed_config, ctx)?;
 
     let trigger = VoucherRequestTrigger {
-        agent_signed_proximity_cert: agent_provided_proximity_registrar_cert.into(),
+        agent_provided_proximity_registrar_cert: agent_provided_proximity_registrar_cert.into(),
         agent_signed_data: agent_signed_data.into_raw(),
+        protocol_version: ctx.pledge_info.protocol_version,
     };
 
     Ok(trigger)
@@ -130,3 +133,25 @@ pub async fn trigger_pvr(
 
     Ok(encoded_pvr)
 }
+
+/// The DER encoded IDevID the pledge signed its PVR with, the first certificate of the x5c
+pub fn pvr_idevid(
+    pvr: &RawSigned<VoucherRequest>,
+    ctx: &PledgeCtx,
+) -> Result<Vec<u8>, ServerError> {
+    let verifier = ctx
+        .pledge_info
+        .supported_voucher_type
+        .signature_type()
+        .get_sv::<VoucherRequest>()?;
+
+    RawSigned::<VoucherRequest>::new(pvr.data())
+        .into_verifyable_boxed(verifier)
+        .verify(None)?
+        .headers()
+        .x509_certificate_chain()
+        .and_then(|chain| chain.into_iter().next())
+        .ok_or(ServerError::BadResponse(
+            "PVR is not signed with an IDevID certificate".to_string(),
+        ))
+}
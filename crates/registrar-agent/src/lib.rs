@@ -1,7 +1,10 @@
+mod bootstrapping;
 mod client;
+mod offline;
 mod parsed_config;
 mod pledge_communicator;
 mod server;
+mod tls;
 
 use cli::config::RegistrarAgentConfig;
 use common::error::AppError;
@@ -9,13 +12,15 @@ use parsed_config::parse_config;
 use tokio::task::JoinHandle;
 use tracing::{event, Level};
 
+pub use bootstrapping::{
+    bootstrap_pledge, BootstrapEngine, BootstrapOutcome, JobBundle, JobRegistry, JobStatus,
+    JobStore, JobView, Phase, PledgeBootstrap, PledgeState, Stage,
+};
 pub use client::*;
+pub use offline::run_command;
 pub use parsed_config::*;
 pub use pledge_communicator::{DiscoveredPledge, PledgeCommunicator, PledgeCtx};
-pub use server::{
-    bootstrap_pledge,
-    server::{get_state, ServerState},
-};
+pub use server::server::{get_state, ServerState};
 
 #[tracing::instrument(
     skip(config),
@@ -0,0 +1,77 @@
+use anyhow::anyhow;
+use chrono::Utc;
+use cli::{config::RegistrarAgentConfig, RegistrarAgentCommand};
+use common::error::AppError;
+use tracing::{event, Level};
+
+use crate::{
+    bootstrapping::{BootstrapEngine, Phase},
+    client::discover_pledges,
+    parsed_config::parse_config,
+    server::server::get_server_state,
+};
+
+/// Runs one phase of bootstrapping on the jobs of the job store, or moves jobs between job stores.
+/// Collect and deliver only talk to pledges, forward only to the registrar.
+#[tracing::instrument(
+    target = "RegistrarAgent",
+    skip(config),
+    name = "RegistrarAgent::run_command"
+)]
+pub async fn run_command(
+    config: RegistrarAgentConfig,
+    command: RegistrarAgentCommand,
+) -> anyhow::Result<(), AppError> {
+    let parsed_config = parse_config(config)?;
+    let state = get_server_state(&parsed_config)?;
+    let store = state
+        .store
+        .clone()
+        .ok_or_else(|| anyhow!("registrar-agent: job_store is required for {:?}", command))?;
+
+    let (phase, jobs) = match command {
+        RegistrarAgentCommand::Export { output } => {
+            let exported = store.export(&output).await?;
+            event!(Level::INFO, "Exported {} jobs into {:?}", exported, output);
+            return Ok(());
+        }
+        RegistrarAgentCommand::Import { input } => {
+            let imported = store.import(&input).await?;
+            event!(Level::INFO, "Imported {} jobs from {:?}", imported, input);
+            return Ok(());
+        }
+        RegistrarAgentCommand::Collect => {
+            let max_age = parsed_config.config.agent_signed_data_max_age;
+            let mut jobs = Vec::new();
+            for pledge in discover_pledges(&parsed_config).await? {
+                jobs.push(store.load_or_restart(pledge, max_age, Utc::now()).await?);
+            }
+            (Phase::Collect, jobs)
+        }
+        RegistrarAgentCommand::Forward => (Phase::Forward, store.list().await?),
+        RegistrarAgentCommand::Deliver => (Phase::Deliver, store.list().await?),
+    };
+
+    let engine = BootstrapEngine::new(state);
+    let outcomes = engine.run_phase(phase, jobs).await;
+
+    for outcome in &outcomes {
+        event!(
+            Level::INFO,
+            "Pledge {} is at {:?}",
+            outcome.serial,
+            outcome.stage
+        );
+    }
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} pledges failed in {:?}",
+            failed,
+            outcomes.len(),
+            phase
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -267,6 +267,49 @@ impl PledgeCommunicator for HTTPCommunicator {
         std::result::Result::Ok(response_body.to_vec())
     }
 
+    #[tracing::instrument(skip(self, query, ctx))]
+    async fn query_pledge_status(
+        &self,
+        query: Vec<u8>,
+        ctx: PledgeCtx,
+    ) -> Result<Vec<u8>, ServerError> {
+        let url = format!("{}/.well-known/brski/qps", ctx.pledge_url);
+
+        event!(
+            tracing::Level::INFO,
+            "Sending status query to pledge at: {}",
+            url
+        );
+
+        let response = self
+            .client
+            .post(url)
+            .header(
+                CONTENT_TYPE,
+                ctx.pledge_info.supported_token_type.as_content_type(),
+            )
+            .header(
+                ACCEPT,
+                ctx.pledge_info.supported_token_type.as_content_type(),
+            )
+            .body(query)
+            .send()
+            .await?;
+
+        event!(tracing::Level::INFO, "Received response");
+
+        if !response.status().is_success() {
+            return Err(ServerError::BadResponse(format!(
+                "Sending status query to pledge failed - Status: {}",
+                response.status()
+            )));
+        }
+
+        let response_body = response.bytes().await?;
+
+        Ok(response_body.to_vec())
+    }
+
     #[tracing::instrument(skip(self, pledge))]
     async fn get_data_interchange_format(
         &self,
@@ -1,9 +1,11 @@
 use brski_prm_artifacts::{
     pledge_info::PledgeInfo,
+    protocol_version::ProtocolVersion,
     token_type::{DataInterchangeFormat, PlainTokenType, TokenType, VoucherTokenType},
 };
 use common::server_error::ServerError;
 use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
 use signeable_payload::SignatureType;
 
 pub mod http_communicator;
@@ -32,6 +34,13 @@ pub trait PledgeCommunicator: Send + Sync + DynClone {
         ctx: PledgeCtx,
     ) -> Result<Vec<u8>, ServerError>;
 
+    /// Sends a signed status query, the pledge answers with its signed status
+    async fn query_pledge_status(
+        &self,
+        query: Vec<u8>,
+        ctx: PledgeCtx,
+    ) -> Result<Vec<u8>, ServerError>;
+
     async fn get_data_interchange_format(
         &self,
         pledge: DiscoveredPledge,
@@ -50,13 +59,15 @@ impl Clone for Box<dyn PledgeCommunicator> {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct DiscoveredPledge {
     pub serial: String,
     pub url: String,
+    /// Capabilities the pledge announced while being discovered, its PledgeInfo stays authoritative
+    pub pledge_info: Option<PledgeInfo>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PledgeCtx {
     /// for anything else that needs to be passed to the communicators
     pub ctx: String,
@@ -75,6 +86,7 @@ impl Default for PledgeCtx {
                 data_interchance_format: DataInterchangeFormat::JSON,
                 supported_token_type: PlainTokenType::JOSE,
                 supported_voucher_type: VoucherTokenType::JWS,
+                protocol_version: ProtocolVersion::default(),
             },
         }
     }
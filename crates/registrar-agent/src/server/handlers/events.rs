@@ -0,0 +1,30 @@
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{stream, Stream};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{event, Level};
+
+use crate::server::server::ServerState;
+
+/// Streams a `job` event with the [crate::JobView] of a pledge whenever it changes
+pub(crate) async fn events(
+    State(state): State<ServerState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let events = stream::unfold(state.jobs.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(view) => return Some((Event::default().event("job").json_data(view), receiver)),
+                // the subscriber fell behind, it can catch up through /admin/pledges
+                Err(RecvError::Lagged(missed)) => {
+                    event!(Level::WARN, "Event subscriber missed {} events", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,139 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use brski_prm_artifacts::status::pledge::status::PledgeStatus;
+use chrono::Utc;
+use common::server_error::ServerError;
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::{
+    bootstrapping::{BootstrapEngine, JobView, PledgeBootstrap, PledgeState},
+    client,
+    server::server::ServerState,
+};
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct BootstrapRequest {
+    pub serials: Vec<String>,
+}
+
+/// Discovers pledges and adds them to the jobs, stored jobs are picked up where they stand unless stale
+#[tracing::instrument(skip(state), target = "RegistrarAgent", name = "discover")]
+pub(crate) async fn discover(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<JobView>>, ServerError> {
+    let pledges = client::discover_pledges(&state.config).await?;
+    event!(Level::INFO, "Discovered {} pledges", pledges.len());
+
+    let max_age = state.config.config.agent_signed_data_max_age;
+    let mut views = Vec::with_capacity(pledges.len());
+    for pledge in pledges {
+        let job = match &state.store {
+            Some(store) => store.load_or_restart(pledge, max_age, Utc::now()).await?,
+            None => PledgeBootstrap::new(pledge),
+        };
+        views.push(state.jobs.discovered(job).await);
+    }
+    Ok(Json(views))
+}
+
+pub(crate) async fn list_pledges(State(state): State<ServerState>) -> Json<Vec<JobView>> {
+    Json(state.jobs.list().await)
+}
+
+pub(crate) async fn get_pledge(
+    State(state): State<ServerState>,
+    Path(serial): Path<String>,
+) -> Result<Json<JobView>, ServerError> {
+    state
+        .jobs
+        .get(&serial)
+        .await
+        .map(Json)
+        .ok_or(ServerError::NotFound)
+}
+
+/// The artifacts collected for the pledge so far
+pub(crate) async fn get_artifacts(
+    State(state): State<ServerState>,
+    Path(serial): Path<String>,
+) -> Result<Json<PledgeState>, ServerError> {
+    state
+        .jobs
+        .job(&serial)
+        .await
+        .map(|job| Json(job.state))
+        .ok_or(ServerError::NotFound)
+}
+
+/// Starts bootstrapping the discovered pledges in the background, follow them at `/admin/events`
+#[tracing::instrument(skip(state), target = "RegistrarAgent", name = "bootstrap")]
+pub(crate) async fn bootstrap(
+    State(state): State<ServerState>,
+    Json(request): Json<BootstrapRequest>,
+) -> Result<(StatusCode, Json<Vec<JobView>>), ServerError> {
+    let jobs = state.jobs.claim(&request.serials).await?;
+    start(state, jobs).await
+}
+
+pub(crate) async fn cancel(
+    State(state): State<ServerState>,
+    Path(serial): Path<String>,
+) -> Result<Json<JobView>, ServerError> {
+    state.jobs.cancel(&serial).await.map(Json)
+}
+
+/// Takes up a failed or cancelled job again, from its last step taken
+pub(crate) async fn retry(
+    State(state): State<ServerState>,
+    Path(serial): Path<String>,
+) -> Result<(StatusCode, Json<Vec<JobView>>), ServerError> {
+    let jobs = state.jobs.claim(&[serial.clone()]).await?;
+    if jobs.is_empty() {
+        return Err(ServerError::BadRequestWithReason(format!(
+            "Pledge {} is being bootstrapped or done",
+            serial
+        )));
+    }
+    start(state, jobs).await
+}
+
+/// Queries the signed status of the pledge and reports it to the registrar
+#[tracing::instrument(skip(state), target = "RegistrarAgent", name = "query_status")]
+pub(crate) async fn query_status(
+    State(state): State<ServerState>,
+    Path(serial): Path<String>,
+) -> Result<Json<PledgeStatus>, ServerError> {
+    let mut job = state.jobs.job(&serial).await.ok_or(ServerError::NotFound)?;
+    let idevid = job
+        .idevid
+        .clone()
+        .ok_or(ServerError::BadRequestWithReason(format!(
+            "The IDevID of pledge {} is only known once its PVR was collected",
+            serial
+        )))?;
+    let ctx = job.pledge_ctx(&state).await?;
+
+    let status = client::report_pledge_status(&state, &ctx, &idevid).await?;
+    state.jobs.status_queried(&serial, status.clone()).await;
+
+    Ok(Json(status))
+}
+
+async fn start(
+    state: ServerState,
+    jobs: Vec<PledgeBootstrap>,
+) -> Result<(StatusCode, Json<Vec<JobView>>), ServerError> {
+    let mut views = Vec::with_capacity(jobs.len());
+    for job in &jobs {
+        views.extend(state.jobs.get(&job.pledge.serial).await);
+    }
+
+    let engine = BootstrapEngine::new(state);
+    tokio::spawn(async move { engine.resume(jobs).await });
+
+    Ok((StatusCode::ACCEPTED, Json(views)))
+}
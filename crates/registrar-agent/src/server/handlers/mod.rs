@@ -1,9 +1,27 @@
+mod events;
 mod init;
-use axum::{routing::post, Router};
-pub use init::bootstrap_pledge;
+mod jobs;
+use axum::{
+    routing::{get, post},
+    Router,
+};
 
 use super::server::ServerState;
 
 pub(crate) fn brski_routes() -> Router<ServerState> {
     Router::new().route("/init", post(init::init))
 }
+
+/// Management API for operators and the app, guarded by [common::middleware::require_admin_token]
+pub(crate) fn admin_routes() -> Router<ServerState> {
+    Router::new()
+        .route("/discover", post(jobs::discover))
+        .route("/bootstrap", post(jobs::bootstrap))
+        .route("/pledges", get(jobs::list_pledges))
+        .route("/pledges/:serial", get(jobs::get_pledge))
+        .route("/pledges/:serial/artifacts", get(jobs::get_artifacts))
+        .route("/pledges/:serial/cancel", post(jobs::cancel))
+        .route("/pledges/:serial/retry", post(jobs::retry))
+        .route("/pledges/:serial/status", post(jobs::query_status))
+        .route("/events", get(events::events))
+}
@@ -1,4 +1,3 @@
 mod handlers;
 pub mod server;
-pub use handlers::bootstrap_pledge;
 pub use server::get_app;
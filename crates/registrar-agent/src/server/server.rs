@@ -1,28 +1,37 @@
 use crate::{
+    bootstrapping::{JobRegistry, JobStore},
     parsed_config::ParsedConfig,
     pledge_communicator::{http_communicator::HTTPCommunicator, PledgeCommunicator},
+    tls::registrar_client,
 };
 use axum::Router;
-use common::error::AppError;
+use common::{
+    error::AppError,
+    middleware::{require_admin_token, AdminToken},
+};
 use reqwest::Client;
 use tower_http::trace::TraceLayer;
 
-use super::handlers::brski_routes;
+use super::handlers::{admin_routes, brski_routes};
 
 #[derive(Clone)]
 pub struct ServerState {
     pub config: ParsedConfig,
+    /// Client for the registrar, see [registrar_client]
     pub client: reqwest::Client,
     pub communicator: Box<dyn PledgeCommunicator>,
+    /// Persists the jobs if `job_store` is configured
+    pub store: Option<JobStore>,
+    pub jobs: JobRegistry,
 }
 
-fn get_server_state(config: &ParsedConfig) -> anyhow::Result<ServerState, AppError> {
-    let client = Client::new();
-
+pub(crate) fn get_server_state(config: &ParsedConfig) -> anyhow::Result<ServerState, AppError> {
     Ok(ServerState {
         config: config.clone(),
-        client: client.clone(),
-        communicator: Box::new(HTTPCommunicator::new(client)),
+        client: registrar_client(config)?,
+        communicator: Box::new(HTTPCommunicator::new(Client::new())),
+        store: JobStore::from_config(config)?,
+        jobs: JobRegistry::default(),
     })
 }
 
@@ -32,15 +41,25 @@ pub fn get_state(
 ) -> anyhow::Result<ServerState, AppError> {
     Ok(ServerState {
         config: config.clone(),
-        client: Client::new(),
+        client: registrar_client(config)?,
         communicator,
+        store: JobStore::from_config(config)?,
+        jobs: JobRegistry::default(),
     })
 }
 
 pub async fn get_app(config: &ParsedConfig) -> anyhow::Result<Router<()>, AppError> {
     let state = get_server_state(config)?;
 
-    let routes = Router::new().nest("/.well-known/brski", brski_routes());
+    let routes = Router::new()
+        .nest("/.well-known/brski", brski_routes())
+        .nest(
+            "/admin",
+            admin_routes().route_layer(axum::middleware::from_fn_with_state(
+                AdminToken(config.config.admin_token.clone()),
+                require_admin_token,
+            )),
+        );
 
     let app = routes
         .with_state(state)
@@ -0,0 +1,76 @@
+use std::{sync::Arc, time::SystemTime};
+
+use reqwest::Client;
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName,
+};
+
+use crate::parsed_config::ParsedConfig;
+
+/// Builds the client used for the registrar.
+/// With TLS enabled it authenticates with the ee certificate and only talks to the pinned registrar.
+pub(crate) fn registrar_client(config: &ParsedConfig) -> anyhow::Result<Client> {
+    if !config.config.use_tls {
+        return Ok(Client::new());
+    }
+
+    let verifier = PinnedRegistrarVerifier::new(config.registrar_certificate.to_der()?)?;
+
+    let tls_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_client_auth_cert(
+            vec![Certificate(config.ee_certificate.to_der()?)],
+            PrivateKey(config.ee_key.clone()),
+        )?;
+
+    Ok(Client::builder()
+        .use_preconfigured_tls(tls_config)
+        .build()?)
+}
+
+/// Accepts exactly the pinned registrar certificate, or a certificate the pinned CA issued for the registrar's host
+struct PinnedRegistrarVerifier {
+    pinned: Certificate,
+    issued_by_pinned: WebPkiVerifier,
+}
+
+impl PinnedRegistrarVerifier {
+    fn new(pinned: Vec<u8>) -> anyhow::Result<Self> {
+        let pinned = Certificate(pinned);
+
+        let mut roots = RootCertStore::empty();
+        roots.add(&pinned)?;
+
+        Ok(Self {
+            pinned,
+            issued_by_pinned: WebPkiVerifier::new(roots, None),
+        })
+    }
+}
+
+impl ServerCertVerifier for PinnedRegistrarVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if *end_entity == self.pinned {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        self.issued_by_pinned.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )
+    }
+}
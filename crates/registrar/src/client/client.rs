@@ -1,28 +1,26 @@
 use brski_prm_artifacts::{
+    audit_log::AuditLog,
     ietf_voucher::{artifact::VoucherArtifact, VoucherRequest},
     issued_voucher::IssuedVoucher,
+    token_type::JSON,
 };
 use common::server_error::ServerError;
 use reqwest::header::CONTENT_TYPE;
 use signeable_payload::signeable::{raw_signed::RawSigned, signed::Signed};
 use tracing::{event, Level};
 
-use crate::parsed_config::ParsedConfig;
+use crate::masa::MasaEndpoint;
 
-use reqwest::{header::ACCEPT, Client};
+use reqwest::header::ACCEPT;
 
-#[tracing::instrument(target = "Registrar", skip(parsed_config, rvr, client))]
-pub async fn get_voucher_from_masa(
-    parsed_config: &ParsedConfig,
-    rvr: Signed<VoucherRequest>,
-    client: &Client,
+#[tracing::instrument(target = "Registrar", skip(masa, rvr), fields(masa = %masa.url))]
+pub(crate) async fn get_voucher_from_masa(
+    masa: &MasaEndpoint,
+    rvr: &Signed<VoucherRequest>,
 ) -> Result<RawSigned<VoucherArtifact>, ServerError> {
     event!(Level::DEBUG, "PVR to be sent: {:#?}", rvr);
 
-    let requestvoucher_masa_url = format!(
-        "{}/.well-known/brski/requestvoucher",
-        parsed_config.config.masa_url
-    );
+    let requestvoucher_masa_url = format!("{}/.well-known/brski/requestvoucher", masa.url);
 
     // from the RVR we signed, we can get the requested content type for for the voucher from the MASA
     let rvr_content_type = rvr
@@ -39,7 +37,8 @@ pub async fn get_voucher_from_masa(
         requestvoucher_masa_url
     );
 
-    let response = client
+    let response = masa
+        .client
         .post(requestvoucher_masa_url)
         .header(ACCEPT, requested_voucher_content_type)
         .header(CONTENT_TYPE, rvr_content_type)
@@ -78,3 +77,53 @@ pub async fn get_voucher_from_masa(
 
     Ok(raw_signed_voucher_artifact)
 }
+
+/// Fetches the audit log of the pledge `rvr` is for, see RFC 8995 5.8
+#[tracing::instrument(target = "Registrar", skip(masa, rvr), fields(masa = %masa.url))]
+pub(crate) async fn get_audit_log_from_masa(
+    masa: &MasaEndpoint,
+    rvr: &Signed<VoucherRequest>,
+) -> Result<AuditLog, ServerError> {
+    let requestauditlog_masa_url = format!("{}/.well-known/brski/requestauditlog", masa.url);
+
+    let rvr_content_type = rvr
+        .header()
+        .content_type()
+        .ok_or(ServerError::InternalError(anyhow::anyhow!(
+            "No content type in RVR"
+        )))?;
+
+    event!(
+        Level::INFO,
+        "Requesting audit log from MASA at {:?}",
+        requestauditlog_masa_url
+    );
+
+    let response = masa
+        .client
+        .post(requestauditlog_masa_url)
+        .header(ACCEPT, JSON)
+        .header(CONTENT_TYPE, rvr_content_type)
+        .body(rvr.data())
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(ServerError::BadResponse(format!(
+            "Requesting audit log from MASA failed with Status: {}",
+            response.status()
+        )));
+    }
+
+    let audit_log: AuditLog = response
+        .json()
+        .await
+        .map_err(|e| ServerError::BadResponse(format!("Can not parse audit log: {}", e)))?;
+    event!(
+        Level::INFO,
+        "Received audit log with {} entries",
+        audit_log.events.len()
+    );
+
+    Ok(audit_log)
+}
@@ -1,3 +1,3 @@
 mod client;
 
-pub use client::get_voucher_from_masa;
+pub(crate) use client::{get_audit_log_from_masa, get_voucher_from_masa};
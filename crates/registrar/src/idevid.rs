@@ -0,0 +1,180 @@
+use anyhow::anyhow;
+use common::server_error::ServerError;
+use openssl::{nid::Nid, x509::X509};
+use signeable_payload::header::HeaderSet;
+use x509_cert::{
+    der::{
+        asn1::{Ia5String, ObjectIdentifier, OctetString},
+        oid::AssociatedOid,
+        Decode, SliceReader,
+    },
+    ext::{
+        pkix::{name::GeneralName, SubjectAltName},
+        Extension,
+    },
+    Certificate,
+};
+
+/// id-on-hardwareModuleName, RFC 4108
+const ID_ON_HARDWARE_MODULE_NAME: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.8.4");
+
+/// id-pe-masa-url, RFC 8995
+const ID_PE_MASA_URL: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.1.32");
+
+/// The parts of a pledge's IDevID certificate profiles can refer to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct IdevidIdentity {
+    /// serialNumber attribute of the subject
+    pub(crate) serial: Option<String>,
+    /// hwType of the hardwareModuleName SAN
+    pub(crate) hw_type: Option<String>,
+    /// hwSerialNum of the hardwareModuleName SAN
+    pub(crate) hw_serial: Option<String>,
+    /// organizationName of the issuer, i.e. the manufacturer
+    pub(crate) manufacturer: Option<String>,
+    /// MASA URI extension
+    pub(crate) masa_uri: Option<String>,
+}
+
+impl IdevidIdentity {
+    pub(crate) fn from_der(der: &[u8]) -> Result<Self, ServerError> {
+        let cert = X509::from_der(der)?;
+        let serial = match cert.subject_name().entries_by_nid(Nid::SERIALNUMBER).next() {
+            Some(entry) => Some(entry.data().as_utf8()?.to_string()),
+            None => None,
+        };
+        let manufacturer = match cert
+            .issuer_name()
+            .entries_by_nid(Nid::ORGANIZATIONNAME)
+            .next()
+        {
+            Some(entry) => Some(entry.data().as_utf8()?.to_string()),
+            None => None,
+        };
+
+        let cert = Certificate::from_der(der).map_err(malformed)?;
+        let extensions = cert.tbs_certificate.extensions.unwrap_or_default();
+
+        let (hw_type, hw_serial) = match hardware_module_name(&extensions)? {
+            Some((hw_type, hw_serial)) => (Some(hw_type), Some(hw_serial)),
+            None => (None, None),
+        };
+
+        let masa_uri = match extensions.iter().find(|ext| ext.extn_id == ID_PE_MASA_URL) {
+            Some(extension) => Some(masa_uri(extension.extn_value.as_bytes())?),
+            None => None,
+        };
+
+        Ok(Self {
+            serial,
+            hw_type,
+            hw_serial,
+            manufacturer,
+            masa_uri,
+        })
+    }
+
+    /// Reads the end-entity certificate of the x5c header of an artifact signed by the pledge.
+    /// Without an x5c header nothing is known about the IDevID.
+    pub(crate) fn from_headers(headers: &HeaderSet) -> Result<Self, ServerError> {
+        match headers
+            .x509_certificate_chain()
+            .and_then(|chain| chain.into_iter().next())
+        {
+            Some(idevid) => Self::from_der(&idevid),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Replaces `{serial}`, `{hw-type}` and `{hw-serial}` in `template`
+    pub(crate) fn render(&self, template: &str) -> Result<String, ServerError> {
+        let mut rendered = template.to_string();
+        for (placeholder, value) in [
+            ("{serial}", &self.serial),
+            ("{hw-type}", &self.hw_type),
+            ("{hw-serial}", &self.hw_serial),
+        ] {
+            if !rendered.contains(placeholder) {
+                continue;
+            }
+            let value = value
+                .as_ref()
+                .ok_or(ServerError::BadRequestWithReason(format!(
+                    "IDevID does not provide {} required by the cert profile",
+                    placeholder
+                )))?;
+            rendered = rendered.replace(placeholder, value);
+        }
+        Ok(rendered)
+    }
+}
+
+fn malformed(e: x509_cert::der::Error) -> ServerError {
+    ServerError::InternalError(anyhow!("Malformed IDevID: {}", e))
+}
+
+/// Reads MASAURLSyntax. Older example certificates carry the bare string instead of an IA5String.
+fn masa_uri(value: &[u8]) -> Result<String, ServerError> {
+    match Ia5String::from_der(value) {
+        Ok(uri) => Ok(uri.to_string()),
+        Err(_) if value.iter().all(|b| b.is_ascii_graphic()) => {
+            Ok(String::from_utf8_lossy(value).into_owned())
+        }
+        Err(e) => Err(malformed(e)),
+    }
+}
+
+/// Reads hwType and hwSerialNum from the hardwareModuleName SAN, if present
+fn hardware_module_name(extensions: &[Extension]) -> Result<Option<(String, String)>, ServerError> {
+    for extension in extensions
+        .iter()
+        .filter(|ext| ext.extn_id == SubjectAltName::OID)
+    {
+        let san = SubjectAltName::from_der(extension.extn_value.as_bytes()).map_err(malformed)?;
+        for name in san.0 {
+            let GeneralName::OtherName(other_name) = name else {
+                continue;
+            };
+            if other_name.type_id != ID_ON_HARDWARE_MODULE_NAME {
+                continue;
+            }
+
+            let mut reader = SliceReader::new(other_name.value.value()).map_err(malformed)?;
+            let hw_type = ObjectIdentifier::decode(&mut reader).map_err(malformed)?;
+            let hw_serial = OctetString::decode(&mut reader).map_err(malformed)?;
+
+            return Ok(Some((hw_type.to_string(), printable(hw_serial.as_bytes()))));
+        }
+    }
+
+    Ok(None)
+}
+
+/// hwSerialNum is an OCTET STRING, we keep it as is if it is printable and hex-encode it otherwise
+fn printable(bytes: &[u8]) -> String {
+    if bytes.iter().all(|b| b.is_ascii_graphic()) {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_renders_templates() {
+        let idevid = IdevidIdentity {
+            serial: Some("0123456789".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            idevid.render("{serial}.pledges.example.com").unwrap(),
+            "0123456789.pledges.example.com"
+        );
+        assert!(idevid.render("{hw-serial}.pledges.example.com").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use brski_prm_artifacts::{ietf_voucher::artifact::VoucherArtifact, token_type::VoucherTokenType};
+use common::{server_error::ServerError, util::voucher_file_name};
+use signeable_payload::signeable::raw_signed::RawSigned;
+use tracing::{event, Level};
+
+use crate::parsed_config::ParsedConfig;
+
+/// Vouchers pre-issued by the MASA for sites that never reach it, see `open-brski masa issue`
+#[derive(Clone, Debug)]
+pub(crate) struct ImportedVouchers {
+    dir: Option<PathBuf>,
+}
+
+impl ImportedVouchers {
+    pub(crate) fn from_config(config: &ParsedConfig) -> Self {
+        Self::new(
+            config
+                .config
+                .imported_vouchers
+                .as_ref()
+                .map(|dir| dir.relative()),
+        )
+    }
+
+    pub(crate) fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    /// The voucher imported for the pledge in the requested format, if there is one
+    pub(crate) async fn get(
+        &self,
+        serial_number: &str,
+        token_type: &VoucherTokenType,
+    ) -> Result<Option<RawSigned<VoucherArtifact>>, ServerError> {
+        let (Some(dir), Some(file_name)) =
+            (&self.dir, voucher_file_name(serial_number, token_type))
+        else {
+            return Ok(None);
+        };
+
+        match tokio::fs::read(dir.join(&file_name)).await {
+            Ok(voucher) => {
+                event!(Level::INFO, "Found imported voucher {}", file_name);
+                Ok(Some(RawSigned::new(voucher)))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_finds_imported_vouchers() {
+        let dir = std::env::temp_dir().join(format!("imported-vouchers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("00-D0-E5-F2-00-02.cose"), b"voucher").unwrap();
+        let imported = ImportedVouchers::new(Some(dir.clone()));
+
+        let voucher = imported
+            .get("00-D0-E5-F2-00-02", &VoucherTokenType::COSE)
+            .await
+            .unwrap();
+        assert_eq!(voucher.unwrap().data(), b"voucher");
+        assert!(imported
+            .get("00-D0-E5-F2-00-02", &VoucherTokenType::JWS)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(imported
+            .get("../00-D0-E5-F2-00-02", &VoucherTokenType::COSE)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(ImportedVouchers::new(None)
+            .get("00-D0-E5-F2-00-02", &VoucherTokenType::COSE)
+            .await
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
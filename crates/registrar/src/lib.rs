@@ -1,10 +1,20 @@
 mod client;
+mod idevid;
+mod imported;
+mod masa;
 mod parsed_config;
+mod policy;
+mod proximity;
 mod server;
 mod sign_cert;
+mod storage;
+#[cfg(test)]
+mod test_certs;
+mod tls;
+mod voucher;
 
 use cli::config::RegistrarConfig;
-use common::error::AppError;
+use common::{error::AppError, tls::ClientCertAcceptor};
 use parsed_config::parse_config;
 use tokio::task::JoinHandle;
 use tracing::{event, Level};
@@ -25,9 +35,21 @@ pub async fn start(config: RegistrarConfig) -> anyhow::Result<JoinHandle<()>, Ap
 
     let app = server::get_app(&parsed_config).await?;
 
-    let listener = tokio::net::TcpListener::bind(parsed_address).await?;
-
-    let server_handle = tokio::spawn(async { axum::serve(listener, app).await.unwrap() });
+    let server_handle = match parsed_config.config.use_tls {
+        true => {
+            event!(Level::INFO, "Requiring mTLS from registrar-agents");
+            let tls_config = tls::server_config(&parsed_config)?;
+            let server =
+                axum_server::bind(*parsed_address).acceptor(ClientCertAcceptor::new(tls_config));
+
+            tokio::spawn(async { server.serve(app.into_make_service()).await.unwrap() })
+        }
+        false => {
+            let listener = tokio::net::TcpListener::bind(parsed_address).await?;
+
+            tokio::spawn(async { axum::serve(listener, app).await.unwrap() })
+        }
+    };
 
     Ok(server_handle)
 }
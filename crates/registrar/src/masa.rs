@@ -0,0 +1,331 @@
+use cli::config::VendorConfig;
+use common::{server_error::ServerError, util::verify_certificate_chain};
+use openssl::x509::X509;
+use reqwest::Client;
+use signeable_payload::header::HeaderSet;
+use tracing::{event, Level};
+
+use crate::{
+    idevid::IdevidIdentity, parsed_config::ParsedConfig, policy::glob_matches, tls::masa_client,
+};
+
+/// Where the voucher request of a pledge is sent to
+#[derive(Clone, Debug)]
+pub(crate) struct MasaEndpoint {
+    /// Base URL of the MASA, the BRSKI well-known paths are appended
+    pub(crate) url: String,
+    /// Name of the vendor configuration that applied, if any
+    pub(crate) vendor: Option<String>,
+    pub(crate) client: Client,
+    /// CA the vouchers of the MASA have to chain to
+    pub(crate) voucher_ca: X509,
+}
+
+#[derive(Clone, Debug)]
+struct Vendor {
+    name: String,
+    config: VendorConfig,
+    client: Client,
+    voucher_ca: X509,
+    idevid_ca: X509,
+}
+
+/// Picks the MASA for each pledge, so devices of several manufacturers can be onboarded side by side.
+///
+/// The URL is taken from the first vendor matching the IDevID issuer, from the IDevID MASA URI extension
+/// or from the configured `masa_url`, in that order. Before that the IDevID has to chain to the IDevID CA
+/// of its vendor, anyone can put any manufacturer and URL into a self-signed certificate.
+#[derive(Clone, Debug)]
+pub(crate) struct MasaRouter {
+    masa_url: String,
+    use_idevid_masa_uri: bool,
+    client: Client,
+    voucher_ca: X509,
+    idevid_ca: X509,
+    vendors: Vec<Vendor>,
+}
+
+impl MasaRouter {
+    pub(crate) fn from_config(config: &ParsedConfig) -> anyhow::Result<Self> {
+        let client = masa_client(config, config.masa_ca_certificate.as_ref(), true)?;
+
+        let vendors = config
+            .config
+            .vendors
+            .iter()
+            .map(|(name, vendor)| {
+                let masa_ca = config
+                    .vendor_masa_ca_certificates
+                    .get(name)
+                    .or(config.masa_ca_certificate.as_ref());
+
+                Ok(Vendor {
+                    name: name.clone(),
+                    config: vendor.clone(),
+                    client: masa_client(config, masa_ca, vendor.client_authentication)?,
+                    voucher_ca: config
+                        .vendor_voucher_ca_certificates
+                        .get(name)
+                        .unwrap_or(&config.voucher_ca_certificate)
+                        .clone(),
+                    idevid_ca: config
+                        .vendor_idevid_ca_certificates
+                        .get(name)
+                        .unwrap_or(&config.idevid_ca_certificate)
+                        .clone(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        event!(Level::INFO, "Configured {} MASA vendors", vendors.len());
+
+        Ok(Self {
+            masa_url: config.config.masa_url.clone(),
+            use_idevid_masa_uri: config.config.use_idevid_masa_uri,
+            client,
+            voucher_ca: config.voucher_ca_certificate.clone(),
+            idevid_ca: config.idevid_ca_certificate.clone(),
+            vendors,
+        })
+    }
+
+    /// `idevid_chain` is the x5c the IDevID was read from, it is rejected unless it chains to the
+    /// IDevID CA of the vendor it claims or to `idevid_ca_certificate` if it matches none
+    pub(crate) fn resolve(
+        &self,
+        idevid: &IdevidIdentity,
+        idevid_chain: &[Vec<u8>],
+    ) -> Result<MasaEndpoint, ServerError> {
+        let vendor = self.trusted_vendor(idevid, idevid_chain)?;
+
+        let idevid_url = idevid
+            .masa_uri
+            .as_deref()
+            .filter(|_| self.use_idevid_masa_uri)
+            .map(base_url);
+
+        let url = vendor
+            .and_then(|vendor| vendor.config.masa_url.clone())
+            .or(idevid_url)
+            .unwrap_or_else(|| self.masa_url.clone());
+
+        Ok(MasaEndpoint {
+            url: url.trim_end_matches('/').to_string(),
+            vendor: vendor.map(|vendor| vendor.name.clone()),
+            client: vendor.map_or(self.client.clone(), |vendor| vendor.client.clone()),
+            voucher_ca: vendor.map_or(self.voucher_ca.clone(), |vendor| vendor.voucher_ca.clone()),
+        })
+    }
+
+    /// Checks that a pledge artifact signed with the IDevID heading `idevid_chain` comes from a genuine pledge,
+    /// as [Self::resolve] does for voucher requests
+    pub(crate) fn verify_idevid(
+        &self,
+        idevid: &IdevidIdentity,
+        idevid_chain: &[Vec<u8>],
+    ) -> Result<(), ServerError> {
+        self.trusted_vendor(idevid, idevid_chain).map(|_| ())
+    }
+
+    /// Reads the IDevID heading the x5c of a verified pledge artifact and checks it with [Self::verify_idevid],
+    /// so statuses are only recorded for the pledge a genuine IDevID names
+    pub(crate) fn signing_idevid(
+        &self,
+        headers: &HeaderSet,
+    ) -> Result<IdevidIdentity, ServerError> {
+        let idevid_chain =
+            headers
+                .x509_certificate_chain()
+                .ok_or(ServerError::BadRequestWithReason(
+                    "Artifact is not signed with an IDevID certificate".to_string(),
+                ))?;
+        let idevid = IdevidIdentity::from_headers(headers)?;
+        self.verify_idevid(&idevid, &idevid_chain)?;
+
+        Ok(idevid)
+    }
+
+    /// The vendor the IDevID claims, None for the default one, if the IDevID chains to its IDevID CA
+    fn trusted_vendor(
+        &self,
+        idevid: &IdevidIdentity,
+        idevid_chain: &[Vec<u8>],
+    ) -> Result<Option<&Vendor>, ServerError> {
+        let vendor = idevid.manufacturer.as_deref().and_then(|manufacturer| {
+            self.vendors
+                .iter()
+                .find(|vendor| glob_matches(&vendor.config.manufacturer, manufacturer))
+        });
+
+        let idevid_ca = vendor.map_or(&self.idevid_ca, |vendor| &vendor.idevid_ca);
+        verify_certificate_chain(idevid_ca, idevid_chain).map_err(|reason| {
+            event!(
+                Level::WARN,
+                "IDevID does not chain to the IDevID CA of vendor {:?}: {}",
+                vendor.map(|vendor| &vendor.name),
+                reason
+            );
+            ServerError::Forbidden("IDevID was not issued by a trusted manufacturer".to_string())
+        })?;
+
+        Ok(vendor)
+    }
+}
+
+/// The MASA URI extension may just carry the authority, https is implied then (RFC 8995 2.3.2)
+fn base_url(uri: &str) -> String {
+    match uri.contains("://") {
+        true => uri.to_string(),
+        false => format!("https://{}", uri),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_certs::{ca, certificate};
+    use brski_prm_artifacts::{
+        status::voucher::{response::VoucherStatusResponse, status::VoucherStatus},
+        token_type::PlainTokenType,
+    };
+    use openssl::pkey::{PKey, Private};
+    use signeable_payload::{
+        signeable::{raw_signed::RawSigned, unsigned::Unsigned},
+        BasicSigningContext,
+    };
+
+    fn issued_by(ca: &(X509, PKey<Private>)) -> Vec<Vec<u8>> {
+        vec![certificate("pledge", Some((&ca.0, &ca.1)))
+            .0
+            .to_der()
+            .unwrap()]
+    }
+
+    #[test]
+    fn it_routes_pledges_to_their_masa() {
+        let (default_ca, acme_ca, other_ca) = (ca("vendor ca"), ca("acme ca"), ca("other ca"));
+
+        let router = MasaRouter {
+            masa_url: "http://localhost:3000".to_string(),
+            use_idevid_masa_uri: true,
+            client: Client::new(),
+            voucher_ca: ca("vendor voucher ca").0,
+            idevid_ca: default_ca.0.clone(),
+            vendors: vec![
+                Vendor {
+                    name: "acme".to_string(),
+                    config: VendorConfig {
+                        manufacturer: "ACME *".to_string(),
+                        masa_url: Some("https://masa.acme.example/".to_string()),
+                        ..Default::default()
+                    },
+                    client: Client::new(),
+                    voucher_ca: ca("acme voucher ca").0,
+                    idevid_ca: acme_ca.0.clone(),
+                },
+                Vendor {
+                    name: "other".to_string(),
+                    config: VendorConfig {
+                        manufacturer: "Other *".to_string(),
+                        ..Default::default()
+                    },
+                    client: Client::new(),
+                    voucher_ca: ca("other voucher ca").0,
+                    idevid_ca: other_ca.0.clone(),
+                },
+            ],
+        };
+
+        let acme = router
+            .resolve(
+                &IdevidIdentity {
+                    manufacturer: Some("ACME Corp".to_string()),
+                    masa_uri: Some("masa.example.com".to_string()),
+                    ..Default::default()
+                },
+                &issued_by(&acme_ca),
+            )
+            .unwrap();
+        assert_eq!(acme.url, "https://masa.acme.example");
+        assert_eq!(acme.vendor.as_deref(), Some("acme"));
+
+        let other = IdevidIdentity {
+            manufacturer: Some("Other Inc".to_string()),
+            masa_uri: Some("masa.example.com:8443".to_string()),
+            ..Default::default()
+        };
+        let trusted = router.resolve(&other, &issued_by(&other_ca)).unwrap();
+        assert_eq!(trusted.url, "https://masa.example.com:8443");
+        assert_eq!(trusted.vendor.as_deref(), Some("other"));
+
+        // claiming another manufacturer than the one that issued the IDevID
+        assert!(matches!(
+            router.resolve(&other, &issued_by(&acme_ca)),
+            Err(ServerError::Forbidden(_))
+        ));
+        assert!(matches!(
+            router.resolve(&other, &issued_by(&ca("forged ca"))),
+            Err(ServerError::Forbidden(_))
+        ));
+        assert!(router.verify_idevid(&other, &issued_by(&other_ca)).is_ok());
+        assert!(matches!(
+            router.verify_idevid(&other, &issued_by(&ca("forged ca"))),
+            Err(ServerError::Forbidden(_))
+        ));
+
+        let unknown = router
+            .resolve(&IdevidIdentity::default(), &issued_by(&default_ca))
+            .unwrap();
+        assert_eq!(unknown.url, "http://localhost:3000");
+        assert_eq!(unknown.vendor, None);
+    }
+
+    #[test]
+    fn it_refuses_statuses_signed_with_an_untrusted_idevid() {
+        let vendor_ca = ca("vendor ca");
+        let router = MasaRouter {
+            masa_url: "http://localhost:3000".to_string(),
+            use_idevid_masa_uri: false,
+            client: Client::new(),
+            voucher_ca: ca("vendor voucher ca").0,
+            idevid_ca: vendor_ca.0.clone(),
+            vendors: vec![],
+        };
+
+        let signed_status = |(idevid, key): (X509, PKey<Private>)| {
+            let token_type = PlainTokenType::JOSE;
+            let sv = || {
+                token_type
+                    .signature_type()
+                    .get_sv::<VoucherStatus>()
+                    .unwrap()
+            };
+            let unsigned: Unsigned<VoucherStatus> =
+                VoucherStatusResponse::new(VoucherStatus::default(), [idevid], token_type.clone())
+                    .try_into()
+                    .unwrap();
+            let signed = unsigned
+                .into_signeable_boxed(sv())
+                .sign(
+                    key.private_key_to_pkcs8().unwrap(),
+                    BasicSigningContext::new(),
+                )
+                .unwrap();
+
+            RawSigned::<VoucherStatus>::new(signed.data())
+                .into_verifyable_boxed(sv())
+                .verify(None)
+                .unwrap()
+        };
+
+        let genuine = signed_status(certificate("pledge", Some((&vendor_ca.0, &vendor_ca.1))));
+        assert!(router.signing_idevid(genuine.headers()).is_ok());
+
+        // the signature verifies with the self-issued certificate it carries
+        let forged = signed_status(certificate("pledge", None));
+        assert!(matches!(
+            router.signing_idevid(forged.headers()),
+            Err(ServerError::Forbidden(_))
+        ));
+    }
+}
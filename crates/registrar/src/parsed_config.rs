@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::anyhow;
 use brski_prm_artifacts::ietf_voucher::pki::Pkey;
 use cli::config::RegistrarConfig;
@@ -16,12 +18,20 @@ pub(crate) struct ParsedConfig {
     pub(crate) registrar_certificate: X509,
     pub(crate) registrar_key: Vec<u8>,
     pub(crate) reg_agt_ee_cert: X509,
-    pub(crate) masa_url: String,
+    /// Only loaded if TLS is enabled
+    pub(crate) agent_ca_certificate: Option<X509>,
+    pub(crate) masa_ca_certificate: Option<X509>,
+    pub(crate) voucher_ca_certificate: X509,
+    pub(crate) idevid_ca_certificate: X509,
+    /// MASA trust anchors of the vendors configuring one, by vendor name
+    pub(crate) vendor_masa_ca_certificates: BTreeMap<String, X509>,
+    /// Voucher trust anchors of the vendors configuring one, by vendor name
+    pub(crate) vendor_voucher_ca_certificates: BTreeMap<String, X509>,
+    /// IDevID trust anchors of the vendors configuring one, by vendor name
+    pub(crate) vendor_idevid_ca_certificates: BTreeMap<String, X509>,
 }
 
 pub(crate) fn parse_config(config: RegistrarConfig) -> anyhow::Result<ParsedConfig, AppError> {
-    let masa_url = config.masa_url.clone();
-
     let unparsed_reg_agt_ee_cert = std::fs::read(config.reg_agt_ee_cert.relative())?;
     let reg_agt_ee_cert = X509::from_pem(&unparsed_reg_agt_ee_cert)?;
 
@@ -46,6 +56,42 @@ pub(crate) fn parse_config(config: RegistrarConfig) -> anyhow::Result<ParsedConf
     let registrar_key = ec::EcKey::private_key_from_pem(&unparsed_registrar_key)?;
     let registrar_key_pkcs8 = PKey::from_ec_key(registrar_key.clone())?.private_key_to_pkcs8()?;
 
+    let agent_ca_certificate = match config.use_tls {
+        true => Some(X509::from_pem(&std::fs::read(
+            config.agent_ca_certificate.relative(),
+        )?)?),
+        false => None,
+    };
+
+    let masa_ca_certificate = match &config.masa_ca_certificate {
+        Some(path) => Some(X509::from_pem(&std::fs::read(path.relative())?)?),
+        None => None,
+    };
+
+    let voucher_ca_certificate =
+        X509::from_pem(&std::fs::read(config.voucher_ca_certificate.relative())?)?;
+
+    let idevid_ca_certificate =
+        X509::from_pem(&std::fs::read(config.idevid_ca_certificate.relative())?)?;
+
+    let mut vendor_masa_ca_certificates = BTreeMap::new();
+    let mut vendor_voucher_ca_certificates = BTreeMap::new();
+    let mut vendor_idevid_ca_certificates = BTreeMap::new();
+    for (name, vendor) in &config.vendors {
+        if let Some(path) = &vendor.masa_ca_certificate {
+            let certificate = X509::from_pem(&std::fs::read(path.relative())?)?;
+            vendor_masa_ca_certificates.insert(name.clone(), certificate);
+        }
+        if let Some(path) = &vendor.voucher_ca_certificate {
+            let certificate = X509::from_pem(&std::fs::read(path.relative())?)?;
+            vendor_voucher_ca_certificates.insert(name.clone(), certificate);
+        }
+        if let Some(path) = &vendor.idevid_ca_certificate {
+            let certificate = X509::from_pem(&std::fs::read(path.relative())?)?;
+            vendor_idevid_ca_certificates.insert(name.clone(), certificate);
+        }
+    }
+
     /// This registrar certificate must be signed by the CA certificate
     assert!(registrar_certificate
         .verify(&openssl::pkey::PKey::from_ec_key(ca_key.clone()).unwrap())
@@ -58,6 +104,12 @@ pub(crate) fn parse_config(config: RegistrarConfig) -> anyhow::Result<ParsedConf
         registrar_certificate,
         registrar_key: registrar_key_pkcs8,
         reg_agt_ee_cert,
-        masa_url,
+        agent_ca_certificate,
+        masa_ca_certificate,
+        voucher_ca_certificate,
+        idevid_ca_certificate,
+        vendor_masa_ca_certificates,
+        vendor_voucher_ca_certificates,
+        vendor_idevid_ca_certificates,
     })
 }
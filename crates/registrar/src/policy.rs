@@ -0,0 +1,401 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use brski_prm_artifacts::audit_log::AuditLog;
+use cli::config::{PolicyAction, PolicyConfig, PolicyRule};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use tracing::{event, Level};
+
+/// Upper bound of operations a policy script may run per decision
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000;
+
+/// What the policy gets to see of a pledge
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DeviceInfo {
+    pub(crate) serial: String,
+    pub(crate) manufacturer: Option<String>,
+    pub(crate) masa_uri: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Decision {
+    pub(crate) action: PolicyAction,
+    pub(crate) reason: String,
+}
+
+impl Decision {
+    fn new(action: PolicyAction, reason: impl Into<String>) -> Self {
+        Self {
+            action,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// The registrar's "accept device?" decision, see [PolicyConfig]
+#[derive(Clone)]
+pub(crate) struct Policy {
+    config: PolicyConfig,
+    script: Option<(Arc<Engine>, Arc<AST>)>,
+}
+
+impl Policy {
+    /// Builds the policy, compiling the configured script
+    pub(crate) fn from_config(config: &PolicyConfig) -> anyhow::Result<Self> {
+        let source = match &config.script {
+            Some(path) => Some(std::fs::read_to_string(path.relative())?),
+            None => None,
+        };
+
+        Self::new(config.clone(), source.as_deref())
+    }
+
+    fn new(config: PolicyConfig, script: Option<&str>) -> anyhow::Result<Self> {
+        let script = match script {
+            Some(source) => {
+                let mut engine = Engine::new();
+                engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+                let ast = engine
+                    .compile(source)
+                    .map_err(|e| anyhow!("Could not compile policy script: {}", e))?;
+                Some((Arc::new(engine), Arc::new(ast)))
+            }
+            None => None,
+        };
+
+        Ok(Self { config, script })
+    }
+
+    pub(crate) fn evaluate(&self, device: &DeviceInfo) -> Decision {
+        let decision = self.decide(device);
+
+        event!(
+            Level::INFO,
+            "Policy decision for pledge {}: {} ({})",
+            device.serial,
+            match decision.action {
+                PolicyAction::Allow => "accepted",
+                PolicyAction::Deny => "rejected",
+                PolicyAction::Review => "needs review",
+            },
+            decision.reason
+        );
+
+        decision
+    }
+
+    /// Checks the MASA audit log of a pledge against [cli::config::AuditLogPolicy].
+    /// `own_domain_ids` are the domainIDs of the certificates vouchers for this registrar pin.
+    pub(crate) fn evaluate_audit_log(
+        &self,
+        serial: &str,
+        log: &AuditLog,
+        own_domain_ids: &[String],
+    ) -> Decision {
+        let policy = &self.config.audit_log;
+        let foreign = log.events.iter().find(|event| {
+            !own_domain_ids.contains(&event.domain_id)
+                && (policy.reject_other_domains
+                    || (policy.reject_nonceless_elsewhere && event.nonce.is_none()))
+        });
+
+        let decision = match foreign {
+            Some(event) if event.nonce.is_none() => Decision::new(
+                PolicyAction::Deny,
+                format!(
+                    "Pledge was issued a nonceless voucher for domain {} on {}",
+                    event.domain_id, event.date
+                ),
+            ),
+            Some(event) => Decision::new(
+                PolicyAction::Deny,
+                format!(
+                    "Pledge was issued a voucher for domain {} on {}",
+                    event.domain_id, event.date
+                ),
+            ),
+            None => Decision::new(PolicyAction::Allow, "Audit log accepted"),
+        };
+
+        event!(
+            Level::INFO,
+            "Audit log decision for pledge {}: {}",
+            serial,
+            decision.reason
+        );
+
+        decision
+    }
+
+    fn decide(&self, device: &DeviceInfo) -> Decision {
+        if let Some((index, rule)) = self
+            .config
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule_matches(rule, device))
+        {
+            let reason = rule
+                .reason
+                .clone()
+                .unwrap_or_else(|| format!("Matched policy rule {}", index));
+            return Decision::new(rule.action, reason);
+        }
+
+        if let Some(decision) = self.run_script(device) {
+            return decision;
+        }
+
+        Decision::new(self.config.default_action, "Default policy action")
+    }
+
+    /// Asks the script, rejecting the device if the script fails
+    fn run_script(&self, device: &DeviceInfo) -> Option<Decision> {
+        let (engine, ast) = self.script.as_ref()?;
+
+        let mut map = Map::new();
+        map.insert("serial".into(), device.serial.clone().into());
+        map.insert(
+            "manufacturer".into(),
+            option_to_dynamic(&device.manufacturer),
+        );
+        map.insert("masa_uri".into(), option_to_dynamic(&device.masa_uri));
+
+        let result = engine.call_fn::<Dynamic>(&mut Scope::new(), ast, "accept", (map,));
+
+        match result {
+            Ok(value) if value.is_unit() => None,
+            Ok(value) if value.is_bool() => Some(match value.as_bool().unwrap() {
+                true => Decision::new(PolicyAction::Allow, "Accepted by policy script"),
+                false => Decision::new(PolicyAction::Deny, "Rejected by policy script"),
+            }),
+            Ok(value) if value.is_string() => Some(Decision::new(
+                PolicyAction::Deny,
+                value.into_string().unwrap(),
+            )),
+            Ok(value) => {
+                event!(
+                    Level::ERROR,
+                    "Policy script returned unexpected {}",
+                    value.type_name()
+                );
+                Some(Decision::new(PolicyAction::Deny, "Policy script failed"))
+            }
+            Err(e) => {
+                event!(Level::ERROR, "Policy script failed: {}", e);
+                Some(Decision::new(PolicyAction::Deny, "Policy script failed"))
+            }
+        }
+    }
+}
+
+fn option_to_dynamic(value: &Option<String>) -> Dynamic {
+    match value {
+        Some(value) => value.clone().into(),
+        None => Dynamic::UNIT,
+    }
+}
+
+fn rule_matches(rule: &PolicyRule, device: &DeviceInfo) -> bool {
+    let matches = |pattern: &Option<String>, value: Option<&str>| match pattern {
+        Some(pattern) => value.is_some_and(|value| glob_matches(pattern, value)),
+        None => true,
+    };
+
+    matches(&rule.serial, Some(&device.serial))
+        && matches(&rule.manufacturer, device.manufacturer.as_deref())
+        && matches(&rule.masa_uri, device.masa_uri.as_deref())
+}
+
+/// Matches `value` against a pattern where `*` matches any number and `?` exactly one character
+pub(crate) fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    // position of the last `*` and the value position it was tried at
+    let mut backtrack = None;
+
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star, star_v)) = backtrack {
+            p = star + 1;
+            v = star_v + 1;
+            backtrack = Some((star, star_v + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use brski_prm_artifacts::audit_log::AuditLogEvent;
+    use cli::config::AuditLogPolicy;
+
+    use super::*;
+
+    fn device(serial: &str) -> DeviceInfo {
+        DeviceInfo {
+            serial: serial.to_string(),
+            manufacturer: Some("Vendor".to_string()),
+            masa_uri: Some("https://masa.example.com".to_string()),
+        }
+    }
+
+    fn rule(action: PolicyAction, serial: &str) -> PolicyRule {
+        PolicyRule {
+            action,
+            serial: Some(serial.to_string()),
+            manufacturer: None,
+            masa_uri: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn it_matches_globs() {
+        assert!(glob_matches("00-D0-E5-*", "00-D0-E5-F2-00-02"));
+        assert!(glob_matches("*-00-0?", "00-D0-E5-F2-00-02"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("00-D0-E5-?", "00-D0-E5-F2"));
+        assert!(!glob_matches("01-*", "00-D0-E5-F2-00-02"));
+    }
+
+    #[test]
+    fn the_first_matching_rule_decides() {
+        let policy = Policy::new(
+            PolicyConfig {
+                default_action: PolicyAction::Deny,
+                rules: vec![
+                    PolicyRule {
+                        reason: Some("Blocked batch".to_string()),
+                        ..rule(PolicyAction::Deny, "00-D0-E5-F2-*")
+                    },
+                    rule(PolicyAction::Allow, "00-D0-E5-*"),
+                ],
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            policy.evaluate(&device("00-D0-E5-F2-00-02")),
+            Decision::new(PolicyAction::Deny, "Blocked batch")
+        );
+        assert_eq!(
+            policy.evaluate(&device("00-D0-E5-01-00-02")).action,
+            PolicyAction::Allow
+        );
+        assert_eq!(
+            policy.evaluate(&device("01-00-00-00-00-00")).action,
+            PolicyAction::Deny
+        );
+    }
+
+    #[test]
+    fn the_audit_log_rejects_pledges_claimed_elsewhere() {
+        let event = |domain_id: &str, nonce: Option<&str>| AuditLogEvent {
+            date: chrono::Utc::now(),
+            domain_id: domain_id.to_string(),
+            nonce: nonce.map(str::to_string),
+            assertion: None,
+            truncated: None,
+        };
+        let policy = |audit_log: AuditLogPolicy| {
+            Policy::new(
+                PolicyConfig {
+                    audit_log,
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap()
+        };
+        let own = ["ours".to_string()];
+
+        let nonced_elsewhere =
+            AuditLog::new(vec![event("ours", None), event("theirs", Some("bm9uY2U"))]);
+        let nonceless_elsewhere = AuditLog::new(vec![event("theirs", None), event("ours", None)]);
+
+        let strict = policy(AuditLogPolicy {
+            reject_other_domains: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            strict
+                .evaluate_audit_log("pledge", &nonced_elsewhere, &own)
+                .action,
+            PolicyAction::Deny
+        );
+        assert_eq!(
+            strict
+                .evaluate_audit_log("pledge", &AuditLog::new(vec![event("ours", None)]), &own)
+                .action,
+            PolicyAction::Allow
+        );
+
+        let nonceless = policy(AuditLogPolicy {
+            reject_nonceless_elsewhere: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            nonceless
+                .evaluate_audit_log("pledge", &nonced_elsewhere, &own)
+                .action,
+            PolicyAction::Allow
+        );
+        assert_eq!(
+            nonceless
+                .evaluate_audit_log("pledge", &nonceless_elsewhere, &own)
+                .action,
+            PolicyAction::Deny
+        );
+    }
+
+    #[test]
+    fn the_script_decides_if_no_rule_matches() {
+        let script = r#"
+            fn accept(device) {
+                if device.manufacturer != "Vendor" {
+                    return "Unknown manufacturer";
+                }
+                if device.serial.starts_with("00-") {
+                    return true;
+                }
+            }
+        "#;
+        let policy = Policy::new(
+            PolicyConfig {
+                default_action: PolicyAction::Deny,
+                ..Default::default()
+            },
+            Some(script),
+        )
+        .unwrap();
+
+        assert_eq!(
+            policy.evaluate(&device("00-D0-E5-F2-00-02")).action,
+            PolicyAction::Allow
+        );
+        assert_eq!(
+            policy.evaluate(&DeviceInfo {
+                manufacturer: None,
+                ..device("00-D0-E5-F2-00-02")
+            }),
+            Decision::new(PolicyAction::Deny, "Unknown manufacturer")
+        );
+        assert_eq!(
+            policy.evaluate(&device("01-00-00-00-00-00")),
+            Decision::new(PolicyAction::Deny, "Default policy action")
+        );
+    }
+}
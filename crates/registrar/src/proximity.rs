@@ -0,0 +1,242 @@
+use base64::Engine;
+use brski_prm_artifacts::ietf_voucher::{
+    agent_signed_data::AgentSignedData, request_artifact::VoucherRequestArtifact,
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{
+    agent_signed_data::{verify_agent_signed_data, AgentSignedDataError},
+    server_error::ServerError,
+};
+use openssl::x509::X509;
+use signeable_payload::SignerVerifyer;
+use tracing::{event, Level};
+
+/// Why the agent-proximity evidence of a PVR was not accepted
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ProximityError {
+    #[error(transparent)]
+    AgentSignedData(#[from] AgentSignedDataError),
+
+    #[error("PVR does not contain agent-provided-proximity-registrar-cert")]
+    MissingRegistrarCert,
+
+    #[error("Can not read the registrar-agent certificate: {0}")]
+    AgentCertificate(String),
+
+    #[error("agent-signed-data key id {0} does not identify the authorized registrar-agent")]
+    UnauthorizedAgent(String),
+
+    #[error("agent-signed-data was created on {0}, which is older than {1} seconds")]
+    Stale(DateTime<Utc>, u64),
+
+    #[error("agent-provided-proximity-registrar-cert is not the certificate of this registrar")]
+    ForeignRegistrar,
+}
+
+impl From<ProximityError> for ServerError {
+    fn from(value: ProximityError) -> Self {
+        match value {
+            ProximityError::AgentSignedData(AgentSignedDataError::Missing)
+            | ProximityError::MissingRegistrarCert => {
+                ServerError::BadRequestWithReason(value.to_string())
+            }
+            _ => ServerError::Forbidden(value.to_string()),
+        }
+    }
+}
+
+/// What agent-proximity evidence is checked against
+pub(crate) struct ProximityVerifier<'a> {
+    /// The registrar-agent allowed to vouch for pledges
+    pub(crate) agent: &'a X509,
+    /// Certificates the agent may have pinned for this registrar
+    pub(crate) registrar_certificates: [&'a X509; 2],
+    /// Maximum age of agent-signed-data in seconds
+    pub(crate) max_age: u64,
+}
+
+impl ProximityVerifier<'_> {
+    /// Checks that the PVR's agent-signed-data was created recently by the authorized agent for the PVR's pledge,
+    /// and that the agent saw this registrar's certificate.
+    pub(crate) fn verify(
+        &self,
+        pvr: &VoucherRequestArtifact,
+        verifyer: Box<dyn SignerVerifyer<AgentSignedData>>,
+        now: DateTime<Utc>,
+    ) -> Result<(), ProximityError> {
+        let agent_skid = self
+            .agent
+            .subject_key_id()
+            .ok_or(ProximityError::AgentCertificate(
+                "missing SubjectKeyIdentifier".to_string(),
+            ))?
+            .as_slice()
+            .to_vec();
+
+        let verified = verify_agent_signed_data(pvr, self.agent, verifyer, now)?;
+
+        let kid = verified.headers().key_id().unwrap_or_default();
+        if !identifies(kid, &agent_skid) {
+            return Err(ProximityError::UnauthorizedAgent(kid.to_string()));
+        }
+
+        let data = &verified.payload().data;
+        if now - data.created_on > Duration::seconds(self.max_age as i64) {
+            return Err(ProximityError::Stale(data.created_on, self.max_age));
+        }
+
+        let registrar_cert = pvr
+            .details
+            .agent_provided_proximity_registrar_cert
+            .as_ref()
+            .ok_or(ProximityError::MissingRegistrarCert)?;
+        let pinned = registrar_cert.to_der().ok();
+        if !self
+            .registrar_certificates
+            .iter()
+            .any(|certificate| certificate.to_der().ok() == pinned)
+        {
+            return Err(ProximityError::ForeignRegistrar);
+        }
+
+        event!(
+            Level::INFO,
+            "Verified agent proximity for {}",
+            pvr.details.serial_number
+        );
+        Ok(())
+    }
+}
+
+/// Agents encode the SKID as kid in any base64 flavour, the draft examples use the standard alphabet
+fn identifies(kid: &str, skid: &[u8]) -> bool {
+    use base64::engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD};
+
+    [STANDARD, URL_SAFE, URL_SAFE_NO_PAD]
+        .iter()
+        .any(|engine| engine.decode(kid).is_ok_and(|decoded| decoded == skid))
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::pkey::{PKey, Private};
+    use signeable_payload::{
+        header::HeaderSet, BasicSigningContext, DefaultSignerVerifyer, Unsigned,
+    };
+
+    use super::*;
+    use crate::test_certs::certificate;
+
+    fn pvr(
+        agent: &X509,
+        key: &PKey<Private>,
+        serial_number: &str,
+        created_on: DateTime<Utc>,
+        registrar: &X509,
+    ) -> VoucherRequestArtifact {
+        let kid = base64::engine::general_purpose::URL_SAFE
+            .encode(agent.subject_key_id().unwrap().as_slice());
+        let mut header = HeaderSet::new();
+        header.set_key_id(kid, true);
+
+        let signed = Unsigned::new(
+            AgentSignedData::new(created_on, serial_number.to_string()),
+            header,
+        )
+        .into_signeable(DefaultSignerVerifyer::default())
+        .sign(
+            key.private_key_to_pkcs8().unwrap(),
+            BasicSigningContext::new(),
+        )
+        .unwrap();
+
+        let mut pvr = VoucherRequestArtifact::default();
+        pvr.details.serial_number = "pledge-1".to_string();
+        pvr.details.agent_signed_data = Some(signed.into_raw());
+        pvr.details.agent_provided_proximity_registrar_cert = Some(registrar.clone().into());
+        pvr
+    }
+
+    fn verify(
+        verifier: &ProximityVerifier,
+        pvr: &VoucherRequestArtifact,
+    ) -> Result<(), ProximityError> {
+        verifier.verify(pvr, Box::new(DefaultSignerVerifyer::default()), Utc::now())
+    }
+
+    #[test]
+    fn it_verifies_agent_proximity() {
+        let (agent, agent_key) = certificate("agent", None);
+        let (other_agent, other_agent_key) = certificate("other agent", None);
+        let (registrar, _) = certificate("registrar", None);
+        let (ca, _) = certificate("registrar ca", None);
+        let (foreign, _) = certificate("foreign registrar", None);
+        let verifier = ProximityVerifier {
+            agent: &agent,
+            registrar_certificates: [&registrar, &ca],
+            max_age: 3600,
+        };
+
+        let now = Utc::now();
+        assert!(verify(
+            &verifier,
+            &pvr(&agent, &agent_key, "pledge-1", now, &registrar)
+        )
+        .is_ok());
+        assert!(verify(&verifier, &pvr(&agent, &agent_key, "pledge-1", now, &ca)).is_ok());
+
+        assert!(matches!(
+            verify(
+                &verifier,
+                &pvr(&other_agent, &other_agent_key, "pledge-1", now, &registrar)
+            ),
+            Err(ProximityError::AgentSignedData(
+                AgentSignedDataError::InvalidSignature(_)
+            ))
+        ));
+        assert!(matches!(
+            verify(
+                &verifier,
+                &pvr(&agent, &agent_key, "pledge-2", now, &registrar)
+            ),
+            Err(ProximityError::AgentSignedData(
+                AgentSignedDataError::SerialMismatch { .. }
+            ))
+        ));
+        assert!(matches!(
+            verify(
+                &verifier,
+                &pvr(
+                    &agent,
+                    &agent_key,
+                    "pledge-1",
+                    now - Duration::hours(2),
+                    &registrar
+                )
+            ),
+            Err(ProximityError::Stale(..))
+        ));
+        assert!(matches!(
+            verify(
+                &verifier,
+                &pvr(
+                    &agent,
+                    &agent_key,
+                    "pledge-1",
+                    now + Duration::hours(2),
+                    &registrar
+                )
+            ),
+            Err(ProximityError::AgentSignedData(
+                AgentSignedDataError::CreatedInFuture(_)
+            ))
+        ));
+        assert!(matches!(
+            verify(
+                &verifier,
+                &pvr(&agent, &agent_key, "pledge-1", now, &foreign)
+            ),
+            Err(ProximityError::ForeignRegistrar)
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use common::server_error::ServerError;
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::{server::server::ServerState, storage::ApprovalRecord};
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ApprovalDecision {
+    pub reason: Option<String>,
+}
+
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_list_approvals(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<ApprovalRecord>>, ServerError> {
+    Ok(Json(state.storage.list_approvals().await?))
+}
+
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_approve(
+    State(state): State<ServerState>,
+    Path(serial_number): Path<String>,
+    decision: Option<Json<ApprovalDecision>>,
+) -> Result<Json<ApprovalRecord>, ServerError> {
+    decide(state, serial_number, true, decision).await
+}
+
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_reject(
+    State(state): State<ServerState>,
+    Path(serial_number): Path<String>,
+    decision: Option<Json<ApprovalDecision>>,
+) -> Result<Json<ApprovalRecord>, ServerError> {
+    decide(state, serial_number, false, decision).await
+}
+
+async fn decide(
+    state: ServerState,
+    serial_number: String,
+    approved: bool,
+    decision: Option<Json<ApprovalDecision>>,
+) -> Result<Json<ApprovalRecord>, ServerError> {
+    let reason = decision.and_then(|Json(decision)| decision.reason);
+
+    let record = state
+        .storage
+        .decide_approval(&serial_number, approved, reason)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    event!(
+        Level::INFO,
+        "Operator {} pledge {}",
+        if approved { "approved" } else { "rejected" },
+        serial_number
+    );
+
+    Ok(Json(record))
+}
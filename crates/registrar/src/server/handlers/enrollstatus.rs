@@ -14,7 +14,7 @@ use common::server_error::ServerError;
 use signeable_payload::{signeable::raw_signed::RawSigned, DefaultSignerVerifyer};
 use tracing::{event, Level};
 
-use crate::{client, server::server::ServerState};
+use crate::{client, server::server::ServerState, storage::PledgeEvent};
 
 // We don't trust client's to supply just any base64 encoded data, so we parse it.
 #[tracing::instrument(target = "Registrar", skip(state, headers, bytes))]
@@ -46,5 +46,21 @@ pub async fn handle_enrollstatus(
 
     event!(Level::INFO, "Enroll Status from Voucher: {:#?}", status);
 
+    let idevid = state.masas.signing_idevid(decoded.headers())?;
+    let serial_number = idevid.serial.ok_or(ServerError::BadRequestWithReason(
+        "Can not get pledge serial number from enroll status signature".to_string(),
+    ))?;
+
+    state
+        .storage
+        .record_event(
+            &serial_number,
+            PledgeEvent::EnrollStatus {
+                status: status.status,
+                reason: Some(status.reason.clone()),
+            },
+        )
+        .await?;
+
     Ok(())
 }
@@ -1,4 +1,7 @@
+mod approvals;
 mod enrollstatus;
+mod pledge_status;
+mod pledges;
 mod requestenroll;
 mod requestvoucher;
 mod voucher_status;
@@ -27,4 +30,30 @@ pub(crate) fn brski_routes() -> Router<ServerState> {
             post(voucher_status::handle_voucher_status),
         )
         .route("/enrollstatus", post(enrollstatus::handle_enrollstatus))
+        .route("/pledge_status", post(pledge_status::handle_pledge_status))
+}
+
+/// Operator endpoints, guarded by [common::middleware::require_admin_token]
+#[tracing::instrument(target = "Registrar")]
+pub(crate) fn admin_routes() -> Router<ServerState> {
+    Router::new()
+        .route("/approvals", get(approvals::handle_list_approvals))
+        .route(
+            "/approvals/:serial_number/approve",
+            post(approvals::handle_approve),
+        )
+        .route(
+            "/approvals/:serial_number/reject",
+            post(approvals::handle_reject),
+        )
+        .route("/pledges", get(pledges::handle_list_pledges))
+        .route("/pledges/:serial_number", get(pledges::handle_get_pledge))
+        .route(
+            "/pledges/:serial_number/voucher",
+            get(pledges::handle_get_voucher),
+        )
+        .route(
+            "/pledges/:serial_number/release",
+            post(pledges::handle_release_pledge),
+        )
 }
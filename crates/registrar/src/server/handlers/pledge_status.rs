@@ -0,0 +1,62 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderMap},
+};
+use brski_prm_artifacts::{status::pledge::status::PledgeStatus, token_type::PlainTokenType};
+use common::server_error::ServerError;
+use signeable_payload::signeable::raw_signed::RawSigned;
+use tracing::{event, Level};
+
+use crate::{server::server::ServerState, storage::PledgeEvent};
+
+/// Records the status a pledge signed with its IDevID in answer to a status query of an agent
+#[tracing::instrument(target = "Registrar", skip(state, headers, bytes))]
+pub async fn handle_pledge_status(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    bytes: Bytes,
+) -> Result<(), ServerError> {
+    event!(Level::DEBUG, "Headers: {:#?}", headers);
+
+    event!(Level::INFO, "Received pledge_status request");
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .ok_or(ServerError::BadRequest)?
+        .to_str()
+        .map_err(|_| ServerError::BadRequest)?;
+
+    let token_type = PlainTokenType::from_content_type(content_type);
+
+    let verifier = token_type.signature_type().get_sv::<PledgeStatus>()?;
+
+    event!(Level::INFO, "Parsing Pledge Status from body");
+    let pledge_status: RawSigned<PledgeStatus> = RawSigned::new(bytes.to_vec());
+
+    let decoded = pledge_status.into_verifyable_boxed(verifier).verify(None)?;
+
+    let status = decoded.payload();
+
+    event!(Level::INFO, "Pledge Status: {:#?}", status);
+
+    // the signature was checked against the first certificate of the x5c, which has to be a genuine IDevID
+    let idevid = state.masas.signing_idevid(decoded.headers())?;
+
+    let serial_number = idevid.serial.ok_or(ServerError::BadRequestWithReason(
+        "Can not get pledge serial number from pledge status signature".to_string(),
+    ))?;
+
+    state
+        .storage
+        .record_event(
+            &serial_number,
+            PledgeEvent::PledgeStatus {
+                status: status.status.to_string(),
+                reason: status.reason.clone(),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
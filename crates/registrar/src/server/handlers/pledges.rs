@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Path, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    Json,
+};
+use common::server_error::ServerError;
+use tracing::{event, Level};
+
+use crate::{server::server::ServerState, storage::PledgeRecord};
+
+/// The onboarding state of every pledge the registrar has seen
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_list_pledges(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<PledgeRecord>>, ServerError> {
+    Ok(Json(state.storage.list_pledges().await?))
+}
+
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_get_pledge(
+    State(state): State<ServerState>,
+    Path(serial_number): Path<String>,
+) -> Result<Json<PledgeRecord>, ServerError> {
+    state
+        .storage
+        .get_pledge(&serial_number)
+        .await?
+        .map(Json)
+        .ok_or(ServerError::NotFound)
+}
+
+/// The last voucher relayed for the pledge, as signed by the MASA
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_get_voucher(
+    State(state): State<ServerState>,
+    Path(serial_number): Path<String>,
+) -> Result<Response, ServerError> {
+    let voucher = state
+        .storage
+        .latest_voucher(&serial_number)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    Ok(([(CONTENT_TYPE, voucher.content_type)], voucher.voucher).into_response())
+}
+
+/// Releases a pledge sold on or returned, the registrar forgets its onboarding state.
+/// The returned record names the LDevID the pledge was issued, so operators can revoke it.
+#[tracing::instrument(target = "Registrar", skip(state))]
+pub async fn handle_release_pledge(
+    State(state): State<ServerState>,
+    Path(serial_number): Path<String>,
+) -> Result<Json<PledgeRecord>, ServerError> {
+    let record = state
+        .storage
+        .release_pledge(&serial_number)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    event!(
+        Level::INFO,
+        "Released pledge {}, its LDevID was {:?}",
+        serial_number,
+        record.ldevid_serial
+    );
+    Ok(Json(record))
+}
@@ -21,7 +21,7 @@ use signeable_payload::{
 use common::{server_error::ServerError, util::is_pkcs7};
 use tracing::{event, Level};
 
-use crate::{client, server::server::ServerState, sign_cert};
+use crate::{client, server::server::ServerState, sign_cert, storage::PledgeEvent};
 
 // We don't trust client's to supply just any base64 encoded data, so we parse it.
 #[tracing::instrument(target = "Registrar", skip(state, headers, bytes))]
@@ -63,21 +63,57 @@ pub async fn handle_requestenroll(
 
     let payload = decoded.payload().clone();
 
-    let csr: X509Req = payload.csr.p10_csr;
+    // the PER signature only proves possession of the key in its x5c, which has to be a genuine IDevID
+    let idevid = state.masas.signing_idevid(decoded.headers())?;
+    event!(Level::DEBUG, "Pledge IDevID: {:?}", idevid);
 
-    let registrar_ca_cert = state.config.ca_certificate.clone();
-    let registrar_ca_key = state.config.ca_key.clone();
+    let Some((profile_name, profile)) =
+        sign_cert::select_cert_profile(&state.config.config, &idevid)
+    else {
+        return Err(ServerError::InternalError(anyhow::anyhow!(
+            "No cert profile configured"
+        )));
+    };
+    event!(Level::INFO, "Using cert profile {}", profile_name);
 
-    let registrar_sign_cert = state.config.registrar_certificate.clone();
-    let registrar_sign_key = state.config.registrar_key.clone();
+    let csr: X509Req = payload.csr.p10_csr;
 
-    let pkey = openssl::pkey::PKey::private_key_from_pkcs8(&state.config.registrar_key.clone())?;
+    let registrar_ca_cert = state.config.ca_certificate.clone();
+    let registrar_ca_key = openssl::pkey::PKey::private_key_from_pkcs8(&state.config.ca_key)?;
 
     event!(Level::INFO, "Signing certificate");
-    let (signed_cert, signed_cert_pkey) =
-        crate::sign_cert::mk_ca_signed_cert(&registrar_ca_cert, &pkey, &csr)?;
+    let signed_cert = sign_cert::mk_ca_signed_cert(
+        &registrar_ca_cert,
+        &registrar_ca_key,
+        &csr,
+        profile,
+        &idevid,
+    )?;
 
     event!(Level::INFO, "Created certificate for pledge");
+
+    match &idevid.serial {
+        Some(serial_number) => {
+            let ldevid_serial = signed_cert
+                .serial_number()
+                .to_bn()?
+                .to_hex_str()?
+                .to_string();
+            state
+                .storage
+                .record_event(
+                    serial_number,
+                    PledgeEvent::LdevidIssued {
+                        serial: ldevid_serial,
+                    },
+                )
+                .await?;
+        }
+        None => event!(
+            Level::WARN,
+            "Pledge IDevID has no serial number, not recording issued LDevID"
+        ),
+    }
     event!(Level::DEBUG, "Signed certificate: {:#?}", signed_cert);
 
     let response = RegistrarEnrollRequestResponse(signed_cert.into());
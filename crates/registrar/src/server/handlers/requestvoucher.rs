@@ -2,41 +2,50 @@ use axum::{
     body::Bytes,
     extract::State,
     http::{
-        header::{ACCEPT, CONTENT_TYPE},
-        HeaderMap,
+        header::{ACCEPT, CONTENT_TYPE, RETRY_AFTER},
+        HeaderMap, StatusCode,
     },
+    response::{IntoResponse, Response},
+    Extension,
 };
 use brski_prm_artifacts::{
     ietf_voucher::{
-        artifact::VoucherArtifact, pki::X509, request_artifact::VoucherRequestArtifact,
-        VoucherRequest,
+        agent_signed_data::AgentSignedData, artifact::VoucherArtifact, pki::X509,
+        request_artifact::VoucherRequestArtifact, VoucherRequest,
     },
     issued_voucher::IssuedVoucher,
     pvr::response::PledgeVoucherRequestResponse,
     rvr::response::RegistrarVoucherRequestResponse,
     token_type::{self, VoucherTokenType},
 };
-use common::server_error::ServerError;
+use cli::config::PolicyAction;
+use common::{server_error::ServerError, tls::TlsClientIdentity, util::domain_id};
 use signeable_payload::{
     algorithm::Algorithm,
     header::HeaderSet,
-    signeable::{
-        raw_signed::RawSigned, signed::Signed, signing_context::BasicSigningContext,
-        unsigned::Unsigned,
-    },
+    signeable::{raw_signed::RawSigned, signing_context::BasicSigningContext, unsigned::Unsigned},
     DefaultSignerVerifyer,
 };
 use tracing::{event, info, Level};
 
-use crate::{client, server::server::ServerState};
+use crate::{
+    client,
+    idevid::IdevidIdentity,
+    policy::DeviceInfo,
+    proximity::ProximityVerifier,
+    server::server::ServerState,
+    storage::{ApprovalStatus, PledgeEvent, VoucherRecord},
+    voucher::VoucherValidator,
+};
 
 // We don't trust client's to supply just any base64 encoded data, so we parse it.
-#[tracing::instrument(target = "Registrar", skip(state, headers, bytes))]
+#[tracing::instrument(target = "Registrar", skip(state, headers, agent, bytes))]
 pub async fn handle_requestvoucher(
     State(state): State<ServerState>,
     headers: HeaderMap,
+    agent: Option<Extension<TlsClientIdentity>>,
     bytes: Bytes,
-) -> Result<Signed<VoucherArtifact>, ServerError> {
+) -> Result<Response, ServerError> {
     event!(Level::DEBUG, "Headers: {:#?}", headers);
 
     event!(Level::INFO, "Received requestvoucher request");
@@ -71,9 +80,11 @@ pub async fn handle_requestvoucher(
 
     let headers = decoded.headers();
 
-    let pledge_idevid_cert = headers
+    let idevid_chain = headers
         .x509_certificate_chain()
-        .ok_or(ServerError::BadRequest)?
+        .ok_or(ServerError::BadRequest)?;
+
+    let pledge_idevid_cert = idevid_chain
         .get(0)
         .ok_or(ServerError::BadRequestWithReason(
             "Can not get x509 certificate chain from header set".to_string(),
@@ -123,6 +134,98 @@ pub async fn handle_requestvoucher(
         "PVR Serial Number matches serial number in pledge certificate!"
     );
 
+    // With mTLS the agent is known from its client certificate, otherwise we trust the configured one
+    let agent_sign_cert = match TlsClientIdentity::certificate_of(agent) {
+        Some(certificate) => openssl::x509::X509::from_der(&certificate).map_err(|_| {
+            ServerError::BadRequestWithReason(
+                "Can not parse registrar-agent client certificate".to_string(),
+            )
+        })?,
+        None => state.config.reg_agt_ee_cert.clone(),
+    };
+
+    ProximityVerifier {
+        agent: &agent_sign_cert,
+        registrar_certificates: [
+            &state.config.registrar_certificate,
+            &state.config.ca_certificate,
+        ],
+        max_age: state.config.config.agent_signed_data_max_age,
+    }
+    .verify(
+        &pvr_vra,
+        token_type.signature_type().get_sv::<AgentSignedData>()?,
+        chrono::Utc::now(),
+    )?;
+
+    let idevid = IdevidIdentity::from_headers(headers)?;
+    let masa = state.masas.resolve(&idevid, &idevid_chain)?;
+    event!(
+        Level::INFO,
+        "Routing voucher request to MASA at {} (vendor {:?})",
+        masa.url,
+        masa.vendor
+    );
+
+    // only genuine pledges forwarded by a nearby agent get an onboarding record
+    state
+        .storage
+        .record_event(
+            &pvr_signature_pledge_serial_number,
+            PledgeEvent::PvrReceived,
+        )
+        .await?;
+
+    let device = DeviceInfo {
+        serial: pvr_signature_pledge_serial_number.clone(),
+        manufacturer: idevid.manufacturer,
+        masa_uri: Some(masa.url.clone()),
+    };
+    let decision = state.policy.evaluate(&device);
+    match decision.action {
+        PolicyAction::Allow => {}
+        PolicyAction::Deny => return Err(ServerError::Forbidden(decision.reason)),
+        PolicyAction::Review => {
+            // RFC 8995 5.6: the agent retries the same PVR until an operator decided
+            let approval = state
+                .storage
+                .request_approval(
+                    &device.serial,
+                    device.manufacturer,
+                    device.masa_uri,
+                    &decision.reason,
+                )
+                .await?;
+
+            match approval.status {
+                ApprovalStatus::Approved => {
+                    event!(Level::INFO, "Pledge was approved by an operator");
+                }
+                ApprovalStatus::Rejected => {
+                    return Err(ServerError::Forbidden(
+                        approval
+                            .reason
+                            .unwrap_or_else(|| "Rejected by operator".to_string()),
+                    ));
+                }
+                ApprovalStatus::Pending => {
+                    event!(
+                        Level::INFO,
+                        "Pledge is awaiting approval, asking agent to retry later"
+                    );
+                    return Ok((
+                        StatusCode::ACCEPTED,
+                        [(
+                            RETRY_AFTER,
+                            state.config.config.policy.retry_after.to_string(),
+                        )],
+                    )
+                        .into_response());
+                }
+            }
+        }
+    }
+
     event!(Level::DEBUG, "PVR VoucherRequestArtifact: {:#?}", pvr_vra);
 
     let mut rvr_vra = VoucherRequestArtifact::default();
@@ -133,15 +236,15 @@ pub async fn handle_requestvoucher(
     rvr_vra.details.assertion = pvr_vra.details.assertion;
     rvr_vra.details.prior_signed_voucher_request = Some(bytes.to_vec());
     rvr_vra.details.serial_number = pvr_vra.details.serial_number;
-    rvr_vra.details.agent_sign_cert = Some(vec![(state.config.reg_agt_ee_cert.clone().into())]);
-    // In this implementation, we pin the registrar cert from the PVR
+    rvr_vra.details.agent_sign_cert = Some(vec![agent_sign_cert.into()]);
+    // Verified to be ours above
     rvr_vra.details.agent_provided_proximity_registrar_cert =
         pvr_vra.details.agent_provided_proximity_registrar_cert;
 
     let requested_token_type = VoucherTokenType::from_content_type(accept);
 
     let rvr = RegistrarVoucherRequestResponse::new(
-        rvr_vra,
+        rvr_vra.clone(),
         [state.config.registrar_certificate.clone()],
         requested_token_type.clone(),
     );
@@ -159,9 +262,81 @@ pub async fn handle_requestvoucher(
         signeable_payload::signeable::signing_context::BasicSigningContext::new(),
     )?;
 
-    event!(Level::INFO, "Sending raw signed RVR to MASA");
-    let issued_voucher =
-        client::get_voucher_from_masa(&state.config, signed_rvr, &state.client).await?;
+    // Sites that never reach the MASA were given vouchers ahead of time
+    let imported_voucher = state
+        .imported_vouchers
+        .get(&pvr_signature_pledge_serial_number, &requested_token_type)
+        .await?;
+    let imported = imported_voucher.is_some();
+    let issued_voucher = match imported_voucher {
+        Some(voucher) => voucher,
+        None => {
+            event!(Level::INFO, "Sending raw signed RVR to MASA");
+            state
+                .storage
+                .record_event(&pvr_signature_pledge_serial_number, PledgeEvent::RvrSent)
+                .await?;
+            client::get_voucher_from_masa(&masa, &signed_rvr).await?
+        }
+    };
+    let voucher = VoucherValidator {
+        trust_anchor: &masa.voucher_ca,
+        registrar_certificates: [
+            &state.config.registrar_certificate,
+            &state.config.ca_certificate,
+        ],
+        allow_nonceless: imported,
+    }
+    .validate(
+        issued_voucher.clone(),
+        requested_token_type
+            .signature_type()
+            .get_sv::<VoucherArtifact>()?,
+        &rvr_vra,
+        chrono::Utc::now(),
+    )?;
+
+    if imported && state.config.config.policy.audit_log.is_enabled() {
+        event!(
+            Level::WARN,
+            "Not checking the audit log of an imported voucher, the MASA is not contacted"
+        );
+    } else if state.config.config.policy.audit_log.is_enabled() {
+        let audit_log = client::get_audit_log_from_masa(&masa, &signed_rvr).await?;
+        let own_domain_ids = [
+            domain_id(&state.config.registrar_certificate)?,
+            domain_id(&state.config.ca_certificate)?,
+        ];
+        let decision = state.policy.evaluate_audit_log(
+            &voucher.details.serial_number,
+            &audit_log,
+            &own_domain_ids,
+        );
+        if decision.action == PolicyAction::Deny {
+            return Err(ServerError::Forbidden(decision.reason));
+        }
+    }
+
+    state
+        .storage
+        .record_event(
+            &pvr_signature_pledge_serial_number,
+            PledgeEvent::VoucherReceived,
+        )
+        .await?;
+
+    state
+        .storage
+        .record_voucher(VoucherRecord {
+            serial_number: voucher.details.serial_number,
+            masa_url: masa.url.clone(),
+            content_type: accept.to_string(),
+            voucher: issued_voucher.data(),
+            created_on: voucher.details.created_on,
+            expires_on: voucher.details.expires_on,
+            received_at: chrono::Utc::now(),
+        })
+        .await?;
 
     let reg_cert_x509: X509 = state.config.registrar_certificate.clone().into();
     let mut headers = HeaderSet::new();
@@ -180,5 +355,5 @@ pub async fn handle_requestvoucher(
     )?;
     event!(Level::INFO, "Returning issued voucher");
 
-    Ok(issued_voucher)
+    Ok(issued_voucher.into_response())
 }
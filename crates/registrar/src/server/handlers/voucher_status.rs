@@ -11,7 +11,7 @@ use common::server_error::ServerError;
 use signeable_payload::signeable::raw_signed::RawSigned;
 use tracing::{event, Level};
 
-use crate::{client, server::server::ServerState};
+use crate::{client, server::server::ServerState, storage::PledgeEvent};
 
 // We don't trust client's to supply just any base64 encoded data, so we parse it.
 #[tracing::instrument(target = "Registrar", skip(state, headers))]
@@ -46,5 +46,21 @@ pub async fn handle_voucher_status(
 
     event!(Level::INFO, "Voucher Status: {:#?}", status);
 
+    let idevid = state.masas.signing_idevid(decoded.headers())?;
+    let serial_number = idevid.serial.ok_or(ServerError::BadRequestWithReason(
+        "Can not get pledge serial number from voucher status signature".to_string(),
+    ))?;
+
+    state
+        .storage
+        .record_event(
+            &serial_number,
+            PledgeEvent::VoucherStatus {
+                status: status.status,
+                reason: status.reason.clone(),
+            },
+        )
+        .await?;
+
     Ok(())
 }
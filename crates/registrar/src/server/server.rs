@@ -1,26 +1,52 @@
-use crate::parsed_config::ParsedConfig;
+use crate::{
+    imported::ImportedVouchers,
+    masa::MasaRouter,
+    parsed_config::ParsedConfig,
+    policy::Policy,
+    storage::{RegistrarStorage, SqliteStorage},
+};
 use axum::Router;
-use common::error::AppError;
-use reqwest::Client;
+use common::{
+    error::AppError,
+    middleware::{require_admin_token, AdminToken},
+};
 use tower_http::trace::TraceLayer;
 
-use super::handlers::brski_routes;
+use super::handlers::{admin_routes, brski_routes};
 
 #[derive(Clone)]
 pub struct ServerState {
     pub config: ParsedConfig,
-    pub client: reqwest::Client,
+    pub masas: MasaRouter,
+    pub storage: Box<dyn RegistrarStorage>,
+    pub policy: Policy,
+    pub imported_vouchers: ImportedVouchers,
 }
 
 pub async fn get_app(config: &ParsedConfig) -> anyhow::Result<Router<()>, AppError> {
-    let client = Client::new();
+    let masas = MasaRouter::from_config(config)?;
+
+    let storage = SqliteStorage::open(config.config.database.relative())?;
+    let policy = Policy::from_config(&config.config.policy)?;
 
     let state = ServerState {
         config: config.clone(),
-        client: client.clone(),
+        masas,
+        storage: Box::new(storage),
+        policy,
+        imported_vouchers: ImportedVouchers::from_config(config),
     };
 
-    let routes = Router::new().nest("/.well-known/brski", brski_routes());
+    let admin_token = AdminToken(config.config.admin_token.clone());
+    let routes = Router::new()
+        .nest("/.well-known/brski", brski_routes())
+        .nest(
+            "/admin",
+            admin_routes().route_layer(axum::middleware::from_fn_with_state(
+                admin_token,
+                require_admin_token,
+            )),
+        );
 
     let app = routes
         .with_state(state)
@@ -1,26 +1,139 @@
+use std::{net::IpAddr, str::FromStr};
+
+use anyhow::anyhow;
+use cli::config::{
+    CertProfile, CertProfileRule, KeyUsageFlag, RegistrarConfig, SanKind, SanTemplate,
+};
+use common::server_error::ServerError;
 use openssl::{
-    asn1::Asn1Time,
+    asn1::{Asn1Object, Asn1OctetString, Asn1Time},
     bn::{BigNum, MsbOption},
-    error::ErrorStack,
     hash::MessageDigest,
-    pkey::{PKey, PKeyRef, Private},
-    rsa::Rsa,
+    nid::Nid,
+    pkey::{Id, PKeyRef, Private, Public},
     x509::{
         extension::{
-            AuthorityKeyIdentifier, BasicConstraints, KeyUsage, SubjectAlternativeName,
-            SubjectKeyIdentifier,
+            AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage,
+            SubjectAlternativeName, SubjectKeyIdentifier,
         },
-        X509NameBuilder, X509Ref, X509Req, X509ReqBuilder, X509VerifyResult, X509,
+        X509Extension, X509Ref, X509ReqRef, X509,
+    },
+};
+use tracing::{event, Level};
+use x509_cert::{
+    der::{
+        asn1::{Ia5String, ObjectIdentifier},
+        oid::AssociatedOid,
+        Decode, Encode,
+    },
+    ext::pkix::{
+        certpolicy::PolicyInformation,
+        crl::dp::DistributionPoint,
+        name::{DistributionPointName, GeneralName},
+        AccessDescription, AuthorityInfoAccessSyntax, CertificatePolicies, CrlDistributionPoints,
+        SubjectAltName,
     },
+    request::{CertReq, ExtensionReq},
 };
 
-pub fn mk_ca_signed_cert(
+use crate::{idevid::IdevidIdentity, policy::glob_matches};
+
+/// id-ad-ocsp, RFC 5280
+const ID_AD_OCSP: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.48.1");
+
+/// Smallest RSA modulus we are willing to certify
+const MIN_RSA_BITS: u32 = 2048;
+
+/// A SAN entry taken over from the CSR into the LDevID
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SanEntry {
+    Dns(String),
+    Uri(String),
+    Email(String),
+    Ip(IpAddr),
+}
+
+/// Returns the name and profile the LDevID of the pledge with `idevid` is issued with.
+///
+/// The first cert profile rule matching the IDevID decides, the default profile is used if none matches.
+pub(crate) fn select_cert_profile<'a>(
+    config: &'a RegistrarConfig,
+    idevid: &IdevidIdentity,
+) -> Option<(&'a str, &'a CertProfile)> {
+    let name = config
+        .cert_profile_rules
+        .iter()
+        .find(|rule| rule_matches(rule, idevid))
+        .map(|rule| rule.profile.as_str())
+        .unwrap_or(config.default_cert_profile.as_str());
+
+    config.cert_profile(name)
+}
+
+fn rule_matches(rule: &CertProfileRule, idevid: &IdevidIdentity) -> bool {
+    let matches = |pattern: &Option<String>, value: &Option<String>| match pattern {
+        Some(pattern) => value
+            .as_deref()
+            .is_some_and(|value| glob_matches(pattern, value)),
+        None => true,
+    };
+
+    matches(&rule.manufacturer, &idevid.manufacturer)
+        && matches(&rule.hw_type, &idevid.hw_type)
+        && matches(&rule.serial, &idevid.serial)
+}
+
+/// Issues an LDevID for the key in `req`, signed by the registrar CA.
+///
+/// The CSR has to be self-signed with the key it carries (proof-of-possession) and must hold an EC or RSA key.
+/// The subject is copied from the CSR and has to carry the serialNumber of the pledge's IDevID,
+/// everything else follows `profile`.
+/// SANs requested in the CSR are only taken over if the profile allows it, SAN types we can not vouch for are rejected.
+pub(crate) fn mk_ca_signed_cert(
     ca_cert: &X509Ref,
-    ca_key_pair: &PKeyRef<Private>,
-    req: &X509Req,
-) -> Result<(X509, PKey<Private>), ErrorStack> {
-    let rsa = Rsa::generate(2048)?;
-    let key_pair = PKey::from_rsa(rsa)?;
+    ca_key: &PKeyRef<Private>,
+    req: &X509ReqRef,
+    profile: &CertProfile,
+    idevid: &IdevidIdentity,
+) -> Result<X509, ServerError> {
+    let req_pubkey = req.public_key()?;
+
+    if !req.verify(&req_pubkey)? {
+        return Err(ServerError::BadRequestWithReason(
+            "CSR signature does not verify with the contained public key".to_string(),
+        ));
+    }
+    event!(Level::DEBUG, "Verified proof-of-possession of CSR");
+
+    validate_key(&req_pubkey)?;
+
+    if req.subject_name().entries().next().is_none() {
+        return Err(ServerError::BadRequestWithReason(
+            "CSR subject is empty".to_string(),
+        ));
+    }
+
+    let csr_serial = match req.subject_name().entries_by_nid(Nid::SERIALNUMBER).next() {
+        Some(entry) => Some(entry.data().as_utf8()?.to_string()),
+        None => None,
+    };
+    if csr_serial != idevid.serial {
+        return Err(ServerError::BadRequestWithReason(format!(
+            "CSR subject serialNumber {:?} does not match the IDevID serialNumber {:?}",
+            csr_serial, idevid.serial
+        )));
+    }
+
+    let mut sans = if profile.copy_csr_sans {
+        requested_sans(req)?
+    } else {
+        vec![]
+    };
+    event!(Level::DEBUG, "Requested SANs: {:?}", sans);
+
+    for template in &profile.san_templates {
+        sans.push(templated_san(template, idevid)?);
+    }
 
     let mut cert_builder = X509::builder()?;
     cert_builder.set_version(2)?;
@@ -32,22 +145,44 @@ pub fn mk_ca_signed_cert(
     cert_builder.set_serial_number(&serial_number)?;
     cert_builder.set_subject_name(req.subject_name())?;
     cert_builder.set_issuer_name(ca_cert.subject_name())?;
-    cert_builder.set_pubkey(&key_pair)?;
+    cert_builder.set_pubkey(&req_pubkey)?;
     let not_before = Asn1Time::days_from_now(0)?;
     cert_builder.set_not_before(&not_before)?;
-    let not_after = Asn1Time::days_from_now(365)?;
+    let not_after = Asn1Time::days_from_now(profile.validity_days)?;
     cert_builder.set_not_after(&not_after)?;
 
-    cert_builder.append_extension(BasicConstraints::new().build()?)?;
+    cert_builder.append_extension(BasicConstraints::new().critical().build()?)?;
+
+    let mut key_usage = KeyUsage::new();
+    key_usage.critical();
+    match &profile.key_usage {
+        Some(flags) => {
+            for flag in flags {
+                match flag {
+                    KeyUsageFlag::DigitalSignature => key_usage.digital_signature(),
+                    KeyUsageFlag::NonRepudiation => key_usage.non_repudiation(),
+                    KeyUsageFlag::KeyEncipherment => key_usage.key_encipherment(),
+                    KeyUsageFlag::DataEncipherment => key_usage.data_encipherment(),
+                    KeyUsageFlag::KeyAgreement => key_usage.key_agreement(),
+                };
+            }
+        }
+        None => {
+            key_usage.digital_signature();
+            if req_pubkey.id() == Id::RSA {
+                key_usage.key_encipherment();
+            }
+        }
+    }
+    cert_builder.append_extension(key_usage.build()?)?;
 
-    cert_builder.append_extension(
-        KeyUsage::new()
-            .critical()
-            .non_repudiation()
-            .digital_signature()
-            .key_encipherment()
-            .build()?,
-    )?;
+    if !profile.extended_key_usage.is_empty() {
+        let mut extended_key_usage = ExtendedKeyUsage::new();
+        for usage in &profile.extended_key_usage {
+            extended_key_usage.other(usage);
+        }
+        cert_builder.append_extension(extended_key_usage.build()?)?;
+    }
 
     let subject_key_identifier =
         SubjectKeyIdentifier::new().build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
@@ -59,14 +194,405 @@ pub fn mk_ca_signed_cert(
         .build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
     cert_builder.append_extension(auth_key_identifier)?;
 
-    let subject_alt_name = SubjectAlternativeName::new()
-        .dns("*.example.com")
-        .dns("hello.com")
-        .build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
-    cert_builder.append_extension(subject_alt_name)?;
+    if !sans.is_empty() {
+        let mut subject_alt_name = SubjectAlternativeName::new();
+        for san in &sans {
+            match san {
+                SanEntry::Dns(dns) => subject_alt_name.dns(dns),
+                SanEntry::Uri(uri) => subject_alt_name.uri(uri),
+                SanEntry::Email(email) => subject_alt_name.email(email),
+                SanEntry::Ip(ip) => subject_alt_name.ip(&ip.to_string()),
+            };
+        }
+        let subject_alt_name =
+            subject_alt_name.build(&cert_builder.x509v3_context(Some(ca_cert), None))?;
+        cert_builder.append_extension(subject_alt_name)?;
+    }
 
-    cert_builder.sign(ca_key_pair, MessageDigest::sha256())?;
+    if !profile.policy_oids.is_empty() {
+        let policies = profile
+            .policy_oids
+            .iter()
+            .map(|oid| {
+                Ok(PolicyInformation {
+                    policy_identifier: parse_oid(oid)?,
+                    policy_qualifiers: None,
+                })
+            })
+            .collect::<Result<Vec<_>, ServerError>>()?;
+        cert_builder.append_extension(der_extension(CertificatePolicies(policies))?)?;
+    }
+
+    if !profile.crl_urls.is_empty() {
+        let distribution_points = profile
+            .crl_urls
+            .iter()
+            .map(|url| {
+                Ok(DistributionPoint {
+                    distribution_point: Some(DistributionPointName::FullName(vec![uri(url)?])),
+                    reasons: None,
+                    crl_issuer: None,
+                })
+            })
+            .collect::<Result<Vec<_>, ServerError>>()?;
+        cert_builder
+            .append_extension(der_extension(CrlDistributionPoints(distribution_points))?)?;
+    }
+
+    if !profile.ocsp_urls.is_empty() {
+        let access_descriptions = profile
+            .ocsp_urls
+            .iter()
+            .map(|url| {
+                Ok(AccessDescription {
+                    access_method: ID_AD_OCSP,
+                    access_location: uri(url)?,
+                })
+            })
+            .collect::<Result<Vec<_>, ServerError>>()?;
+        cert_builder.append_extension(der_extension(AuthorityInfoAccessSyntax(
+            access_descriptions,
+        ))?)?;
+    }
+
+    cert_builder.sign(ca_key, MessageDigest::sha256())?;
     let cert = cert_builder.build();
 
-    Ok((cert, key_pair))
+    Ok(cert)
+}
+
+fn templated_san(template: &SanTemplate, idevid: &IdevidIdentity) -> Result<SanEntry, ServerError> {
+    let value = idevid.render(&template.value)?;
+    match template.kind {
+        SanKind::Dns => Ok(SanEntry::Dns(value)),
+        SanKind::Uri => Ok(SanEntry::Uri(value)),
+        SanKind::Email => Ok(SanEntry::Email(value)),
+        SanKind::Ip => IpAddr::from_str(&value).map(SanEntry::Ip).map_err(|_| {
+            ServerError::InternalError(anyhow!("SAN template renders to invalid IP: {}", value))
+        }),
+    }
+}
+
+fn parse_oid(oid: &str) -> Result<ObjectIdentifier, ServerError> {
+    ObjectIdentifier::new(oid).map_err(|e| {
+        ServerError::InternalError(anyhow!("Invalid OID {} in cert profile: {}", oid, e))
+    })
+}
+
+fn uri(url: &str) -> Result<GeneralName, ServerError> {
+    Ia5String::new(url)
+        .map(GeneralName::UniformResourceIdentifier)
+        .map_err(|e| {
+            ServerError::InternalError(anyhow!("Invalid URL {} in cert profile: {}", url, e))
+        })
+}
+
+/// Encodes an extension openssl has no builder for
+fn der_extension<T: AssociatedOid + Encode>(value: T) -> Result<X509Extension, ServerError> {
+    let der = value
+        .to_der()
+        .map_err(|e| ServerError::InternalError(anyhow!(e)))?;
+    let oid = Asn1Object::from_str(&T::OID.to_string())?;
+    let der = Asn1OctetString::new_from_bytes(&der)?;
+
+    Ok(X509Extension::new_from_der(&oid, false, &der)?)
+}
+
+fn validate_key(pubkey: &PKeyRef<Public>) -> Result<(), ServerError> {
+    match pubkey.id() {
+        Id::EC => Ok(()),
+        Id::RSA if pubkey.bits() >= MIN_RSA_BITS => Ok(()),
+        Id::RSA => Err(ServerError::BadRequestWithReason(format!(
+            "RSA key in CSR is too small: {} bits, at least {} required",
+            pubkey.bits(),
+            MIN_RSA_BITS
+        ))),
+        _ => Err(ServerError::BadRequestWithReason(
+            "CSR contains an unsupported key type, only EC and RSA are supported".to_string(),
+        )),
+    }
+}
+
+/// Reads the SANs from the extensionRequest attribute of the CSR
+fn requested_sans(req: &X509ReqRef) -> Result<Vec<SanEntry>, ServerError> {
+    let malformed = |e: x509_cert::der::Error| {
+        ServerError::BadRequestWithReason(format!("Malformed CSR: {}", e))
+    };
+
+    let cert_req = CertReq::from_der(&req.to_der()?).map_err(malformed)?;
+
+    let mut sans = vec![];
+    for attribute in cert_req.info.attributes.iter() {
+        if attribute.oid != ExtensionReq::OID {
+            continue;
+        }
+
+        for value in attribute.values.iter() {
+            let extensions =
+                ExtensionReq::from_der(&value.to_der().map_err(malformed)?).map_err(malformed)?;
+
+            for extension in extensions.0.iter() {
+                if extension.extn_id != SubjectAltName::OID {
+                    continue;
+                }
+
+                let san =
+                    SubjectAltName::from_der(extension.extn_value.as_bytes()).map_err(malformed)?;
+                for name in san.0 {
+                    sans.push(san_entry(name)?);
+                }
+            }
+        }
+    }
+
+    Ok(sans)
+}
+
+fn san_entry(name: GeneralName) -> Result<SanEntry, ServerError> {
+    match name {
+        GeneralName::DnsName(dns) => Ok(SanEntry::Dns(dns.to_string())),
+        GeneralName::UniformResourceIdentifier(uri) => Ok(SanEntry::Uri(uri.to_string())),
+        GeneralName::Rfc822Name(email) => Ok(SanEntry::Email(email.to_string())),
+        GeneralName::IpAddress(ip) => {
+            let ip = match ip.as_bytes().len() {
+                4 => IpAddr::from(<[u8; 4]>::try_from(ip.as_bytes()).unwrap()),
+                16 => IpAddr::from(<[u8; 16]>::try_from(ip.as_bytes()).unwrap()),
+                _ => {
+                    return Err(ServerError::BadRequestWithReason(
+                        "Malformed IP address SAN in CSR".to_string(),
+                    ))
+                }
+            };
+            Ok(SanEntry::Ip(ip))
+        }
+        other => Err(ServerError::BadRequestWithReason(format!(
+            "Unsupported SAN in CSR: {:?}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{
+        ec::{EcGroup, EcKey},
+        pkey::PKey,
+        rsa::Rsa,
+        stack::Stack,
+        x509::{X509NameBuilder, X509Req, X509ReqBuilder},
+    };
+
+    use super::*;
+
+    fn idevid() -> IdevidIdentity {
+        IdevidIdentity {
+            serial: Some("0123456789".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn ca() -> (X509, PKey<Private>) {
+        let key = ec_key();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "Registrar CA").unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+        (builder.build(), key)
+    }
+
+    fn csr(pubkey: &PKeyRef<Private>, signing_key: &PKeyRef<Private>) -> X509Req {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "pledge").unwrap();
+        name.append_entry_by_text("serialNumber", "0123456789")
+            .unwrap();
+
+        let mut builder = X509ReqBuilder::new().unwrap();
+        builder.set_subject_name(&name.build()).unwrap();
+        builder.set_pubkey(pubkey).unwrap();
+
+        let san = SubjectAlternativeName::new()
+            .dns("pledge.example.com")
+            .ip("192.0.2.1")
+            .build(&builder.x509v3_context(None))
+            .unwrap();
+        let mut extensions = Stack::new().unwrap();
+        extensions.push(san).unwrap();
+        builder.add_extensions(&extensions).unwrap();
+
+        builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn it_issues_over_the_csr_key() {
+        let (ca_cert, ca_key) = ca();
+
+        for key in [
+            ec_key(),
+            PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+        ] {
+            let req = csr(&key, &key);
+            let profile = CertProfile {
+                copy_csr_sans: true,
+                ..Default::default()
+            };
+            let cert = mk_ca_signed_cert(&ca_cert, &ca_key, &req, &profile, &idevid()).unwrap();
+
+            assert!(cert.public_key().unwrap().public_eq(&key));
+            assert!(cert.verify(&ca_key).unwrap());
+            assert_eq!(
+                cert.subject_name().to_der().unwrap(),
+                req.subject_name().to_der().unwrap()
+            );
+
+            let sans = cert.subject_alt_names().unwrap();
+            assert_eq!(sans.get(0).unwrap().dnsname(), Some("pledge.example.com"));
+            assert_eq!(sans.get(1).unwrap().ipaddress(), Some(&[192, 0, 2, 1][..]));
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_csr_without_proof_of_possession() {
+        let (ca_cert, ca_key) = ca();
+
+        let req = csr(&ec_key(), &ec_key());
+
+        assert!(matches!(
+            mk_ca_signed_cert(&ca_cert, &ca_key, &req, &CertProfile::default(), &idevid(),),
+            Err(ServerError::BadRequestWithReason(_))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_a_csr_naming_another_pledge() {
+        let (ca_cert, ca_key) = ca();
+        let key = ec_key();
+        let req = csr(&key, &key);
+
+        for idevid in [
+            IdevidIdentity {
+                serial: Some("9876543210".to_string()),
+                ..Default::default()
+            },
+            IdevidIdentity::default(),
+        ] {
+            assert!(matches!(
+                mk_ca_signed_cert(&ca_cert, &ca_key, &req, &CertProfile::default(), &idevid),
+                Err(ServerError::BadRequestWithReason(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn it_applies_the_profile() {
+        let (ca_cert, ca_key) = ca();
+        let key = ec_key();
+        let req = csr(&key, &key);
+
+        let profile = CertProfile {
+            validity_days: 30,
+            copy_csr_sans: false,
+            san_templates: vec![SanTemplate {
+                kind: SanKind::Dns,
+                value: "{serial}.pledges.example.com".to_string(),
+            }],
+            extended_key_usage: vec!["clientAuth".to_string(), "serverAuth".to_string()],
+            policy_oids: vec!["1.3.6.1.4.1.0.1".to_string()],
+            crl_urls: vec!["http://crl.example.com/ca.crl".to_string()],
+            ocsp_urls: vec!["http://ocsp.example.com".to_string()],
+            ..Default::default()
+        };
+        let cert = mk_ca_signed_cert(&ca_cert, &ca_key, &req, &profile, &idevid()).unwrap();
+
+        let sans = cert.subject_alt_names().unwrap();
+        assert_eq!(sans.len(), 1);
+        assert_eq!(
+            sans.get(0).unwrap().dnsname(),
+            Some("0123456789.pledges.example.com")
+        );
+        assert_eq!(cert.not_before().diff(cert.not_after()).unwrap().days, 30);
+
+        let text = String::from_utf8(cert.to_text().unwrap()).unwrap();
+        assert!(text.contains("TLS Web Client Authentication"));
+        assert!(text.contains("TLS Web Server Authentication"));
+        assert!(text.contains("1.3.6.1.4.1.0.1"));
+        assert!(text.contains("http://crl.example.com/ca.crl"));
+        assert!(text.contains("OCSP - URI:http://ocsp.example.com"));
+    }
+
+    #[test]
+    fn it_selects_the_profile_by_idevid() {
+        let (ca_cert, ca_key) = ca();
+        let key = ec_key();
+        let req = csr(&key, &key);
+
+        let config = RegistrarConfig {
+            cert_profiles: [
+                ("ldevid".to_string(), CertProfile::default()),
+                (
+                    "acme".to_string(),
+                    CertProfile {
+                        validity_days: 30,
+                        extended_key_usage: vec!["serverAuth".to_string()],
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+            cert_profile_rules: vec![CertProfileRule {
+                profile: "acme".to_string(),
+                manufacturer: Some("ACME *".to_string()),
+                hw_type: None,
+                serial: None,
+            }],
+            default_cert_profile: "ldevid".to_string(),
+            ..Default::default()
+        };
+
+        let issue = |manufacturer: &str| {
+            let idevid = IdevidIdentity {
+                serial: Some("0123456789".to_string()),
+                manufacturer: Some(manufacturer.to_string()),
+                ..Default::default()
+            };
+            let (name, profile) = select_cert_profile(&config, &idevid).unwrap();
+            let cert = mk_ca_signed_cert(&ca_cert, &ca_key, &req, profile, &idevid).unwrap();
+            (name, cert)
+        };
+
+        let (name, cert) = issue("ACME Corp");
+        assert_eq!(name, "acme");
+        assert_eq!(cert.not_before().diff(cert.not_after()).unwrap().days, 30);
+        let text = String::from_utf8(cert.to_text().unwrap()).unwrap();
+        assert!(text.contains("TLS Web Server Authentication"));
+        assert!(!text.contains("TLS Web Client Authentication"));
+
+        let (name, cert) = issue("Other Inc");
+        assert_eq!(name, "ldevid");
+        assert!(cert.subject_alt_names().is_none());
+        assert_eq!(cert.not_before().diff(cert.not_after()).unwrap().days, 365);
+        let text = String::from_utf8(cert.to_text().unwrap()).unwrap();
+        assert!(text.contains("TLS Web Client Authentication"));
+    }
 }
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
+
+mod sqlite;
+
+pub(crate) use sqlite::SqliteStorage;
+
+/// Something that happened to a pledge during onboarding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PledgeEvent {
+    /// A PVR of the pledge passed verification
+    PvrReceived,
+    /// The RVR built from the PVR was sent to the MASA
+    RvrSent,
+    /// The MASA issued a voucher
+    VoucherReceived,
+    VoucherStatus {
+        status: bool,
+        reason: Option<String>,
+    },
+    /// An LDevID was issued, `serial` is the hex-encoded certificate serial number
+    LdevidIssued { serial: String },
+    EnrollStatus {
+        status: bool,
+        reason: Option<String>,
+    },
+    /// The pledge answered a status query of an agent, `status` is e.g. `enroll-success`
+    PledgeStatus {
+        status: String,
+        reason: Option<String>,
+    },
+}
+
+/// Everything the registrar knows about a pledge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PledgeRecord {
+    pub(crate) serial_number: String,
+    pub(crate) first_seen: DateTime<Utc>,
+    pub(crate) last_updated: DateTime<Utc>,
+    pub(crate) pvr_received_at: Option<DateTime<Utc>>,
+    pub(crate) rvr_sent_at: Option<DateTime<Utc>>,
+    pub(crate) voucher_received_at: Option<DateTime<Utc>>,
+    pub(crate) voucher_status: Option<bool>,
+    pub(crate) voucher_status_reason: Option<String>,
+    pub(crate) voucher_status_at: Option<DateTime<Utc>>,
+    pub(crate) ldevid_serial: Option<String>,
+    pub(crate) ldevid_issued_at: Option<DateTime<Utc>>,
+    pub(crate) enroll_status: Option<bool>,
+    pub(crate) enroll_status_reason: Option<String>,
+    pub(crate) enroll_status_at: Option<DateTime<Utc>>,
+    pub(crate) pledge_status: Option<String>,
+    pub(crate) pledge_status_reason: Option<String>,
+    pub(crate) pledge_status_at: Option<DateTime<Utc>>,
+}
+
+/// A voucher the registrar validated and relayed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct VoucherRecord {
+    pub(crate) serial_number: String,
+    /// Base URL of the MASA that issued the voucher
+    pub(crate) masa_url: String,
+    pub(crate) content_type: String,
+    /// The voucher as signed by the MASA, before the registrar added its signature
+    pub(crate) voucher: Vec<u8>,
+    pub(crate) created_on: Option<DateTime<Utc>>,
+    pub(crate) expires_on: Option<DateTime<Utc>>,
+    pub(crate) received_at: DateTime<Utc>,
+}
+
+/// Where a voucher request held for manual approval stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A pledge whose voucher request waits for, or got, an operator's decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ApprovalRecord {
+    pub(crate) serial_number: String,
+    pub(crate) manufacturer: Option<String>,
+    pub(crate) masa_uri: Option<String>,
+    /// Why the policy asked for a review
+    pub(crate) policy_reason: String,
+    pub(crate) status: ApprovalStatus,
+    /// Reason given by the operator
+    pub(crate) reason: Option<String>,
+    pub(crate) requested_at: DateTime<Utc>,
+    /// When the agent last asked for a voucher for the pledge
+    pub(crate) last_requested_at: DateTime<Utc>,
+    pub(crate) decided_at: Option<DateTime<Utc>>,
+}
+
+/// Persistence of the registrar's per-pledge state
+#[async_trait::async_trait]
+pub(crate) trait RegistrarStorage: Send + Sync + DynClone {
+    /// Records `event` for the pledge, creating its record if this is the first event
+    async fn record_event(&self, serial_number: &str, event: PledgeEvent) -> anyhow::Result<()>;
+
+    async fn get_pledge(&self, serial_number: &str) -> anyhow::Result<Option<PledgeRecord>>;
+
+    async fn list_pledges(&self) -> anyhow::Result<Vec<PledgeRecord>>;
+
+    /// Queues the pledge for manual approval unless it already is, returning the current record
+    async fn request_approval(
+        &self,
+        serial_number: &str,
+        manufacturer: Option<String>,
+        masa_uri: Option<String>,
+        policy_reason: &str,
+    ) -> anyhow::Result<ApprovalRecord>;
+
+    /// Approves or rejects a queued pledge, returns `None` if the pledge was never queued
+    async fn decide_approval(
+        &self,
+        serial_number: &str,
+        approved: bool,
+        reason: Option<String>,
+    ) -> anyhow::Result<Option<ApprovalRecord>>;
+
+    async fn list_approvals(&self) -> anyhow::Result<Vec<ApprovalRecord>>;
+
+    /// Keeps a validated voucher, all vouchers ever issued for a pledge are retained
+    async fn record_voucher(&self, voucher: VoucherRecord) -> anyhow::Result<()>;
+
+    /// The voucher received last for the pledge
+    async fn latest_voucher(&self, serial_number: &str) -> anyhow::Result<Option<VoucherRecord>>;
+
+    /// Forgets the onboarding state and approval of the pledge, so another domain or this one can onboard it afresh.
+    /// Its vouchers are retained. Returns the state the pledge had.
+    async fn release_pledge(&self, serial_number: &str) -> anyhow::Result<Option<PledgeRecord>>;
+}
+
+impl Clone for Box<dyn RegistrarStorage> {
+    fn clone(&self) -> Self {
+        dyn_clone::clone_box(&**self)
+    }
+}
@@ -0,0 +1,706 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use tracing::{event, Level};
+
+use super::{
+    ApprovalRecord, ApprovalStatus, PledgeEvent, PledgeRecord, RegistrarStorage, VoucherRecord,
+};
+
+/// Schema changes in the order they were made, `user_version` counts the ones applied to a database.
+///
+/// Databases created before the schema was versioned are at version 0 but may already hold the tables
+/// of any earlier schema, so these have to tolerate existing tables and columns.
+const MIGRATIONS: &[fn(&Transaction) -> rusqlite::Result<()>] = &[
+    |tx| {
+        tx.execute_batch(
+            "CREATE TABLE IF NOT EXISTS pledges (
+                serial_number TEXT PRIMARY KEY NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_updated TEXT NOT NULL,
+                pvr_received_at TEXT,
+                rvr_sent_at TEXT,
+                voucher_received_at TEXT,
+                voucher_status INTEGER,
+                voucher_status_reason TEXT,
+                voucher_status_at TEXT,
+                ldevid_serial TEXT,
+                ldevid_issued_at TEXT,
+                enroll_status INTEGER,
+                enroll_status_reason TEXT,
+                enroll_status_at TEXT
+            );",
+        )
+    },
+    |tx| {
+        tx.execute_batch(
+            "CREATE TABLE IF NOT EXISTS approvals (
+                serial_number TEXT PRIMARY KEY NOT NULL,
+                manufacturer TEXT,
+                masa_uri TEXT,
+                policy_reason TEXT NOT NULL,
+                status TEXT NOT NULL,
+                reason TEXT,
+                requested_at TEXT NOT NULL,
+                last_requested_at TEXT NOT NULL,
+                decided_at TEXT
+            );",
+        )
+    },
+    |tx| {
+        tx.execute_batch(
+            "CREATE TABLE IF NOT EXISTS vouchers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                serial_number TEXT NOT NULL,
+                masa_url TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                voucher BLOB NOT NULL,
+                created_on TEXT,
+                expires_on TEXT,
+                received_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS vouchers_serial_number ON vouchers (serial_number);",
+        )
+    },
+    |tx| {
+        add_column(tx, "pledges", "pledge_status", "TEXT")?;
+        add_column(tx, "pledges", "pledge_status_reason", "TEXT")?;
+        add_column(tx, "pledges", "pledge_status_at", "TEXT")
+    },
+];
+
+const COLUMNS: &str = "serial_number, first_seen, last_updated, pvr_received_at, rvr_sent_at, \
+    voucher_received_at, voucher_status, voucher_status_reason, voucher_status_at, ldevid_serial, \
+    ldevid_issued_at, enroll_status, enroll_status_reason, enroll_status_at, pledge_status, \
+    pledge_status_reason, pledge_status_at";
+
+const APPROVAL_COLUMNS: &str = "serial_number, manufacturer, masa_uri, policy_reason, status, \
+    reason, requested_at, last_requested_at, decided_at";
+
+const VOUCHER_COLUMNS: &str =
+    "serial_number, masa_url, content_type, voucher, created_on, expires_on, received_at";
+
+/// The default [RegistrarStorage], keeping everything in a single SQLite database
+#[derive(Clone)]
+pub(crate) struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating it and its parent directories if necessary
+    pub(crate) fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        event!(
+            Level::INFO,
+            "Opening registrar database at {}",
+            path.display()
+        );
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> anyhow::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> anyhow::Result<Self> {
+        migrate(&mut connection)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `f` on the connection without blocking the async runtime
+    async fn with_connection<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow!("Registrar database lock poisoned"))?;
+            Ok(f(&mut connection)?)
+        })
+        .await?
+    }
+}
+
+/// Brings the schema of the database up to date, each migration in its own transaction
+fn migrate(connection: &mut Connection) -> anyhow::Result<()> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(anyhow!(
+            "Registrar database has schema version {}, this registrar only knows up to {}",
+            version,
+            MIGRATIONS.len()
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        event!(
+            Level::INFO,
+            "Migrating registrar database to schema version {}",
+            index + 1
+        );
+        let tx = connection.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn add_column(tx: &Transaction, table: &str, column: &str, kind: &str) -> rusqlite::Result<()> {
+    let exists: bool = tx.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        tx.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, kind),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn pledge_record(row: &Row) -> rusqlite::Result<PledgeRecord> {
+    Ok(PledgeRecord {
+        serial_number: row.get(0)?,
+        first_seen: row.get(1)?,
+        last_updated: row.get(2)?,
+        pvr_received_at: row.get(3)?,
+        rvr_sent_at: row.get(4)?,
+        voucher_received_at: row.get(5)?,
+        voucher_status: row.get(6)?,
+        voucher_status_reason: row.get(7)?,
+        voucher_status_at: row.get(8)?,
+        ldevid_serial: row.get(9)?,
+        ldevid_issued_at: row.get(10)?,
+        enroll_status: row.get(11)?,
+        enroll_status_reason: row.get(12)?,
+        enroll_status_at: row.get(13)?,
+        pledge_status: row.get(14)?,
+        pledge_status_reason: row.get(15)?,
+        pledge_status_at: row.get(16)?,
+    })
+}
+
+fn approval_status_to_sql(status: ApprovalStatus) -> &'static str {
+    match status {
+        ApprovalStatus::Pending => "pending",
+        ApprovalStatus::Approved => "approved",
+        ApprovalStatus::Rejected => "rejected",
+    }
+}
+
+fn approval_record(row: &Row) -> rusqlite::Result<ApprovalRecord> {
+    let status: String = row.get(4)?;
+    let status = match status.as_str() {
+        "pending" => ApprovalStatus::Pending,
+        "approved" => ApprovalStatus::Approved,
+        "rejected" => ApprovalStatus::Rejected,
+        _ => {
+            return Err(rusqlite::Error::InvalidColumnType(
+                4,
+                "status".to_string(),
+                rusqlite::types::Type::Text,
+            ))
+        }
+    };
+
+    Ok(ApprovalRecord {
+        serial_number: row.get(0)?,
+        manufacturer: row.get(1)?,
+        masa_uri: row.get(2)?,
+        policy_reason: row.get(3)?,
+        status,
+        reason: row.get(5)?,
+        requested_at: row.get(6)?,
+        last_requested_at: row.get(7)?,
+        decided_at: row.get(8)?,
+    })
+}
+
+fn voucher_record(row: &Row) -> rusqlite::Result<VoucherRecord> {
+    Ok(VoucherRecord {
+        serial_number: row.get(0)?,
+        masa_url: row.get(1)?,
+        content_type: row.get(2)?,
+        voucher: row.get(3)?,
+        created_on: row.get(4)?,
+        expires_on: row.get(5)?,
+        received_at: row.get(6)?,
+    })
+}
+
+#[async_trait::async_trait]
+impl RegistrarStorage for SqliteStorage {
+    async fn record_event(&self, serial_number: &str, event: PledgeEvent) -> anyhow::Result<()> {
+        let serial_number = serial_number.to_string();
+        let now = Utc::now();
+
+        self.with_connection(move |connection| {
+            let tx = connection.transaction()?;
+            tx.execute(
+                "INSERT INTO pledges (serial_number, first_seen, last_updated) VALUES (?1, ?2, ?2)
+                 ON CONFLICT(serial_number) DO UPDATE SET last_updated = ?2",
+                params![serial_number, now],
+            )?;
+
+            match event {
+                PledgeEvent::PvrReceived => tx.execute(
+                    "UPDATE pledges SET pvr_received_at = ?2 WHERE serial_number = ?1",
+                    params![serial_number, now],
+                ),
+                PledgeEvent::RvrSent => tx.execute(
+                    "UPDATE pledges SET rvr_sent_at = ?2 WHERE serial_number = ?1",
+                    params![serial_number, now],
+                ),
+                PledgeEvent::VoucherReceived => tx.execute(
+                    "UPDATE pledges SET voucher_received_at = ?2 WHERE serial_number = ?1",
+                    params![serial_number, now],
+                ),
+                PledgeEvent::VoucherStatus { status, reason } => tx.execute(
+                    "UPDATE pledges SET voucher_status = ?2, voucher_status_reason = ?3,
+                     voucher_status_at = ?4 WHERE serial_number = ?1",
+                    params![serial_number, status, reason, now],
+                ),
+                PledgeEvent::LdevidIssued { serial } => tx.execute(
+                    "UPDATE pledges SET ldevid_serial = ?2, ldevid_issued_at = ?3
+                     WHERE serial_number = ?1",
+                    params![serial_number, serial, now],
+                ),
+                PledgeEvent::EnrollStatus { status, reason } => tx.execute(
+                    "UPDATE pledges SET enroll_status = ?2, enroll_status_reason = ?3,
+                     enroll_status_at = ?4 WHERE serial_number = ?1",
+                    params![serial_number, status, reason, now],
+                ),
+                PledgeEvent::PledgeStatus { status, reason } => tx.execute(
+                    "UPDATE pledges SET pledge_status = ?2, pledge_status_reason = ?3,
+                     pledge_status_at = ?4 WHERE serial_number = ?1",
+                    params![serial_number, status, reason, now],
+                ),
+            }?;
+
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn get_pledge(&self, serial_number: &str) -> anyhow::Result<Option<PledgeRecord>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    &format!("SELECT {} FROM pledges WHERE serial_number = ?1", COLUMNS),
+                    params![serial_number],
+                    pledge_record,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    async fn list_pledges(&self) -> anyhow::Result<Vec<PledgeRecord>> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM pledges ORDER BY first_seen",
+                COLUMNS
+            ))?;
+            let records = statement
+                .query_map([], pledge_record)?
+                .collect::<rusqlite::Result<Vec<_>>>();
+            records
+        })
+        .await
+    }
+
+    async fn request_approval(
+        &self,
+        serial_number: &str,
+        manufacturer: Option<String>,
+        masa_uri: Option<String>,
+        policy_reason: &str,
+    ) -> anyhow::Result<ApprovalRecord> {
+        let serial_number = serial_number.to_string();
+        let policy_reason = policy_reason.to_string();
+        let now = Utc::now();
+
+        self.with_connection(move |connection| {
+            let tx = connection.transaction()?;
+            tx.execute(
+                "INSERT INTO approvals (serial_number, manufacturer, masa_uri, policy_reason, status,
+                 requested_at, last_requested_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT(serial_number) DO UPDATE SET last_requested_at = ?6",
+                params![
+                    serial_number,
+                    manufacturer,
+                    masa_uri,
+                    policy_reason,
+                    approval_status_to_sql(ApprovalStatus::Pending),
+                    now
+                ],
+            )?;
+            let record = tx.query_row(
+                &format!(
+                    "SELECT {} FROM approvals WHERE serial_number = ?1",
+                    APPROVAL_COLUMNS
+                ),
+                params![serial_number],
+                approval_record,
+            )?;
+            tx.commit()?;
+            Ok(record)
+        })
+        .await
+    }
+
+    async fn decide_approval(
+        &self,
+        serial_number: &str,
+        approved: bool,
+        reason: Option<String>,
+    ) -> anyhow::Result<Option<ApprovalRecord>> {
+        let serial_number = serial_number.to_string();
+        let status = match approved {
+            true => ApprovalStatus::Approved,
+            false => ApprovalStatus::Rejected,
+        };
+        let now = Utc::now();
+
+        self.with_connection(move |connection| {
+            let tx = connection.transaction()?;
+            tx.execute(
+                "UPDATE approvals SET status = ?2, reason = ?3, decided_at = ?4
+                 WHERE serial_number = ?1",
+                params![serial_number, approval_status_to_sql(status), reason, now],
+            )?;
+            let record = tx
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM approvals WHERE serial_number = ?1",
+                        APPROVAL_COLUMNS
+                    ),
+                    params![serial_number],
+                    approval_record,
+                )
+                .optional()?;
+            tx.commit()?;
+            Ok(record)
+        })
+        .await
+    }
+
+    async fn list_approvals(&self) -> anyhow::Result<Vec<ApprovalRecord>> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM approvals ORDER BY requested_at",
+                APPROVAL_COLUMNS
+            ))?;
+            let records = statement
+                .query_map([], approval_record)?
+                .collect::<rusqlite::Result<Vec<_>>>();
+            records
+        })
+        .await
+    }
+
+    async fn record_voucher(&self, voucher: VoucherRecord) -> anyhow::Result<()> {
+        self.with_connection(move |connection| {
+            connection.execute(
+                &format!(
+                    "INSERT INTO vouchers ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    VOUCHER_COLUMNS
+                ),
+                params![
+                    voucher.serial_number,
+                    voucher.masa_url,
+                    voucher.content_type,
+                    voucher.voucher,
+                    voucher.created_on,
+                    voucher.expires_on,
+                    voucher.received_at
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn latest_voucher(&self, serial_number: &str) -> anyhow::Result<Option<VoucherRecord>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM vouchers WHERE serial_number = ?1 ORDER BY id DESC LIMIT 1",
+                        VOUCHER_COLUMNS
+                    ),
+                    params![serial_number],
+                    voucher_record,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    async fn release_pledge(&self, serial_number: &str) -> anyhow::Result<Option<PledgeRecord>> {
+        let serial_number = serial_number.to_string();
+
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+            let record = transaction
+                .query_row(
+                    &format!("SELECT {} FROM pledges WHERE serial_number = ?1", COLUMNS),
+                    params![serial_number],
+                    pledge_record,
+                )
+                .optional()?;
+            transaction.execute(
+                "DELETE FROM pledges WHERE serial_number = ?1",
+                params![serial_number],
+            )?;
+            transaction.execute(
+                "DELETE FROM approvals WHERE serial_number = ?1",
+                params![serial_number],
+            )?;
+            transaction.commit()?;
+            Ok(record)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_records_the_onboarding_of_a_pledge() {
+        let storage = SqliteStorage::in_memory().unwrap();
+
+        storage
+            .record_event("0123456789", PledgeEvent::PvrReceived)
+            .await
+            .unwrap();
+        storage
+            .record_event("0123456789", PledgeEvent::VoucherReceived)
+            .await
+            .unwrap();
+        storage
+            .record_event(
+                "0123456789",
+                PledgeEvent::LdevidIssued {
+                    serial: "0a1b".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        storage
+            .record_event(
+                "0123456789",
+                PledgeEvent::EnrollStatus {
+                    status: false,
+                    reason: Some("Failed to install LDevID".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        storage
+            .record_event(
+                "0123456789",
+                PledgeEvent::PledgeStatus {
+                    status: "enroll-error".to_string(),
+                    reason: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let record = storage.get_pledge("0123456789").await.unwrap().unwrap();
+        assert!(record.pvr_received_at.is_some());
+        assert!(record.rvr_sent_at.is_none());
+        assert!(record.voucher_received_at.is_some());
+        assert_eq!(record.ldevid_serial.as_deref(), Some("0a1b"));
+        assert_eq!(record.enroll_status, Some(false));
+        assert_eq!(
+            record.enroll_status_reason.as_deref(),
+            Some("Failed to install LDevID")
+        );
+        assert_eq!(record.pledge_status.as_deref(), Some("enroll-error"));
+        assert!(record.first_seen <= record.last_updated);
+
+        assert!(storage.get_pledge("9876543210").await.unwrap().is_none());
+        assert_eq!(storage.list_pledges().await.unwrap(), vec![record]);
+
+        let released = storage.release_pledge("0123456789").await.unwrap();
+        assert_eq!(released.unwrap().ldevid_serial.as_deref(), Some("0a1b"));
+        assert!(storage.get_pledge("0123456789").await.unwrap().is_none());
+        assert!(storage
+            .release_pledge("0123456789")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn it_queues_pledges_for_approval() {
+        let storage = SqliteStorage::in_memory().unwrap();
+
+        let record = storage
+            .request_approval("0123456789", Some("Vendor".to_string()), None, "New device")
+            .await
+            .unwrap();
+        assert_eq!(record.status, ApprovalStatus::Pending);
+
+        let decided = storage
+            .decide_approval("0123456789", false, Some("Unknown device type".to_string()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decided.status, ApprovalStatus::Rejected);
+        assert!(decided.decided_at.is_some());
+
+        // asking again keeps the operator's decision
+        let record = storage
+            .request_approval("0123456789", Some("Vendor".to_string()), None, "New device")
+            .await
+            .unwrap();
+        assert_eq!(record.status, ApprovalStatus::Rejected);
+        assert_eq!(record.reason.as_deref(), Some("Unknown device type"));
+
+        assert!(storage
+            .decide_approval("9876543210", true, None)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(storage.list_approvals().await.unwrap(), vec![record]);
+    }
+
+    #[tokio::test]
+    async fn it_keeps_every_voucher() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let voucher = |voucher: &[u8]| VoucherRecord {
+            serial_number: "0123456789".to_string(),
+            masa_url: "https://masa.example.com".to_string(),
+            content_type: "application/voucher-jws+json".to_string(),
+            voucher: voucher.to_vec(),
+            created_on: Some(Utc::now()),
+            expires_on: None,
+            received_at: Utc::now(),
+        };
+
+        storage.record_voucher(voucher(b"first")).await.unwrap();
+        storage.record_voucher(voucher(b"second")).await.unwrap();
+
+        let latest = storage.latest_voucher("0123456789").await.unwrap().unwrap();
+        assert_eq!(latest.voucher, b"second");
+        assert!(storage
+            .latest_voucher("9876543210")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn it_survives_a_restart() {
+        let path = std::env::temp_dir().join(format!(
+            "open-brski-registrar-{}.sqlite",
+            std::process::id()
+        ));
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        storage
+            .record_event(
+                "0123456789",
+                PledgeEvent::VoucherStatus {
+                    status: true,
+                    reason: None,
+                },
+            )
+            .await
+            .unwrap();
+        drop(storage);
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        let record = storage.get_pledge("0123456789").await.unwrap().unwrap();
+        assert_eq!(record.voucher_status, Some(true));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_migrates_unversioned_databases() {
+        let pledges = |extra_columns: &str| {
+            format!(
+                "CREATE TABLE pledges (
+                    serial_number TEXT PRIMARY KEY NOT NULL,
+                    first_seen TEXT NOT NULL,
+                    last_updated TEXT NOT NULL,
+                    pvr_received_at TEXT,
+                    rvr_sent_at TEXT,
+                    voucher_received_at TEXT,
+                    voucher_status INTEGER,
+                    voucher_status_reason TEXT,
+                    voucher_status_at TEXT,
+                    ldevid_serial TEXT,
+                    ldevid_issued_at TEXT,
+                    enroll_status INTEGER,
+                    enroll_status_reason TEXT,
+                    enroll_status_at TEXT{}
+                );
+                INSERT INTO pledges (serial_number, first_seen, last_updated, ldevid_serial)
+                VALUES ('0123456789', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z', '0a1b');",
+                extra_columns
+            )
+        };
+
+        // before approvals were added and after the pledge status columns were added
+        for schema in [
+            pledges(""),
+            pledges(", pledge_status TEXT, pledge_status_reason TEXT, pledge_status_at TEXT"),
+        ] {
+            let connection = Connection::open_in_memory().unwrap();
+            connection.execute_batch(&schema).unwrap();
+            let storage = SqliteStorage::from_connection(connection).unwrap();
+
+            storage
+                .record_event(
+                    "0123456789",
+                    PledgeEvent::PledgeStatus {
+                        status: "enroll-success".to_string(),
+                        reason: None,
+                    },
+                )
+                .await
+                .unwrap();
+            let record = storage.get_pledge("0123456789").await.unwrap().unwrap();
+            assert_eq!(record.ldevid_serial.as_deref(), Some("0a1b"));
+            assert_eq!(record.pledge_status.as_deref(), Some("enroll-success"));
+            assert!(storage.list_approvals().await.unwrap().is_empty());
+
+            let mut connection = storage.connection.lock().unwrap();
+            let version: usize = connection
+                .query_row("PRAGMA user_version", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(version, MIGRATIONS.len());
+            migrate(&mut connection).unwrap();
+        }
+    }
+}
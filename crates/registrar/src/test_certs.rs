@@ -0,0 +1,65 @@
+//! Throwaway certificates for unit tests
+
+use openssl::{
+    asn1::Asn1Time,
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    x509::{
+        extension::{BasicConstraints, SubjectKeyIdentifier},
+        X509NameBuilder, X509,
+    },
+};
+
+/// A self-signed CA certificate
+pub(crate) fn ca(name: &str) -> (X509, PKey<Private>) {
+    build(name, None, true)
+}
+
+/// An end-entity certificate issued by `issuer`, self-signed without one
+pub(crate) fn certificate(
+    name: &str,
+    issuer: Option<(&X509, &PKey<Private>)>,
+) -> (X509, PKey<Private>) {
+    build(name, issuer, false)
+}
+
+fn build(name: &str, issuer: Option<(&X509, &PKey<Private>)>, ca: bool) -> (X509, PKey<Private>) {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut subject = X509NameBuilder::new().unwrap();
+    subject.append_entry_by_text("CN", name).unwrap();
+    let subject = subject.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&subject).unwrap();
+    match issuer {
+        Some((issuer, _)) => builder.set_issuer_name(issuer.subject_name()),
+        None => builder.set_issuer_name(&subject),
+    }
+    .unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    if ca {
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+    }
+    let skid = SubjectKeyIdentifier::new()
+        .build(&builder.x509v3_context(None, None))
+        .unwrap();
+    builder.append_extension(skid).unwrap();
+    builder
+        .sign(issuer.map_or(&key, |(_, key)| key), MessageDigest::sha256())
+        .unwrap();
+
+    (builder.build(), key)
+}
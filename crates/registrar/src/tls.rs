@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum_server::tls_rustls::RustlsConfig;
+use openssl::{pkey::PKey, x509::X509};
+use reqwest::Client;
+use rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+
+use crate::parsed_config::ParsedConfig;
+
+/// Serves the registrar certificate and only accepts clients with a certificate issued by the agent CA
+pub(crate) fn server_config(config: &ParsedConfig) -> anyhow::Result<RustlsConfig> {
+    let agent_ca = config.agent_ca_certificate.as_ref().ok_or(anyhow!(
+        "TLS is enabled but no agent CA certificate was loaded"
+    ))?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add(&Certificate(agent_ca.to_der()?))?;
+
+    let chain = vec![
+        Certificate(config.registrar_certificate.to_der()?),
+        Certificate(config.ca_certificate.to_der()?),
+    ];
+
+    let mut server_config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        .with_single_cert(chain, PrivateKey(config.registrar_key.clone()))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(RustlsConfig::from_config(Arc::new(server_config)))
+}
+
+/// Builds a client used for a MASA, trusting `masa_ca` or the built-in roots if there is none.
+/// With `client_authentication` it authenticates with the registrar certificate, the same one signing the RVR,
+/// so the MASA can bind both.
+pub(crate) fn masa_client(
+    config: &ParsedConfig,
+    masa_ca: Option<&X509>,
+    client_authentication: bool,
+) -> anyhow::Result<Client> {
+    let mut builder = Client::builder().use_rustls_tls();
+
+    if client_authentication {
+        let mut identity = config.registrar_certificate.to_pem()?;
+        identity.extend(
+            PKey::private_key_from_pkcs8(&config.registrar_key)?.private_key_to_pem_pkcs8()?,
+        );
+        builder = builder.identity(reqwest::Identity::from_pem(&identity)?);
+    }
+
+    if let Some(masa_ca) = masa_ca {
+        builder = builder
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(reqwest::Certificate::from_der(&masa_ca.to_der()?)?);
+    }
+
+    Ok(builder.build()?)
+}
@@ -0,0 +1,304 @@
+use brski_prm_artifacts::ietf_voucher::{
+    artifact::VoucherArtifact, request_artifact::VoucherRequestArtifact,
+};
+use chrono::{DateTime, Duration, Utc};
+use common::{
+    agent_signed_data::ALLOWED_CLOCK_SKEW, server_error::ServerError,
+    util::verify_certificate_chain,
+};
+use openssl::{asn1::Asn1Time, x509::X509};
+use signeable_payload::{signeable::raw_signed::RawSigned, SignerVerifyer};
+use tracing::{event, Level};
+
+/// Why a voucher issued by the MASA is not relayed to the pledge
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum VoucherValidationError {
+    #[error("Voucher signature is invalid: {0}")]
+    InvalidSignature(String),
+
+    #[error("Voucher does not carry the MASA certificate")]
+    MissingSigner,
+
+    #[error("Voucher signer does not chain to the vendor trust anchor: {0}")]
+    UntrustedSigner(String),
+
+    #[error("Voucher is for serial number {voucher}, the RVR for {rvr}")]
+    SerialMismatch { voucher: String, rvr: String },
+
+    #[error("Voucher nonce does not match the RVR nonce")]
+    NonceMismatch,
+
+    #[error("Voucher does not contain pinned-domain-cert")]
+    MissingPinnedDomainCert,
+
+    #[error("Voucher pins a certificate that is not the certificate of this registrar")]
+    ForeignPinnedDomainCert,
+
+    #[error("Voucher was created on {0}, which is in the future")]
+    CreatedInFuture(DateTime<Utc>),
+
+    #[error("Voucher expired on {0}")]
+    Expired(DateTime<Utc>),
+
+    #[error("Voucher expires on {expires_on}, before it was created on {created_on}")]
+    ExpiresBeforeCreated {
+        created_on: DateTime<Utc>,
+        expires_on: DateTime<Utc>,
+    },
+
+    #[error("Voucher expires on {0}, after its pinned-domain-cert")]
+    OutlivesPinnedDomainCert(DateTime<Utc>),
+}
+
+impl From<VoucherValidationError> for ServerError {
+    fn from(value: VoucherValidationError) -> Self {
+        ServerError::BadResponse(value.to_string())
+    }
+}
+
+/// What a voucher from the MASA is checked against before the registrar countersigns it
+pub(crate) struct VoucherValidator<'a> {
+    /// CA the MASA signing certificate has to chain to
+    pub(crate) trust_anchor: &'a X509,
+    /// Certificates the voucher may pin, see [crate::proximity::ProximityVerifier]
+    pub(crate) registrar_certificates: [&'a X509; 2],
+    /// Accept a voucher without nonce for an RVR with nonce, as pre-issued vouchers predate the PVR
+    pub(crate) allow_nonceless: bool,
+}
+
+impl VoucherValidator<'_> {
+    /// Verifies the voucher was issued by a trusted MASA for the RVR and pins this registrar, returning its payload
+    pub(crate) fn validate(
+        &self,
+        voucher: RawSigned<VoucherArtifact>,
+        verifyer: Box<dyn SignerVerifyer<VoucherArtifact>>,
+        rvr: &VoucherRequestArtifact,
+        now: DateTime<Utc>,
+    ) -> Result<VoucherArtifact, VoucherValidationError> {
+        let verified = voucher
+            .into_verifyable_boxed(verifyer)
+            .verify(None)
+            .map_err(|e| VoucherValidationError::InvalidSignature(e.to_string()))?;
+
+        let chain = verified
+            .headers()
+            .x509_certificate_chain()
+            .filter(|chain| !chain.is_empty())
+            .ok_or(VoucherValidationError::MissingSigner)?;
+        verify_certificate_chain(self.trust_anchor, &chain)
+            .map_err(VoucherValidationError::UntrustedSigner)?;
+
+        let voucher = verified.payload().clone();
+        let details = &voucher.details;
+
+        if details.serial_number != rvr.details.serial_number {
+            return Err(VoucherValidationError::SerialMismatch {
+                voucher: details.serial_number.clone(),
+                rvr: rvr.details.serial_number.clone(),
+            });
+        }
+
+        let nonceless = self.allow_nonceless && details.nonce.is_none();
+        if !nonceless && details.nonce != rvr.details.nonce {
+            return Err(VoucherValidationError::NonceMismatch);
+        }
+
+        let pinned = details
+            .pinned_domain_cert
+            .as_ref()
+            .ok_or(VoucherValidationError::MissingPinnedDomainCert)?;
+        let pinned_der = pinned.to_der().ok();
+        if !self
+            .registrar_certificates
+            .iter()
+            .any(|certificate| certificate.to_der().ok() == pinned_der)
+        {
+            return Err(VoucherValidationError::ForeignPinnedDomainCert);
+        }
+
+        if let Some(created_on) = details.created_on {
+            if created_on > now + Duration::seconds(ALLOWED_CLOCK_SKEW) {
+                return Err(VoucherValidationError::CreatedInFuture(created_on));
+            }
+        }
+
+        if let Some(expires_on) = details.expires_on {
+            if expires_on <= now {
+                return Err(VoucherValidationError::Expired(expires_on));
+            }
+            if let Some(created_on) = details
+                .created_on
+                .filter(|created_on| expires_on <= *created_on)
+            {
+                return Err(VoucherValidationError::ExpiresBeforeCreated {
+                    created_on,
+                    expires_on,
+                });
+            }
+            let outlives_pinned = Asn1Time::from_unix(expires_on.timestamp())
+                .map_or(true, |expires_on| pinned.not_after() < expires_on);
+            if outlives_pinned {
+                return Err(VoucherValidationError::OutlivesPinnedDomainCert(expires_on));
+            }
+        }
+
+        event!(
+            Level::INFO,
+            "Validated voucher for {}",
+            details.serial_number
+        );
+        Ok(voucher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use brski_prm_artifacts::{
+        ietf_voucher::artifact::VoucherArtifactDetails, issued_voucher::IssuedVoucher,
+        token_type::VoucherTokenType,
+    };
+    use openssl::pkey::{PKey, Private};
+    use signeable_payload::{BasicSigningContext, DefaultSignerVerifyer, Unsigned};
+
+    use super::*;
+    use crate::test_certs::{ca, certificate};
+
+    fn voucher(
+        masa: &X509,
+        masa_key: &PKey<Private>,
+        details: VoucherArtifactDetails,
+    ) -> RawSigned<VoucherArtifact> {
+        let issued = IssuedVoucher::try_new(
+            VoucherArtifact { details },
+            [masa.to_der().unwrap()],
+            VoucherTokenType::JWS,
+        )
+        .unwrap();
+        let unsigned: Unsigned<VoucherArtifact> = issued.try_into().unwrap();
+
+        unsigned
+            .into_signeable(DefaultSignerVerifyer::default())
+            .sign(
+                masa_key.private_key_to_pkcs8().unwrap(),
+                BasicSigningContext::new(),
+            )
+            .unwrap()
+            .into_raw()
+    }
+
+    #[test]
+    fn it_validates_vouchers() {
+        let (vendor_ca, vendor_ca_key) = ca("vendor ca");
+        let (masa, masa_key) = certificate("masa", Some((&vendor_ca, &vendor_ca_key)));
+        let (rogue_masa, rogue_masa_key) = certificate("rogue masa", None);
+        let (registrar, _) = certificate("registrar", None);
+        let (registrar_ca, _) = certificate("registrar ca", None);
+        let (foreign, _) = certificate("foreign registrar", None);
+        let validator = VoucherValidator {
+            trust_anchor: &vendor_ca,
+            registrar_certificates: [&registrar, &registrar_ca],
+            allow_nonceless: false,
+        };
+
+        let mut rvr = VoucherRequestArtifact::default();
+        rvr.details.serial_number = "pledge-1".to_string();
+        rvr.details.nonce = Some(b"nonce".to_vec());
+
+        let details = VoucherArtifactDetails {
+            serial_number: "pledge-1".to_string(),
+            nonce: Some(b"nonce".to_vec()),
+            created_on: Some(Utc::now()),
+            pinned_domain_cert: Some(registrar_ca.clone().into()),
+            ..Default::default()
+        };
+        let validate = |voucher: RawSigned<VoucherArtifact>| {
+            validator.validate(
+                voucher,
+                Box::new(DefaultSignerVerifyer::default()),
+                &rvr,
+                Utc::now(),
+            )
+        };
+
+        assert!(validate(voucher(&masa, &masa_key, details.clone())).is_ok());
+
+        assert!(matches!(
+            validate(voucher(&rogue_masa, &rogue_masa_key, details.clone())),
+            Err(VoucherValidationError::UntrustedSigner(_))
+        ));
+        assert!(matches!(
+            validate(voucher(
+                &masa,
+                &masa_key,
+                VoucherArtifactDetails {
+                    serial_number: "pledge-2".to_string(),
+                    ..details.clone()
+                }
+            )),
+            Err(VoucherValidationError::SerialMismatch { .. })
+        ));
+        assert!(matches!(
+            validate(voucher(
+                &masa,
+                &masa_key,
+                VoucherArtifactDetails {
+                    nonce: None,
+                    ..details.clone()
+                }
+            )),
+            Err(VoucherValidationError::NonceMismatch)
+        ));
+        assert!(VoucherValidator {
+            allow_nonceless: true,
+            ..validator
+        }
+        .validate(
+            voucher(
+                &masa,
+                &masa_key,
+                VoucherArtifactDetails {
+                    nonce: None,
+                    expires_on: Some(Utc::now() + Duration::days(1)),
+                    ..details.clone()
+                }
+            ),
+            Box::new(DefaultSignerVerifyer::default()),
+            &rvr,
+            Utc::now(),
+        )
+        .is_ok());
+        assert!(matches!(
+            validate(voucher(
+                &masa,
+                &masa_key,
+                VoucherArtifactDetails {
+                    pinned_domain_cert: Some(foreign.into()),
+                    ..details.clone()
+                }
+            )),
+            Err(VoucherValidationError::ForeignPinnedDomainCert)
+        ));
+        assert!(matches!(
+            validate(voucher(
+                &masa,
+                &masa_key,
+                VoucherArtifactDetails {
+                    expires_on: Some(Utc::now() - Duration::hours(1)),
+                    ..details.clone()
+                }
+            )),
+            Err(VoucherValidationError::Expired(_))
+        ));
+        assert!(matches!(
+            validate(voucher(
+                &masa,
+                &masa_key,
+                VoucherArtifactDetails {
+                    expires_on: Some(Utc::now() + Duration::days(30)),
+                    ..details.clone()
+                }
+            )),
+            Err(VoucherValidationError::OutlivesPinnedDomainCert(_))
+        ));
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
     signeable::{
         signer_verifyer::{SignatureAdder, SignerVerifyer, VerifyResult},
         signing_context::{BasicSigningContext, SigningContext},
-        verifying_context::BasicVeryingContext,
+        verifying_context::{BasicVeryingContext, VerifyingContext},
     },
     signer_verifyer::MultipleSignerVerifyer,
 };
@@ -102,33 +102,38 @@ impl JoseSignerVerifyer {
 
         let cell: OnceCell<Box<dyn josekit::jws::JwsVerifier>> = OnceCell::new();
 
-        // TODO insert logic if x5c header is not present
+        // Without an x5c header the caller has to supply the signer's public key, e.g. from an agent-sign-cert
+        let ctx_pub_key = ctx.and_then(|ctx| ctx.get_public_key());
+
         let (data, header) = jws_context.deserialize_json_with_selector(data, |header| {
-            let cert_chain_raw =
-                header
-                    .x509_certificate_chain()
+            let pub_key = match header.x509_certificate_chain() {
+                Some(cert_chain_raw) => {
+                    let cert_chain_end = openssl::x509::X509::from_der(&cert_chain_raw[0].clone())
+                        .map_err(|_| {
+                            josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
+                                "Could not parse x509 end of certificate chain"
+                            ))
+                        })?;
+                    cert_chain_end
+                        .public_key()
+                        .map_err(|_| {
+                            josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
+                                "Could not get public key from certificate"
+                            ))
+                        })?
+                        .public_key_to_der()
+                        .map_err(|_| {
+                            josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
+                                "Could not serialize public key to DER"
+                            ))
+                        })?
+                }
+                None => ctx_pub_key
+                    .clone()
                     .ok_or(josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
-                        "Could not get x509 certificate chain"
-                    )))?;
-            let cert_chain_end = openssl::x509::X509::from_der(&cert_chain_raw[0].clone())
-                .map_err(|_| {
-                    josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
-                        "Could not parse x509 end of certificate chain"
-                    ))
-                })?;
-            let pub_key = cert_chain_end
-                .public_key()
-                .map_err(|_| {
-                    josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
-                        "Could not get public key from certificate"
-                    ))
-                })?
-                .public_key_to_der()
-                .map_err(|_| {
-                    josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!(
-                        "Could not serialize public key to DER"
-                    ))
-                })?;
+                        "Could not get x509 certificate chain and no public key was provided"
+                    )))?,
+            };
 
             let verifier = self.get_jws_verifier(&pub_key, header)?.ok_or(
                 josekit::JoseError::InvalidJwsFormat(anyhow::anyhow!("Could not get verifier")),
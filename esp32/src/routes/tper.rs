@@ -14,7 +14,7 @@ use signeable_payload::{BasicSigningContext, Signed, Unsigned};
 
 use crate::{
     ble_async::UUIDS,
-    CREDENTIALS,
+    CREDENTIALS, PLEDGE_INFO,
 };
 
 pub fn handle_tper(data: Vec<u8>) -> anyhow::Result<Signed<PledgeEnrollRequest>> {
@@ -29,6 +29,7 @@ pub fn handle_tper(data: Vec<u8>) -> anyhow::Result<Signed<PledgeEnrollRequest>>
         signature_type: brski_prm_artifacts::token_type::PlainTokenType::COSE,
         pledge_idevid_key: CREDENTIALS.private_key.to_vec(),
         pledge_idevid_chain: vec![cert],
+        protocol_version: PLEDGE_INFO.protocol_version,
     };
 
     let transformed = transform_per(args)?;
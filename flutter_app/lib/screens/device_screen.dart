@@ -128,7 +128,8 @@ class _DeviceScreenState extends State<DeviceScreen> {
       var package = await getCharacteristicPackage(_services);
       var bootsrapper = await getPledgeFFIBootstrapper(package);
       await bootsrapper.bootstrap(serialNumber: "abcdefg");
-      Snackbar.show(ABC.c, "Discover Services: Success", success: true);
+      var status = await bootsrapper.queryStatus(serialNumber: "abcdefg");
+      Snackbar.show(ABC.c, "Discover Services: Success, pledge status: $status", success: true);
     } catch (e) {
       log(e.toString());
       Snackbar.show(ABC.c, prettyException("Discover Services Error:", e), success: false);
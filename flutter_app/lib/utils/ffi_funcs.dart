@@ -4,7 +4,7 @@ import 'package:flutter_app/src/rust/api/identifiers.dart';
 import 'package:flutter_app/src/rust/api/config.dart';
 import 'package:flutter/services.dart';
 import 'package:flutter_blue_plus/flutter_blue_plus.dart';
-import 'dart:convert' show utf8;
+import 'dart:typed_data';
 
 import 'dart:math';
 // split write should be used with caution.
@@ -39,19 +39,25 @@ class CharacteristicPackage {
   //CharacteristicPair voucher;
   //CharacteristicPair caCerts;
   //CharacteristicPair enrollResponse;
+  CharacteristicPair pledgeStatus;
 
-  CharacteristicPackage(this.tpvr);
+  CharacteristicPackage(this.tpvr, this.pledgeStatus);
+}
+
+CharacteristicPair getCharacteristicPair(List<BluetoothService> services, Identifier identifier) {
+  BluetoothService service = services.firstWhere((el) => el.uuid.toString() == identifier.uuid);
+  BluetoothCharacteristic read = service.characteristics.firstWhere((el) => el.uuid.toString() == identifier.readUuid);
+  BluetoothCharacteristic write = service.characteristics.firstWhere((el) => el.uuid.toString() == identifier.writeUuid);
+
+  return CharacteristicPair(read, write);
 }
 
 Future<CharacteristicPackage> getCharacteristicPackage(List<BluetoothService> services) async {
   BleIdentifiers identifiers = await getIdentifiers();
-  
-  BluetoothService tpvrService = services.firstWhere((el) => el.uuid.toString() == identifiers.tpvr.uuid);
-  BluetoothCharacteristic tpvrRead = tpvrService.characteristics.firstWhere((el) => el.uuid.toString() == identifiers.tpvr.readUuid);
-  BluetoothCharacteristic tpvrWrite = tpvrService.characteristics.firstWhere((el) => el.uuid.toString() == identifiers.tpvr.writeUuid);
 
-  var pair = CharacteristicPair(tpvrRead, tpvrWrite);
-  return CharacteristicPackage(pair);
+  var tpvr = getCharacteristicPair(services, identifiers.tpvr);
+  var pledgeStatus = getCharacteristicPair(services, identifiers.pledgeStatus);
+  return CharacteristicPackage(tpvr, pledgeStatus);
 }
 
 Future<ParsedConfig> getParsedConfig() async {
@@ -66,10 +72,8 @@ Future<ParsedConfig> getParsedConfig() async {
   return parsedConfig;
 }
 
-Future<String> characteristicCallback(CharacteristicPair pair, String payload) async {
-  List<int> encoded = utf8.encode(payload);
-
-  await pair.write.splitWrite(encoded);
+Future<Uint8List> characteristicCallback(CharacteristicPair pair, List<int> payload) async {
+  await pair.write.splitWrite(payload);
 
   List<int> buf = [];
   while (true) {
@@ -79,8 +83,7 @@ Future<String> characteristicCallback(CharacteristicPair pair, String payload) a
     }
     buf.addAll(ret);
   }
-  var decoded = utf8.decode(buf);
-  return decoded;
+  return Uint8List.fromList(buf);
 }
 
 Future<Bootstrapper> getPledgeFFIBootstrapper(CharacteristicPackage cpackage) async {
@@ -90,6 +93,7 @@ Future<Bootstrapper> getPledgeFFIBootstrapper(CharacteristicPackage cpackage) as
   builder = await builder.setVoucherFfi(callback: (trigger, ctx) => "Hallo");
   builder = await builder.setCaCertsFfi(callback: (trigger, ctx) => "Hallo");
   builder = await builder.setEnrollResponseFfi(callback: (trigger, ctx) => "Hallo");
+  builder = await builder.setPledgeStatusFfi(callback: (query, ctx) => characteristicCallback(cpackage.pledgeStatus, query));
 
   var communicator = await builder.build();
 